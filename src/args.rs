@@ -2,37 +2,478 @@
 //!
 //! This module defines the command line arguments for the solana-copy-trade-detect application.
 
-use std::path::PathBuf;
+use std::{fmt, net::SocketAddr, path::PathBuf};
 
-use clap::Parser;
+use clap::{builder::RangedU64ValueParser, ArgAction, Parser, Subcommand, ValueEnum};
 use solana_sdk::pubkey::Pubkey;
 
+use crate::RangeBound;
+
 /// Command line arguments for the solana-copy-trade-detect application.
 #[derive(Parser, Debug)]
-#[command(version, about)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    /// The subcommand to run. A single wallet is scanned if none is given.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// The scan parameters, present when scanning a single wallet.
+    #[command(flatten)]
+    pub scan: Option<ScanArgs>,
+    /// The wallet to scan.
+    #[arg(short, long, required = true)]
+    pub wallet: Option<Pubkey>,
+    /// Output file to write detected wallets. Default is wallet_address with the format extension.
+    #[arg(short, long)]
+    pub output_file: Option<PathBuf>,
+    /// Output format: text, json, jsonl, csv or html.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, hide_possible_values = true)]
+    pub format: OutputFormat,
+    /// Where to write the output: file, stdout or both. Default is stdout for json, jsonl and csv.
+    #[arg(long, value_enum, value_name = "DEST", hide_possible_values = true)]
+    pub output: Option<OutputDestination>,
+    /// Do not print progress or status messages, only the output and errors.
+    #[arg(
+        short,
+        long,
+        overrides_with = "no_quiet",
+        default_value_if("no_quiet", "false", "false")
+    )]
+    pub quiet: bool,
+    /// Print progress and status messages, even if quiet is set in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "quiet")]
+    pub no_quiet: bool,
+    /// Format of error messages on stderr: text or json.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text, hide_possible_values = true)]
+    pub error_format: ErrorFormat,
+    /// Configuration file. Default is copytrade.toml if it exists.
+    #[arg(long, global = true, env = "COPYTRADE_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Profile of the configuration file to use.
+    #[arg(long, global = true, env = "COPYTRADE_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+    /// The webhook targets receiving the report.
+    #[command(flatten)]
+    pub webhook: WebhookArgs,
+}
+
+/// Subcommands of the solana-copy-trade-detect application.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan many wallets read from a file or stdin and highlight leaders shared between them.
+    Batch(BatchArgs),
+    /// Inspect the configuration file and profiles.
+    Config(ConfigArgs),
+    /// Compare two scans of a wallet and report new, dropped and changed leaders.
+    Diff(DiffArgs),
+    /// Aggregate saved scan outputs into a matrix of scanned wallets and the leaders behind them.
+    Overlap(OverlapArgs),
+    /// Serve a REST API to submit scans, poll their progress and fetch their results.
+    Serve(ServeArgs),
+    /// Print the JSON Schema of an output format.
+    Schema(SchemaArgs),
+    /// Keep polling wallets and alert when a leader crosses a copy count or score threshold.
+    Watch(WatchArgs),
+}
+
+/// Output formats of a scan.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text.
+    Text,
+    /// A single JSON report.
+    Json,
+    /// One JSON object per detected wallet and line.
+    Jsonl,
+    /// One CSV row per detected wallet and copied swap.
+    Csv,
+    /// A self-contained HTML report.
+    Html,
+}
+
+impl OutputFormat {
+    /// Returns the file extension of the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Destinations of the output of a scan.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputDestination {
+    /// The output file.
+    File,
+    /// The standard output.
+    Stdout,
+    /// Both the output file and the standard output.
+    Both,
+}
+
+/// Formats of error messages.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Human-readable text.
+    Text,
+    /// A JSON object per error.
+    Json,
+}
+
+/// Parameters shared by every scan.
+///
+/// The parameters serialize without the API keys, RPC URL and database, so that they can be
+/// recorded with a scan.
+#[derive(clap::Args, Debug, Clone, serde::Serialize)]
+pub struct ScanArgs {
     /// Cielo API key.
     #[arg(short, long, env = "CIELO_API_KEY")]
+    #[serde(skip)]
     pub cielo_api_key: String,
     /// Shyft API key.
     #[arg(short, long, env = "SHYFT_API_KEY")]
+    #[serde(skip)]
     pub shyft_api_key: String,
     /// Solana RPC URL. The Shyft RPC endpoint is used by default if not provided.
     #[arg(short, long, env = "RPC_URL")]
+    #[serde(skip)]
     pub rpc_url: Option<String>,
-    /// The wallet to scan.
-    #[arg(short, long)]
-    pub wallet: Pubkey,
-    /// Number of recent fresh swaps from the wallet to consider (max 100).
+    /// Number of recent fresh swaps from the wallet to consider (max 100). Ignored with a range.
     #[arg(long, default_value = "15", value_parser = clap::value_parser!(u32).range(1..=100))]
     pub swap_num: u32,
+    /// Only analyse swaps from this time or slot on: a Unix timestamp, a UTC date or slot:<SLOT>.
+    #[arg(long, value_name = "TIME|SLOT")]
+    pub since: Option<RangeBound>,
+    /// Only analyse swaps up to this time or slot: a Unix timestamp, a UTC date or slot:<SLOT>.
+    #[arg(long, value_name = "TIME|SLOT")]
+    pub until: Option<RangeBound>,
+    /// Maximum number of pages of 1000 signatures to page back through with --since or --until.
+    #[arg(long, default_value = "10", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_range_pages: u32,
     /// Number of transactions to scan for each swap to detect repeated wallets (max 100).
     #[arg(long, default_value = "50", value_parser = clap::value_parser!(u32).range(1..=100))]
     pub scan_tx_count: u32,
+    /// Scan every transaction up to this many slots before each swap instead of --scan-tx-count.
+    #[arg(long, value_name = "SLOTS", conflicts_with = "lookback_secs")]
+    pub lookback_slots: Option<u64>,
+    /// Scan every transaction up to this many seconds before each swap instead of --scan-tx-count.
+    #[arg(long, value_name = "SECS")]
+    pub lookback_secs: Option<u64>,
+    /// Maximum number of transactions to scan for each swap with a lookback window.
+    #[arg(long, default_value = "1000", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_scan_tx: u32,
+    /// Only analyse swaps on tokens created at most this many seconds before the swap.
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(..=i64::MAX as u64))]
+    pub max_token_age: Option<u64>,
     /// Delay between Shyft API requests in milliseconds.
     #[arg(short, long, default_value = "500")]
     pub delay_ms: u64,
-    /// Output file to write detected wallets. Default is wallet_address.txt.
+    /// Check same-slot buyers for sandwich (MEV) patterns, fetching two full blocks per swap.
+    #[arg(
+        long,
+        overrides_with = "no_mev_check",
+        default_value_if("no_mev_check", "false", "false")
+    )]
+    pub mev_check: bool,
+    /// Do not check for MEV patterns, even if enabled in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "mev_check")]
+    #[serde(skip)]
+    pub no_mev_check: bool,
+    /// Trace the SOL funding of detected wallets and cluster wallets sharing a funding source.
+    #[arg(
+        long,
+        overrides_with = "no_cluster_funding",
+        default_value_if("no_cluster_funding", "false", "false")
+    )]
+    pub cluster_funding: bool,
+    /// Do not cluster detected wallets, even if enabled in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "cluster_funding")]
+    #[serde(skip)]
+    pub no_cluster_funding: bool,
+    /// Reconstruct the trades of detected wallets on the scanned tokens and report their PnL.
+    #[arg(
+        long,
+        overrides_with = "no_leader_pnl",
+        default_value_if("no_leader_pnl", "false", "false")
+    )]
+    pub leader_pnl: bool,
+    /// Do not report the PnL of detected wallets, even if enabled in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "leader_pnl")]
+    #[serde(skip)]
+    pub no_leader_pnl: bool,
+    /// Compare how the wallet and each detected wallet exited the tokens they both bought.
+    #[arg(
+        long,
+        overrides_with = "no_compare_outcomes",
+        default_value_if("no_compare_outcomes", "false", "false")
+    )]
+    pub compare_outcomes: bool,
+    /// Do not compare the exits of the tokens, even if enabled in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "compare_outcomes")]
+    #[serde(skip)]
+    pub no_compare_outcomes: bool,
+    /// Annotate copied buys with the age, launch platform and migration of the bought token.
+    #[arg(
+        long,
+        overrides_with = "no_token_launch",
+        default_value_if("no_token_launch", "false", "false")
+    )]
+    pub token_launch: bool,
+    /// Do not annotate the bought tokens, even if enabled in the configuration file.
+    #[arg(long, action = ArgAction::SetFalse, overrides_with = "token_launch")]
+    #[serde(skip)]
+    pub no_token_launch: bool,
+    /// SQLite database recording every scan, its swaps, previous buys and detected wallets.
+    #[arg(long, env = "COPYTRADE_DB", value_name = "FILE")]
+    #[serde(skip)]
+    pub db: Option<PathBuf>,
+}
+
+/// Arguments of the `batch` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct BatchArgs {
+    /// The scan parameters applied to every wallet.
+    #[command(flatten)]
+    pub scan: ScanArgs,
+    /// File with the wallets to scan, one per line. Wallets are read from stdin if not provided or "-".
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+    /// Directory to write the combined report and per-wallet outputs to.
+    #[arg(short, long, default_value = "batch")]
+    pub output_dir: PathBuf,
+    /// The format and destination of the combined report.
+    #[command(flatten)]
+    pub report: ReportArgs,
+    /// The webhook targets receiving the combined report.
+    #[command(flatten)]
+    pub webhook: WebhookArgs,
+}
+
+/// Arguments of the `overlap` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct OverlapArgs {
+    /// Saved JSON outputs to aggregate: batch reports or `<wallet>.json` files of single scans.
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+    /// Minimum number of scanned wallets a leader must be detected behind to be listed.
+    #[arg(long, default_value = "2")]
+    pub min_targets: usize,
+    /// Output file to write the overlap matrix to. Default is overlap with the format extension.
+    #[arg(short, long)]
+    pub output_file: Option<PathBuf>,
+    /// The format and destination of the overlap matrix.
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+/// Arguments of the `diff` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// The older scan: a saved JSON output, or the id of a scan stored in the database.
+    #[arg(required_unless_present = "wallet", requires = "new")]
+    pub old: Option<String>,
+    /// The newer scan: a saved JSON output, or the id of a scan stored in the database.
+    pub new: Option<String>,
+    /// Compare the two latest stored scans of this wallet, or select it in batch reports.
+    #[arg(short, long)]
+    pub wallet: Option<String>,
+    /// SQLite database to load stored scans from.
+    #[arg(long, env = "COPYTRADE_DB", value_name = "FILE")]
+    pub db: Option<PathBuf>,
+    /// Minimum change in copy count to report a leader as changed.
+    #[arg(long, default_value = "2", value_name = "N")]
+    pub min_copy_change: usize,
+    /// Minimum change in average lag in slots to report a leader as changed.
+    #[arg(long, default_value = "1", value_name = "SLOTS")]
+    pub min_lag_change: f64,
+    /// Output file to write the diff to. Default is diff with the format extension.
     #[arg(short, long)]
     pub output_file: Option<PathBuf>,
+    /// The format and destination of the diff.
+    #[command(flatten)]
+    pub report: ReportArgs,
+}
+
+/// Output options of the subcommands writing a report.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReportArgs {
+    /// Output format: text or json.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, hide_possible_values = true)]
+    pub format: OutputFormat,
+    /// Where to write the output: file, stdout or both. Default is both for batch, otherwise the output file if given and stdout if not.
+    #[arg(long, value_enum, value_name = "DEST", hide_possible_values = true)]
+    pub output: Option<OutputDestination>,
+    /// Format of error messages on stderr: text or json.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text, hide_possible_values = true)]
+    pub error_format: ErrorFormat,
+}
+
+/// Arguments of the `config` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    /// The config action to run.
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// Actions of the `config` subcommand.
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the effective configuration and where every setting comes from.
+    Show {
+        /// Print API keys and tokens instead of masking them.
+        #[arg(long)]
+        show_secrets: bool,
+    },
+}
+
+/// Arguments of the `schema` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct SchemaArgs {
+    /// The output format to describe.
+    #[arg(value_enum, default_value_t = OutputFormat::Json)]
+    pub format: OutputFormat,
+}
+
+/// Arguments of the `serve` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// The default scan parameters of submitted scans.
+    #[command(flatten)]
+    pub scan: ScanArgs,
+    /// Address to listen on.
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    pub bind: SocketAddr,
+    /// Maximum number of scans running at the same time.
+    #[arg(long, default_value = "2", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_concurrent: usize,
+    /// Maximum number of scans waiting to run. Further submissions are rejected.
+    #[arg(long, default_value = "100", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_queued: usize,
+    /// The webhook targets receiving the report of every finished scan.
+    #[command(flatten)]
+    pub webhook: WebhookArgs,
+}
+
+/// Arguments of the `watch` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct WatchArgs {
+    /// The scan parameters applied to every poll.
+    #[command(flatten)]
+    pub scan: ScanArgs,
+    /// Wallets to watch. Can be repeated.
+    #[arg(short, long = "wallet", required_unless_present = "input")]
+    pub wallets: Vec<Pubkey>,
+    /// File with more wallets to watch, one per line, or "-" for stdin.
+    #[arg(short, long)]
+    pub input: Option<PathBuf>,
+    /// Seconds to wait between polls.
+    #[arg(long, default_value = "60")]
+    pub interval_secs: u64,
+    /// Alert when a leader has preceded this many of a wallet's swaps.
+    #[arg(long, default_value = "3", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub alert_copies: usize,
+    /// Alert when a leader has preceded at least this fraction (0-1) of a wallet's swaps.
+    #[arg(long, value_parser = parse_fraction)]
+    pub alert_score: Option<f64>,
+    /// Serve Prometheus metrics on /metrics at this address.
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_bind: Option<SocketAddr>,
+    /// Output format of the events and statistics: text, or json and jsonl for JSON lines.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, hide_possible_values = true)]
+    pub format: OutputFormat,
+    /// Format of error messages on stderr: text or json.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text, hide_possible_values = true)]
+    pub error_format: ErrorFormat,
+    /// The webhook targets receiving alerts.
+    #[command(flatten)]
+    pub webhook: WebhookArgs,
+    /// Solana PubSub websocket URL. Wallets are polled as soon as they transact if provided.
+    #[arg(long, env = "WS_URL")]
+    pub ws_url: Option<String>,
+    /// Yellowstone gRPC endpoint. Wallets are polled as soon as they transact and recent mint
+    /// history is taken from the stream instead of the RPC if provided.
+    #[cfg(feature = "yellowstone")]
+    #[arg(long, env = "GRPC_URL", conflicts_with = "ws_url")]
+    pub grpc_url: Option<String>,
+    /// Access token of the Yellowstone gRPC endpoint.
+    #[cfg(feature = "yellowstone")]
+    #[arg(long, env = "GRPC_X_TOKEN")]
+    pub grpc_x_token: Option<String>,
+}
+
+/// Webhook targets receiving completed reports and alerts.
+#[derive(clap::Args, Debug, Clone)]
+pub struct WebhookArgs {
+    /// URL receiving reports and alerts as JSON. Can be repeated.
+    #[arg(long = "webhook", value_name = "URL")]
+    pub webhooks: Vec<String>,
+    /// Secret signing JSON webhook payloads (HMAC-SHA256 in X-Signature-256).
+    #[arg(
+        long,
+        env = "WEBHOOK_SECRET",
+        hide_env_values = true,
+        value_name = "SECRET"
+    )]
+    pub webhook_secret: Option<String>,
+    /// Discord webhook URL receiving reports and alerts. Can be repeated.
+    #[arg(long = "discord-webhook", value_name = "URL")]
+    pub discord_webhooks: Vec<String>,
+    /// Telegram bot token sending reports and alerts.
+    #[arg(
+        long,
+        env = "TELEGRAM_BOT_TOKEN",
+        hide_env_values = true,
+        value_name = "TOKEN",
+        requires = "telegram_chat_id"
+    )]
+    pub telegram_bot_token: Option<String>,
+    /// Telegram chat id receiving reports and alerts.
+    #[arg(
+        long,
+        env = "TELEGRAM_CHAT_ID",
+        requires = "telegram_bot_token",
+        value_name = "CHAT_ID"
+    )]
+    pub telegram_chat_id: Option<String>,
+    /// Number of retries of a failed webhook delivery.
+    #[arg(long, default_value = "3", value_name = "N")]
+    pub webhook_retries: u32,
+    /// File to append failed webhook deliveries to.
+    #[arg(long, default_value = "webhooks.failed.jsonl", value_name = "FILE")]
+    pub dead_letter_file: PathBuf,
+}
+
+/// Parses a fraction between 0 and 1.
+///
+/// # Arguments
+///
+/// * `value` - The fraction to parse.
+///
+/// # Errors
+///
+/// This function will return an error if the value is not a number between 0 and 1.
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    let fraction: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(format!("{fraction} is not between 0 and 1"));
+    }
+    Ok(fraction)
 }
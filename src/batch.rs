@@ -0,0 +1,137 @@
+//! # Batch Scanning
+//!
+//! This module scans many wallets in one run and highlights the leaders shared between them.
+//! The shared leaders are reported as an [`OverlapReport`].
+//!
+//! All wallets are scanned with a single [`Scanner`], so API clients, caches and request delays
+//! are shared between scans. A failing wallet is recorded in the report instead of aborting the
+//! whole batch.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufRead, BufReader, IsTerminal},
+    path::Path,
+    str::FromStr,
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{print_if_terminal, BatchArgs, OverlapReport, RepeatingWallet, Scanner};
+
+/// Minimum number of scanned wallets a leader must be detected behind to be reported as shared.
+const MIN_SHARED_TARGETS: usize = 2;
+
+/// Represents the result of scanning one wallet in a batch.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TargetScan {
+    /// The scanned wallet address.
+    pub wallet: String,
+    /// The detected wallets, empty if the scan failed.
+    pub leaders: Vec<RepeatingWallet>,
+    /// The error message if the scan failed.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Represents the combined report of a batch scan.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BatchReport {
+    /// The results per scanned wallet, in input order.
+    pub targets: Vec<TargetScan>,
+    /// The leaders detected behind more than one scanned wallet.
+    pub overlap: OverlapReport,
+}
+
+/// Reads the wallets to scan from a file or stdin.
+///
+/// Every line holds one wallet; for spreadsheet exports only the first comma, semicolon or
+/// whitespace separated column is used. Empty lines, `#` comments and entries that are not valid
+/// wallet addresses (e.g. a header row) are skipped. Duplicates are removed, keeping the first
+/// occurrence.
+///
+/// # Arguments
+///
+/// * `input` - The file to read, or `None` or `-` to read from stdin.
+///
+/// # Errors
+///
+/// This function will return an error if the input cannot be read.
+pub fn read_wallets(input: Option<&Path>) -> Result<Vec<Pubkey>, io::Error> {
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) if path != Path::new("-") => Box::new(BufReader::new(File::open(path)?)),
+        _ => Box::new(io::stdin().lock()),
+    };
+
+    let mut seen = HashSet::new();
+    let mut wallets = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let entry = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .trim_matches('"');
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+
+        match Pubkey::from_str(entry) {
+            Ok(wallet) => {
+                if seen.insert(wallet) {
+                    wallets.push(wallet);
+                }
+            }
+            Err(_) => tracing::warn!("Skipping invalid wallet {:?} on line {}", entry, index + 1),
+        }
+    }
+
+    Ok(wallets)
+}
+
+/// Scans every wallet of a batch.
+///
+/// # Arguments
+///
+/// * `args` - The batch arguments containing the scan parameters.
+/// * `wallets` - The wallets to scan.
+///
+/// # Errors
+///
+/// This function will return an error if the API clients could not be built. Errors while scanning
+/// a single wallet are recorded in its [`TargetScan`] instead.
+pub async fn run_batch(args: &BatchArgs, wallets: &[Pubkey]) -> Result<BatchReport, crate::Error> {
+    let mut scanner = Scanner::new(&args.scan)?;
+    let mut targets = Vec::with_capacity(wallets.len());
+
+    for (index, wallet) in wallets.iter().enumerate() {
+        print_if_terminal!(
+            "{} Scanning wallet {}",
+            console::style(format!("[{}/{}]", index + 1, wallets.len()))
+                .bold()
+                .cyan(),
+            wallet
+        );
+
+        let target = match scanner.scan(wallet).await {
+            Ok(leaders) => TargetScan {
+                wallet: wallet.to_string(),
+                leaders,
+                error: None,
+            },
+            Err(e) => {
+                tracing::error!("Failed to scan {}: {}", wallet, e);
+                print_if_terminal!("{}", console::style(format!("Error: {}", e)).red());
+                TargetScan {
+                    wallet: wallet.to_string(),
+                    leaders: Vec::new(),
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        targets.push(target);
+    }
+
+    let overlap = crate::overlap(&targets, MIN_SHARED_TARGETS);
+    Ok(BatchReport { targets, overlap })
+}
@@ -0,0 +1,319 @@
+//! # Funding Clusters
+//!
+//! This module traces where candidate wallets get their SOL from and clusters wallets that share
+//! a funding source.
+//!
+//! Sophisticated traders split their activity across many wallets, which would otherwise show up
+//! as unrelated candidates. Both the initial funding transfers at the start of a wallet's history
+//! and recurring top-ups in its most recent transactions are taken into account.
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+    UiParsedInstruction, UiTransactionEncoding,
+};
+
+use crate::{Funding, RepeatingWallet, WalletCluster};
+
+/// Maximum number of signature pages (1000 signatures each) to walk back through when looking
+/// for the start of a wallet's history.
+const MAX_HISTORY_PAGES: usize = 3;
+
+/// Number of the oldest transactions inspected for the initial funding.
+const INITIAL_FUNDING_TX_COUNT: usize = 3;
+
+/// Number of the most recent transactions inspected for top-ups.
+const TOP_UP_TX_COUNT: usize = 10;
+
+/// Transfers below this amount are ignored, as they are usually dust or address poisoning spam.
+const MIN_FUNDING_LAMPORTS: u64 = 10_000_000;
+
+/// Well-known exchange hot wallets. They fund countless unrelated wallets, so sharing one of
+/// them as a funding source says nothing about common ownership.
+const EXCHANGE_WALLETS: [&str; 6] = [
+    "5tzFkiKscXHK5ZXCGbXZxdw7gTjjD1mBwuoFbhUvuAi9", // Binance
+    "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM", // Binance 2
+    "H8sMJSCQxfKiFTCfDR3DUMLPwcRbM61LGFJ8N4dK3WjS", // Coinbase
+    "2AQdpHJ2JpcEgPiATUXjQxA8QmafFegfQwSLWSprPicm", // Coinbase 2
+    "5VCwKtCXgCJ6kit5FybXjvriW3xELsFDhYrPSqtJNmcD", // OKX
+    "AC5RDfQFmDS1deWZos921JfqscXdByf8BKHs5ACWjtW2", // Bybit
+];
+
+/// Fetches the SOL transfers that funded a wallet.
+///
+/// This function walks back through the wallet's signatures to find the start of its history,
+/// then fetches its oldest and most recent transactions and extracts incoming SOL transfers.
+/// Transfers are only marked as initial funding if the start of the history was reached.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `wallet` - The wallet address to trace.
+/// * `delay_ms` - The delay in milliseconds between requests.
+///
+/// # Errors
+///
+/// This function will return an error if the Solana RPC request fails.
+pub(crate) async fn fetch_funding(
+    rpc_client: &RpcClient,
+    wallet: &str,
+    delay_ms: u64,
+) -> Result<Vec<Funding>, solana_client::client_error::ClientError> {
+    let Ok(address) = Pubkey::from_str(wallet) else {
+        tracing::warn!(
+            "Skipping funding trace for invalid wallet address {}",
+            wallet
+        );
+        return Ok(Vec::new());
+    };
+
+    // Signatures are returned newest first
+    let mut signatures = Vec::new();
+    let mut before = None;
+    let mut reached_start = false;
+    for _ in 0..MAX_HISTORY_PAGES {
+        let page = crate::metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_signatures_for_address_with_config(
+                    &address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: None,
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await,
+        )?;
+        crate::metrics::rate_limit_wait(delay_ms).await;
+
+        reached_start = page.len() < 1000;
+        before = page
+            .last()
+            .and_then(|s| Signature::from_str(&s.signature).ok());
+        signatures.extend(
+            page.into_iter()
+                .filter(|s| s.err.is_none())
+                .map(|s| s.signature),
+        );
+
+        if reached_start || before.is_none() {
+            break;
+        }
+    }
+
+    tracing::debug!(
+        "Fetched {} signatures for {} (start reached: {})",
+        signatures.len(),
+        wallet,
+        reached_start
+    );
+
+    let oldest_start = signatures.len().saturating_sub(INITIAL_FUNDING_TX_COUNT);
+    let mut funding = Vec::new();
+    for (index, signature) in signatures.iter().enumerate() {
+        let initial = index >= oldest_start;
+        if !initial && index >= TOP_UP_TX_COUNT {
+            continue;
+        }
+        let Ok(signature) = Signature::from_str(signature) else {
+            continue;
+        };
+
+        let tx = crate::metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_transaction_with_config(
+                    &signature,
+                    RpcTransactionConfig {
+                        encoding: Some(UiTransactionEncoding::JsonParsed),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                        max_supported_transaction_version: Some(0),
+                    },
+                )
+                .await,
+        )?;
+        crate::metrics::rate_limit_wait(delay_ms).await;
+
+        funding.extend(
+            incoming_transfers(&tx, wallet)
+                .into_iter()
+                .map(|(source, lamports)| Funding {
+                    source,
+                    lamports,
+                    tx_hash: signature.to_string(),
+                    initial: initial && reached_start,
+                }),
+        );
+    }
+
+    Ok(funding)
+}
+
+/// Extracts the SOL transfers into the wallet from a parsed transaction.
+///
+/// Both top-level and inner system program transfers are considered. Transfers from the wallet
+/// itself, dust transfers and transfers from exchange hot wallets are skipped.
+///
+/// # Returns
+///
+/// A vector of source addresses and the transferred lamports.
+fn incoming_transfers(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    wallet: &str,
+) -> Vec<(String, u64)> {
+    let outer = match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => match &ui_tx.message {
+            UiMessage::Parsed(message) => message.instructions.clone(),
+            UiMessage::Raw(_) => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+    let inner = tx
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|m| Option::<&Vec<_>>::from(m.inner_instructions.as_ref()))
+        .into_iter()
+        .flatten()
+        .flat_map(|inner| inner.instructions.clone());
+
+    outer
+        .into_iter()
+        .chain(inner)
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(instruction))
+                if instruction.program == "system" =>
+            {
+                Some(instruction.parsed)
+            }
+            _ => None,
+        })
+        .filter(|parsed| {
+            matches!(
+                parsed["type"].as_str(),
+                Some("transfer" | "transferWithSeed" | "createAccount")
+            )
+        })
+        .filter_map(|parsed| {
+            let info = &parsed["info"];
+            let destination = info["destination"]
+                .as_str()
+                .or_else(|| info["newAccount"].as_str())?;
+            let source = info["source"].as_str()?;
+            let lamports = info["lamports"].as_u64()?;
+            (destination == wallet
+                && source != wallet
+                && lamports >= MIN_FUNDING_LAMPORTS
+                && !EXCHANGE_WALLETS.contains(&source))
+            .then(|| (source.to_owned(), lamports))
+        })
+        .collect()
+}
+
+/// Assigns cluster ids to wallets that share a funding source.
+///
+/// Wallets are linked if they were funded by the same address or if one of them funded the
+/// other. Only groups of at least two wallets form a cluster; the cluster id of every other
+/// wallet is left as `None`.
+///
+/// # Arguments
+///
+/// * `wallets` - The repeating wallets with their traced funding.
+pub(crate) fn assign_clusters(wallets: &mut [RepeatingWallet]) {
+    // Link each wallet to its funders, and funders back to the wallets they funded
+    let mut links: HashMap<&str, Vec<&str>> = HashMap::new();
+    for wallet in wallets.iter() {
+        for funding in wallet.funding.iter() {
+            links
+                .entry(wallet.wallet.as_str())
+                .or_default()
+                .push(funding.source.as_str());
+            links
+                .entry(funding.source.as_str())
+                .or_default()
+                .push(wallet.wallet.as_str());
+        }
+    }
+
+    let candidates: HashSet<&str> = wallets.iter().map(|w| w.wallet.as_str()).collect();
+    let mut cluster_of: HashMap<String, usize> = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut next_id = 0;
+
+    for wallet in wallets.iter() {
+        if !visited.insert(wallet.wallet.as_str()) {
+            continue;
+        }
+
+        // Walk the connected component of this wallet
+        let mut members = vec![wallet.wallet.as_str()];
+        let mut stack = vec![wallet.wallet.as_str()];
+        while let Some(node) = stack.pop() {
+            for &linked in links.get(node).into_iter().flatten() {
+                if visited.insert(linked) {
+                    if candidates.contains(linked) {
+                        members.push(linked);
+                    }
+                    stack.push(linked);
+                }
+            }
+        }
+
+        if members.len() > 1 {
+            for member in members {
+                cluster_of.insert(member.to_owned(), next_id);
+            }
+            next_id += 1;
+        }
+    }
+
+    for wallet in wallets.iter_mut() {
+        wallet.cluster = cluster_of.get(&wallet.wallet).copied();
+    }
+}
+
+/// Groups the clustered wallets into one entity per cluster.
+///
+/// # Arguments
+///
+/// * `wallets` - The repeating wallets with their assigned cluster ids.
+///
+/// # Returns
+///
+/// A vector of clusters sorted by their aggregated copy count in descending order.
+pub fn clusters(wallets: &[RepeatingWallet]) -> Vec<WalletCluster> {
+    let mut clusters: HashMap<usize, WalletCluster> = HashMap::new();
+    for wallet in wallets.iter() {
+        let Some(id) = wallet.cluster else {
+            continue;
+        };
+        let cluster = clusters.entry(id).or_insert_with(|| WalletCluster {
+            id,
+            wallets: Vec::new(),
+            funders: Vec::new(),
+            copy_count: 0,
+        });
+        cluster.wallets.push(wallet.wallet.to_owned());
+        cluster.copy_count += wallet.txs.len();
+        for funding in wallet.funding.iter() {
+            if !cluster.funders.contains(&funding.source) {
+                cluster.funders.push(funding.source.to_owned());
+            }
+        }
+    }
+
+    let mut clusters: Vec<_> = clusters.into_values().collect();
+    clusters.sort_by(|a, b| b.copy_count.cmp(&a.copy_count).then(a.id.cmp(&b.id)));
+    clusters
+}
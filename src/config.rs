@@ -0,0 +1,454 @@
+//! # Configuration File
+//!
+//! This module loads settings from a TOML configuration file, `copytrade.toml` by default, so that
+//! API keys, rate limits and thresholds do not have to be repeated on every run.
+//!
+//! The file holds base settings grouped in sections, and named profiles overriding them:
+//!
+//! ```toml
+//! # The profile used unless --profile is given
+//! profile = "fast"
+//!
+//! [providers]
+//! cielo_api_key = "..."
+//! shyft_api_key = "..."
+//!
+//! [profiles.fast.thresholds]
+//! swap_num = 5
+//! scan_tx_count = 20
+//!
+//! [profiles.paid-rpc.providers]
+//! rpc_url = "https://mainnet.helius-rpc.com/?api-key=..."
+//!
+//! [profiles.paid-rpc.rate_limits]
+//! delay_ms = 50
+//! ```
+//!
+//! Every setting is named after the command line option it configures, with underscores instead
+//! of dashes. The settings become the defaults of the options, so the precedence is, from lowest
+//! to highest: built-in defaults, base settings, profile settings, environment variables and
+//! command line options.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{CommandFactory, FromArgMatches};
+
+use crate::{Args, ScanArgs};
+
+/// The configuration file loaded if `--config` is not given and it exists.
+pub const DEFAULT_CONFIG_FILE: &str = "copytrade.toml";
+
+/// The settings of every section, named after the options they configure.
+const SECTIONS: [(&str, &[&str]); 5] = [
+    (
+        "providers",
+        &[
+            "cielo_api_key",
+            "shyft_api_key",
+            "rpc_url",
+            "ws_url",
+            "grpc_url",
+            "grpc_x_token",
+        ],
+    ),
+    (
+        "rate_limits",
+        &[
+            "delay_ms",
+            "interval_secs",
+            "max_concurrent",
+            "max_queued",
+            "webhook_retries",
+        ],
+    ),
+    (
+        "thresholds",
+        &[
+            "swap_num",
+            "max_range_pages",
+            "scan_tx_count",
+            "lookback_slots",
+            "lookback_secs",
+            "max_scan_tx",
+            "max_token_age",
+            "mev_check",
+            "cluster_funding",
+            "leader_pnl",
+            "compare_outcomes",
+            "token_launch",
+            "alert_copies",
+            "alert_score",
+            "min_targets",
+            "min_copy_change",
+            "min_lag_change",
+        ],
+    ),
+    (
+        "output",
+        &[
+            "format",
+            "output",
+            "quiet",
+            "error_format",
+            "output_dir",
+            "db",
+            "dead_letter_file",
+        ],
+    ),
+    (
+        "webhooks",
+        &[
+            "webhooks",
+            "webhook_secret",
+            "discord_webhooks",
+            "telegram_bot_token",
+            "telegram_chat_id",
+        ],
+    ),
+];
+
+/// The settings masked by `config show` unless secrets are requested.
+const SECRETS: [&str; 5] = [
+    "cielo_api_key",
+    "shyft_api_key",
+    "grpc_x_token",
+    "webhook_secret",
+    "telegram_bot_token",
+];
+
+/// Represents a loaded configuration file with the selected profile.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// The loaded file, if any.
+    pub path: Option<PathBuf>,
+    /// The selected profile, if any.
+    pub profile: Option<String>,
+    /// The base settings by section.
+    base: toml::Table,
+    /// The settings of the selected profile by section.
+    overrides: toml::Table,
+}
+
+impl Config {
+    /// Loads a configuration file and selects a profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The configuration file. If not given, [`DEFAULT_CONFIG_FILE`] is loaded if it
+    ///   exists.
+    /// * `profile` - The profile to select. If not given, the profile named by the `profile`
+    ///   setting of the file is selected, if any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file cannot be read, is not valid TOML, holds
+    /// unknown settings or values of the wrong type, or the profile does not exist.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, crate::Error> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => PathBuf::from(DEFAULT_CONFIG_FILE),
+            None => {
+                return match profile {
+                    Some(profile) => Err(crate::Error::UnknownProfile(profile.to_owned())),
+                    None => Ok(Self::default()),
+                }
+            }
+        };
+        let invalid = |message: String| crate::Error::InvalidConfig(path.clone(), message);
+
+        let mut base: toml::Table = fs::read_to_string(&path)?
+            .parse()
+            .map_err(|e: toml::de::Error| invalid(e.message().to_owned()))?;
+        let default_profile = match base.remove("profile") {
+            Some(toml::Value::String(profile)) => Some(profile),
+            Some(_) => return Err(invalid("profile must be a string".to_owned())),
+            None => None,
+        };
+        let mut profiles = match base.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => return Err(invalid("profiles must be a table".to_owned())),
+            None => toml::Table::new(),
+        };
+
+        validate(&base).map_err(&invalid)?;
+        for (name, settings) in profiles.iter() {
+            let toml::Value::Table(settings) = settings else {
+                return Err(invalid(format!("profile {} must be a table", name)));
+            };
+            validate(settings).map_err(|e| invalid(format!("profile {}: {}", name, e)))?;
+        }
+
+        let profile = profile.map(str::to_owned).or(default_profile);
+        let overrides = match profile.as_deref() {
+            Some(name) => match profiles.remove(name) {
+                Some(toml::Value::Table(settings)) => settings,
+                _ => return Err(crate::Error::UnknownProfile(name.to_owned())),
+            },
+            None => toml::Table::new(),
+        };
+
+        tracing::debug!(
+            "Loaded config {} with profile {:?}",
+            path.display(),
+            profile
+        );
+        Ok(Self {
+            path: Some(path),
+            profile,
+            base,
+            overrides,
+        })
+    }
+
+    /// Sets the configured settings as the defaults of the options they configure.
+    ///
+    /// The settings apply to every subcommand with the option, except `schema`, whose format
+    /// argument is not an output setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command line definition.
+    pub fn apply(&self, command: clap::Command) -> clap::Command {
+        SECTIONS
+            .iter()
+            .flat_map(|(section, keys)| keys.iter().map(move |key| (*section, *key)))
+            .filter_map(|(section, key)| {
+                self.lookup(section, key)
+                    .map(|(value, _)| (key, values(&value)))
+            })
+            .fold(command, |command, (key, values)| {
+                set_default(command, key, &values)
+            })
+    }
+
+    /// Renders the effective settings as TOML, with the source of every setting as a comment.
+    ///
+    /// Command line options are not taken into account.
+    ///
+    /// # Arguments
+    ///
+    /// * `show_secrets` - Whether to print API keys and tokens instead of masking them.
+    pub fn show(&self, show_secrets: bool) -> String {
+        let command = Args::command();
+        let mut rendered = format!(
+            "# Config file: {}\n# Profile: {}\n",
+            self.path
+                .as_deref()
+                .map_or("none".into(), |p| p.display().to_string()),
+            self.profile.as_deref().unwrap_or("none")
+        );
+
+        for (section, keys) in SECTIONS.iter() {
+            let mut lines = String::new();
+            for key in keys.iter() {
+                let Some(arg) = find_arg(&command, key) else {
+                    continue;
+                };
+                let env = arg
+                    .get_env()
+                    .and_then(|name| Some((name.to_str()?, std::env::var(name).ok()?)))
+                    .filter(|(_, value)| !value.is_empty());
+
+                let (value, source) = match (env, self.lookup(section, key)) {
+                    (Some((name, value)), _) => (parse_value(&value), format!("env {}", name)),
+                    (None, Some((value, source))) => (value, source),
+                    (None, None) => match arg.get_default_values() {
+                        [] => continue,
+                        [value] => (parse_value(&value.to_string_lossy()), "default".to_owned()),
+                        values => (
+                            toml::Value::Array(
+                                values
+                                    .iter()
+                                    .map(|v| parse_value(&v.to_string_lossy()))
+                                    .collect(),
+                            ),
+                            "default".to_owned(),
+                        ),
+                    },
+                };
+                let value = if !show_secrets && SECRETS.contains(key) {
+                    toml::Value::String("***".to_owned())
+                } else {
+                    value
+                };
+                lines.push_str(&format!("{} = {} # {}\n", key, value, source));
+            }
+            if !lines.is_empty() {
+                rendered.push_str(&format!("\n[{}]\n{}", section, lines));
+            }
+        }
+        rendered
+    }
+
+    /// Returns the configured value of a setting and its source, the profile taking precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `section` - The section of the setting.
+    /// * `key` - The setting.
+    fn lookup(&self, section: &str, key: &str) -> Option<(toml::Value, String)> {
+        let get = |settings: &toml::Table| settings.get(section)?.get(key).cloned();
+        match (get(&self.overrides), self.profile.as_deref()) {
+            (Some(value), Some(profile)) => Some((value, format!("profile {}", profile))),
+            _ => get(&self.base).map(|value| (value, "config file".to_owned())),
+        }
+    }
+}
+
+/// Parses the command line arguments with the configured settings as defaults.
+///
+/// The configuration file and profile are taken from `--config` and `--profile` or their
+/// environment variables. On invalid arguments, the usage is printed and the process exits.
+///
+/// # Errors
+///
+/// This function will return an error if the configuration file cannot be loaded.
+pub fn parse_args() -> Result<Args, crate::Error> {
+    // Find the configuration file before the required options can be checked
+    let (path, profile) = Args::command()
+        .ignore_errors(true)
+        .try_get_matches()
+        .map(|matches| {
+            (
+                matches.get_one::<PathBuf>("config").cloned(),
+                matches.get_one::<String>("profile").cloned(),
+            )
+        })
+        .unwrap_or_default();
+    let config = Config::load(path.as_deref(), profile.as_deref())?;
+
+    let matches = config.apply(Args::command()).get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // The scan parameters are only parsed if one of them is given on the command line or in the
+    // environment, not if all of them come from the configuration file
+    if args.command.is_none() && args.scan.is_none() {
+        args.scan = Some(ScanArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()));
+    }
+    Ok(args)
+}
+
+/// Checks that a table of settings only holds known sections and settings with plain values.
+///
+/// # Arguments
+///
+/// * `settings` - The settings by section.
+///
+/// # Errors
+///
+/// This function will return a message describing the first invalid setting.
+fn validate(settings: &toml::Table) -> Result<(), String> {
+    for (section, values) in settings.iter() {
+        let Some((_, keys)) = SECTIONS.iter().find(|(name, _)| name == section) else {
+            return Err(format!("unknown section {}", section));
+        };
+        let toml::Value::Table(values) = values else {
+            return Err(format!("{} must be a table", section));
+        };
+        for (key, value) in values.iter() {
+            if !keys.contains(&key.as_str()) {
+                return Err(format!("unknown setting {}.{}", section, key));
+            }
+            let plain = |value: &toml::Value| {
+                matches!(
+                    value,
+                    toml::Value::String(_)
+                        | toml::Value::Integer(_)
+                        | toml::Value::Float(_)
+                        | toml::Value::Boolean(_)
+                )
+            };
+            let valid = match value {
+                toml::Value::Array(items) => items.iter().all(plain),
+                value => plain(value),
+            };
+            if !valid {
+                return Err(format!(
+                    "{}.{} must be a string, number, boolean or an array of them",
+                    section, key
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the option values of a setting.
+///
+/// # Arguments
+///
+/// * `value` - The setting value, an array for options taking several values.
+fn values(value: &toml::Value) -> Vec<String> {
+    match value {
+        toml::Value::String(value) => vec![value.to_owned()],
+        toml::Value::Array(items) => items.iter().flat_map(values).collect(),
+        value => vec![value.to_string()],
+    }
+}
+
+/// Parses an option value given as a string into the matching TOML value.
+///
+/// # Arguments
+///
+/// * `value` - The option value.
+fn parse_value(value: &str) -> toml::Value {
+    if let Ok(value) = value.parse::<i64>() {
+        toml::Value::Integer(value)
+    } else if let Ok(value) = value.parse::<f64>() {
+        toml::Value::Float(value)
+    } else if let Ok(value) = value.parse::<bool>() {
+        toml::Value::Boolean(value)
+    } else {
+        toml::Value::String(value.to_owned())
+    }
+}
+
+/// Sets the default values of an option in a command and its subcommands.
+///
+/// Options with a default are no longer required, and secrets are left out of the help.
+///
+/// # Arguments
+///
+/// * `command` - The command.
+/// * `id` - The option id.
+/// * `values` - The default values.
+fn set_default(command: clap::Command, id: &str, values: &[String]) -> clap::Command {
+    let command = if command.get_name() != "schema"
+        && command.get_arguments().any(|arg| arg.get_id() == id)
+    {
+        command.mut_arg(id, |arg| {
+            arg.default_values(values.iter().cloned())
+                .hide_default_value(SECRETS.contains(&id))
+                .required(false)
+        })
+    } else {
+        command
+    };
+
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_owned())
+        .collect();
+    subcommands.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| set_default(subcommand, id, values))
+    })
+}
+
+/// Finds an option in a command or its subcommands.
+///
+/// # Arguments
+///
+/// * `command` - The command.
+/// * `id` - The option id.
+fn find_arg<'a>(command: &'a clap::Command, id: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_id() == id)
+        .or_else(|| {
+            command
+                .get_subcommands()
+                .find_map(|subcommand| find_arg(subcommand, id))
+        })
+}
@@ -2,16 +2,21 @@
 //!
 //! This module contains the core functionality for the solana-copy-trade-detect application.
 //!
-//! The main function in this module is `run`, which orchestrates the fetching and processing
+//! The main type in this module is `Scanner`, which orchestrates the fetching and processing
 //! of fresh swap transactions and their previous buy transactions to detect copy trading wallets.
 
-use std::{collections::HashMap, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use cielo_rs_sdk::{
     api::feed::{Filters, TxType},
     models, CieloApi,
 };
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use shyft_rs_sdk::{
     models::parsed_transaction_details::{self, ParsedTransactionDetails},
     ShyftApi,
@@ -23,279 +28,1257 @@ use solana_client::{
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::UiTransactionEncoding;
 
-use crate::{error::PrevBuysFetchError, get_spinner, PrevBuy, RepeatingWallet};
+use crate::{
+    cluster,
+    error::PrevBuysFetchError,
+    get_spinner, metrics,
+    mev::{self, FrontRun},
+    pnl,
+    range::{self, RangeBound},
+    store::ScanRecord,
+    token::{self, LaunchLookup, TokenLaunch, TokenMetadata},
+    Funding, PrevBuy, RepeatingWallet, Sandwich, ScanArgs, Store,
+};
 
-/// Runs the main logic of the solana-copy-trade-detect application.
+/// Runs the main logic of the solana-copy-trade-detect application for a single wallet.
 ///
-/// This function fetches fresh swap transactions and their previous buy transactions,
-/// then filters and retains only wallets with more than one repeating previous buy.
+/// This is a convenience wrapper that builds a [`Scanner`] and scans one wallet with it.
 ///
 /// # Arguments
 ///
-/// * `args` - The command line arguments containing API keys, wallet address, and other parameters.
+/// * `args` - The scan parameters containing API keys and other parameters.
+/// * `wallet` - The wallet to scan.
 ///
 /// # Errors
 ///
-/// This function will return an error if fetching fresh swaps or previous buys fails.
-pub async fn run(args: &crate::Args) -> Result<Vec<RepeatingWallet>, crate::Error> {
-    let mut prev_wallets = HashMap::new();
-
-    let spinner = get_spinner!(format!(
-        "{} {}Fetching fresh swaps...",
-        console::style("[1/3]").bold().dim(),
-        crate::LIGHTNING,
-    ));
-    let fresh_swaps = fetch_fresh_swaps(args).await?;
-    spinner.finish();
-
-    tracing::info!("Fetched {} fresh swaps", fresh_swaps.len());
-
-    if fresh_swaps.is_empty() {
-        eprintln!(
-            "\n{}",
-            console::style("Error: Cielo API returned no swaps for the given wallet.")
-                .red()
-                .bold()
+/// This function will return an error if building the API clients, fetching fresh swaps or
+/// previous buys fails.
+pub async fn run(args: &ScanArgs, wallet: &Pubkey) -> Result<Vec<RepeatingWallet>, crate::Error> {
+    Scanner::new(args)?.scan(wallet).await
+}
+
+/// The outcome of analysing a single fresh swap.
+#[derive(Debug, Default)]
+pub struct SwapAnalysis {
+    /// The buys that preceded the swap, with the wallet that made them.
+    pub prev_buys: Vec<(String, PrevBuy)>,
+    /// The sandwiches around the swap, with the wallet that executed them.
+    pub sandwiches: Vec<(String, Sandwich)>,
+}
+
+/// The swaps and previous buys observed during a scan, recorded in the store.
+#[derive(Default)]
+struct Observed {
+    /// The analysed swaps of the scanned wallet.
+    swaps: Vec<models::feed::Swap>,
+    /// The previous buys with the hash of the swap they preceded and the buyer.
+    prev_buys: Vec<(String, String, PrevBuy)>,
+}
+
+/// A callback receiving the number of analysed swaps and the total number of swaps of a scan.
+type ProgressFn = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+/// A callback receiving a copied buy of a repeating wallet with the wallet that made it.
+type FoundFn = Box<dyn Fn(&str, &PrevBuy) + Send + Sync>;
+
+/// Scans wallets for copy trading.
+///
+/// The API clients and caches are shared between scans, so that scanning many wallets with the
+/// same scanner avoids refetching transactions and funding traces seen in earlier scans.
+pub struct Scanner {
+    /// The scan parameters.
+    args: ScanArgs,
+    /// The Cielo API client.
+    cielo_api: CieloApi,
+    /// The Shyft API client.
+    shyft_api: ShyftApi,
+    /// The Solana RPC client.
+    rpc_client: RpcClient,
+    /// Parsed transactions by signature, or `None` for transactions that are not buys.
+    parsed_txs: HashMap<String, Option<ParsedTransactionDetails>>,
+    /// Parsed transactions of the scanned wallets' swaps by signature, whatever they traded, or
+    /// `None` for swaps that Shyft did not return.
+    swap_txs: HashMap<String, Option<ParsedTransactionDetails>>,
+    /// Traced funding by wallet address.
+    funding: HashMap<String, Vec<Funding>>,
+    /// Token metadata by mint address.
+    tokens: HashMap<String, TokenMetadata>,
+    /// Token launches by mint address, for the tokens whose launch was found.
+    launches: HashMap<String, TokenLaunch>,
+    /// The callback reporting the scan progress instead of progress bars, if any.
+    progress: Option<ProgressFn>,
+    /// The callback receiving the copied buys of repeating wallets as they are found, if any.
+    found: Option<FoundFn>,
+    /// The store recording every scan, if a database is configured.
+    store: Option<Arc<Store>>,
+    /// The mint history recorded from a stream, if any.
+    mint_history: Option<crate::MintHistory>,
+}
+
+impl Scanner {
+    /// Creates a new scanner with the given scan parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The scan parameters containing API keys and other parameters.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Cielo or Shyft API client could not be built, or
+    /// the database could not be opened.
+    pub fn new(args: &ScanArgs) -> Result<Self, crate::Error> {
+        let cielo_api = CieloApi::new(&args.cielo_api_key, None, None, None)?;
+        let shyft_api = ShyftApi::new(&args.shyft_api_key, None, None, None, None, None)?;
+
+        // Use Shyft RPC if no RPC URL is provided
+        let rpc_url = args
+            .rpc_url
+            .as_ref()
+            .unwrap_or(&format!(
+                "https://rpc.shyft.to/?api_key={}",
+                args.shyft_api_key
+            ))
+            .to_owned();
+        let rpc_client = RpcClient::new_with_timeout(rpc_url, Duration::from_secs(10));
+
+        Ok(Self {
+            args: args.clone(),
+            cielo_api,
+            shyft_api,
+            rpc_client,
+            parsed_txs: HashMap::new(),
+            swap_txs: HashMap::new(),
+            funding: HashMap::new(),
+            tokens: HashMap::new(),
+            launches: HashMap::new(),
+            progress: None,
+            found: None,
+            store: args
+                .db
+                .as_deref()
+                .map(Store::open)
+                .transpose()?
+                .map(Arc::new),
+            mint_history: None,
+        })
+    }
+
+    /// Reports the scan progress to a callback instead of drawing progress bars.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - Called with the number of analysed swaps and the total number of swaps.
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(usize, usize) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Reports the copied buys of repeating wallets as soon as their swap is analysed.
+    ///
+    /// A wallet is reported once it preceded two swaps, with both buys, and then with every
+    /// further buy. The detected wallets are still returned complete once the scan finishes.
+    ///
+    /// # Arguments
+    ///
+    /// * `found` - Called with the address of the wallet and its buy.
+    pub fn with_found(mut self, found: impl Fn(&str, &PrevBuy) + Send + Sync + 'static) -> Self {
+        self.found = Some(Box::new(found));
+        self
+    }
+
+    /// Uses a mint history recorded from a stream to find previous buys.
+    ///
+    /// Swaps covered by the history no longer page `getSignaturesForAddress` on the RPC.
+    ///
+    /// # Arguments
+    ///
+    /// * `history` - The mint history.
+    pub fn with_mint_history(mut self, history: crate::MintHistory) -> Self {
+        self.mint_history = Some(history);
+        self
+    }
+
+    /// Scans a wallet for copy trading.
+    ///
+    /// This function fetches fresh swap transactions and their previous buy transactions,
+    /// then filters and retains only wallets with more than one repeating previous buy.
+    /// Wallets caught sandwiching the fresh swaps are flagged as MEV and sorted last. If enabled,
+    /// the funding of the remaining wallets is traced and wallets sharing a funding source are
+    /// clustered; wallets whose funding cannot be traced are logged and left unclustered. The
+    /// trades of wallets whose history cannot be fetched are logged and their PnL left empty, and
+    /// if the wallet's own trades cannot be fetched, no outcomes are compared. If a database is
+    /// configured, the scan is recorded in it, even if it failed; a failure to record it is
+    /// logged without failing the scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The wallet to scan.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if Cielo returns no fresh swaps for the wallet, or if
+    /// fetching fresh swaps or previous buys fails.
+    pub async fn scan(&mut self, wallet: &Pubkey) -> Result<Vec<RepeatingWallet>, crate::Error> {
+        let started_at = now();
+        let started = Instant::now();
+        let mut observed = Observed::default();
+        let result = self.detect(wallet, &mut observed).await;
+        metrics::scan_duration("scan", started.elapsed());
+
+        if let Some(store) = self.store.clone() {
+            let record = ScanRecord {
+                wallet: wallet.to_string(),
+                args: self.args.clone(),
+                started_at,
+                finished_at: now(),
+                swaps: observed.swaps,
+                prev_buys: observed.prev_buys,
+                result: result.as_ref().cloned().map_err(ToString::to_string),
+            };
+            // rusqlite blocks, so keep it off the async workers
+            match tokio::task::spawn_blocking(move || store.record_scan(&record)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::error!("Failed to record the scan of {}: {}", wallet, e),
+                Err(e) => tracing::error!("Failed to record the scan of {}: {}", wallet, e),
+            }
+        }
+        result
+    }
+
+    /// Detects the wallets copied by a wallet, collecting the observed swaps and previous buys.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The wallet to scan.
+    /// * `observed` - Collects the analysed swaps and previous buys if a store is configured.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if Cielo returns no fresh swaps for the wallet, or if
+    /// fetching fresh swaps or previous buys fails.
+    async fn detect(
+        &mut self,
+        wallet: &Pubkey,
+        observed: &mut Observed,
+    ) -> Result<Vec<RepeatingWallet>, crate::Error> {
+        let args = self.args.clone();
+        let mut prev_wallets = HashMap::new();
+        let mut sandwiches = HashMap::new();
+
+        let spinner = get_spinner!(format!(
+            "{} {}Fetching fresh swaps...",
+            console::style("[1/3]").bold().dim(),
+            crate::LIGHTNING,
+        ));
+        self.hide_if_reporting(&spinner);
+        let fresh_swaps = self.fetch_swaps(wallet).await?;
+        // The pools are only needed to page signatures on the RPC
+        if self.mint_history.is_none() || self.lookback().is_some() {
+            let hashes: Vec<_> = fresh_swaps.iter().map(|s| s.tx_hash.to_owned()).collect();
+            self.fetch_swap_txs(&hashes).await?;
+        }
+        spinner.finish();
+
+        tracing::info!("Fetched {} fresh swaps", fresh_swaps.len());
+
+        if fresh_swaps.is_empty() {
+            return Err(crate::Error::NoFreshSwaps(*wallet));
+        }
+
+        let progress_bar = ProgressBar::new(fresh_swaps.len() as u64);
+        progress_bar.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} {msg} [{wide_bar:.green/magenta}] {percent}%",
+            )
+            .unwrap(),
         );
-        eprintln!("This may happen if the wallet is not on your watchlist or is a bot wallet that Cielo does not support. Please check the wallet page on Cielo.");
-        eprintln!("https://app.cielo.finance/profile/{}\n", args.wallet);
-        eprintln!("Exiting...");
-        std::process::exit(1);
+        progress_bar.enable_steady_tick(Duration::from_millis(120));
+        progress_bar.set_message(format!(
+            "{} {}Scanning transaction history...",
+            console::style("[2/3]").bold().dim(),
+            crate::SCAN,
+        ));
+        self.hide_if_reporting(&progress_bar);
+        if let Some(progress) = self.progress.as_ref() {
+            progress(0, fresh_swaps.len());
+        }
+
+        for (index, swap) in fresh_swaps.iter().enumerate() {
+            let analysis = match self.analyse_swap(swap).await {
+                Err(e) if e.is_malformed_data() => {
+                    tracing::warn!("Skipping swap {}: {}", swap.tx_hash, e);
+                    SwapAnalysis::default()
+                }
+                result => result?,
+            };
+            if self.store.is_some() {
+                observed.swaps.push(swap.clone());
+                observed
+                    .prev_buys
+                    .extend(analysis.prev_buys.iter().map(|(wallet, buy)| {
+                        (swap.tx_hash.to_owned(), wallet.to_owned(), buy.clone())
+                    }));
+            }
+            for (wallet, buy) in analysis.prev_buys {
+                let buys = prev_wallets.entry(wallet.clone()).or_insert_with(Vec::new);
+                buys.push(buy);
+                if let Some(found) = self.found.as_ref() {
+                    // The first buy is reported along with the second, which makes the wallet repeat
+                    let new = match buys.len() {
+                        1 => &[][..],
+                        2 => &buys[..],
+                        n => &buys[n - 1..],
+                    };
+                    for buy in new {
+                        found(&wallet, buy);
+                    }
+                }
+            }
+            for (wallet, sandwich) in analysis.sandwiches {
+                sandwiches
+                    .entry(wallet)
+                    .or_insert_with(Vec::new)
+                    .push(sandwich);
+            }
+            progress_bar.inc(1);
+            if let Some(progress) = self.progress.as_ref() {
+                progress(index + 1, fresh_swaps.len());
+            }
+        }
+
+        progress_bar.finish();
+
+        // Retain only wallets with more than one repeating previous buy
+        prev_wallets.retain(|_, buys| buys.len() > 1);
+
+        // Sort the repeating wallets by the number of previous buys in descending order,
+        // keeping MEV wallets after the potential leaders
+        let mut repeating_wallets_vec: Vec<_> = prev_wallets
+            .into_iter()
+            .map(|(wallet, buys)| {
+                let sandwiches = sandwiches.remove(&wallet).unwrap_or_default();
+                let sizing = crate::size_correlation(&buys);
+                RepeatingWallet {
+                    wallet,
+                    txs: buys,
+                    sandwiches,
+                    funding: Vec::new(),
+                    cluster: None,
+                    pnl: None,
+                    outcomes: None,
+                    sizing,
+                }
+            })
+            .collect();
+        repeating_wallets_vec.sort_by(|a, b| {
+            a.is_mev()
+                .cmp(&b.is_mev())
+                .then(b.txs.len().cmp(&a.txs.len()))
+        });
+
+        if args.cluster_funding {
+            let spinner = get_spinner!(format!(
+                "{} {}Tracing wallet funding...",
+                console::style("[2/3]").bold().dim(),
+                crate::SCAN,
+            ));
+            self.hide_if_reporting(&spinner);
+            for wallet in repeating_wallets_vec.iter_mut().filter(|w| !w.is_mev()) {
+                let cached = self.funding.contains_key(&wallet.wallet);
+                metrics::cache("funding", cached as usize, !cached as usize);
+                if !cached {
+                    match cluster::fetch_funding(&self.rpc_client, &wallet.wallet, args.delay_ms)
+                        .await
+                    {
+                        Ok(funding) => {
+                            self.funding.insert(wallet.wallet.to_owned(), funding);
+                        }
+                        // Failures are not cached, so the wallet is traced again on the next scan
+                        Err(e) => {
+                            tracing::warn!(
+                                "Failed to trace the funding of {}: {}",
+                                wallet.wallet,
+                                e
+                            );
+                            continue;
+                        }
+                    }
+                }
+                wallet.funding = self.funding[&wallet.wallet].clone();
+            }
+            cluster::assign_clusters(&mut repeating_wallets_vec);
+            spinner.finish();
+        }
+
+        if args.leader_pnl || args.compare_outcomes {
+            let spinner = get_spinner!(format!(
+                "{} {}Reconstructing leader trades...",
+                console::style("[2/3]").bold().dim(),
+                crate::SCAN,
+            ));
+            self.hide_if_reporting(&spinner);
+            let mut target_trades = None;
+            if args.compare_outcomes {
+                let leaders: Vec<_> = repeating_wallets_vec
+                    .iter()
+                    .filter(|w| !w.is_mev())
+                    .collect();
+                let mints = traded_mints(&leaders);
+                match pnl::fetch_trades(
+                    &self.rpc_client,
+                    &self.shyft_api,
+                    wallet,
+                    &mints,
+                    args.delay_ms,
+                )
+                .await
+                {
+                    Ok(trades) => target_trades = Some(trades),
+                    Err(e) => tracing::warn!("Failed to fetch the trades of {}: {}", wallet, e),
+                }
+            }
+            // Without the wallet's own trades, the leaders' trades are only needed for their PnL
+            let leaders = repeating_wallets_vec
+                .iter_mut()
+                .filter(|w| !w.is_mev() && (args.leader_pnl || target_trades.is_some()));
+            for leader in leaders {
+                let Ok(address) = Pubkey::from_str(&leader.wallet) else {
+                    continue;
+                };
+                let mints = traded_mints(&[&*leader]);
+                let trades = match pnl::fetch_trades(
+                    &self.rpc_client,
+                    &self.shyft_api,
+                    &address,
+                    &mints,
+                    args.delay_ms,
+                )
+                .await
+                {
+                    Ok(trades) => trades,
+                    Err(e) => {
+                        tracing::warn!("Failed to fetch the trades of {}: {}", leader.wallet, e);
+                        continue;
+                    }
+                };
+                if args.leader_pnl {
+                    leader.pnl = Some(crate::leader_pnl(&trades));
+                }
+                if let Some(target_trades) = target_trades.as_ref() {
+                    leader.outcomes = Some(crate::compare_outcomes(target_trades, &trades));
+                }
+            }
+            spinner.finish();
+        }
+
+        metrics::candidates(repeating_wallets_vec.iter().filter(|w| !w.is_mev()).count());
+        Ok(repeating_wallets_vec)
     }
 
-    let progress_bar = ProgressBar::new(fresh_swaps.len() as u64);
-    progress_bar.set_style(
-        ProgressStyle::with_template(
-            "{spinner:.green} {msg} [{wide_bar:.green/magenta}] {percent}%",
+    /// Hides a progress bar if the progress is reported to a callback.
+    ///
+    /// # Arguments
+    ///
+    /// * `bar` - The progress bar or spinner.
+    fn hide_if_reporting(&self, bar: &ProgressBar) {
+        if self.progress.is_some() {
+            bar.set_draw_target(ProgressDrawTarget::hidden());
+        }
+    }
+
+    /// Returns the lookback window set with `--lookback-slots` or `--lookback-secs`, if any.
+    fn lookback(&self) -> Option<Lookback> {
+        self.args
+            .lookback_slots
+            .map(Lookback::Slots)
+            .or(self.args.lookback_secs.map(Lookback::Secs))
+    }
+
+    /// Fetches the fresh swaps of a wallet.
+    ///
+    /// The latest swaps are taken from the Cielo feed. If a range is given with `--since` or
+    /// `--until`, the swaps within it are found in the wallet's history instead, and their
+    /// parsed transactions are cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The wallet to fetch swaps for.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Cielo API request fails, or fetching the
+    /// wallet's history fails.
+    pub(crate) async fn fetch_swaps(
+        &mut self,
+        wallet: &Pubkey,
+    ) -> Result<Vec<models::feed::Swap>, crate::Error> {
+        if self.args.since.is_some() || self.args.until.is_some() {
+            let (swaps, parsed_txs) = range::fetch_swaps_in_range(
+                &self.rpc_client,
+                &self.shyft_api,
+                wallet,
+                self.args.since,
+                self.args.until,
+                self.args.max_range_pages as usize,
+                self.args.delay_ms,
+            )
+            .await?;
+            self.cache_swap_txs(parsed_txs);
+            return Ok(swaps);
+        }
+        let items = fetch_fresh_swaps(&self.cielo_api, &self.args, wallet).await?;
+        Ok(items
+            .into_iter()
+            .filter_map(|item| match item {
+                models::feed::Item::Swap(swap) => Some(swap),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Analyses a single fresh swap.
+    ///
+    /// This function fetches the buys that preceded the swap, calculates their block difference
+    /// and, with `--mev-check`, checks the buyers in the same or adjacent slot for sandwiches.
+    /// Previous buys whose slot is unknown are skipped. The buys are annotated with the metadata
+    /// of the bought token, cached across swaps, and with `--token-launch` or `--max-token-age`,
+    /// its launch and whether it had migrated off its launchpad. With `--max-token-age`, swaps on
+    /// older tokens are skipped before their previous buys are fetched. Failed token lookups are
+    /// logged and leave the annotations empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the swap's transaction hash or token mint is
+    /// malformed, or if fetching previous buys or their blocks fails.
+    /// Malformed data is reported before any request is made, see
+    /// [`Error::is_malformed_data`](crate::Error::is_malformed_data).
+    pub async fn analyse_swap(
+        &mut self,
+        swap: &models::feed::Swap,
+    ) -> Result<SwapAnalysis, crate::Error> {
+        let delay_ms = self.args.delay_ms;
+        let signature = Signature::from_str(&swap.tx_hash)
+            .map_err(|e| crate::Error::InvalidSignature(swap.tx_hash.to_owned(), e))?;
+        let mint = mint_address(swap)?;
+        let mut launch = None;
+        let mut launch_searched = false;
+        if let Some(max_age) = self.args.max_token_age {
+            let max_age = i64::try_from(max_age).unwrap_or(i64::MAX);
+            let not_before = swap.timestamp.saturating_sub(max_age);
+            let too_old = match self
+                .token_launch(swap, &signature, &mint, Some(not_before))
+                .await
+            {
+                LaunchLookup::Found(found) => {
+                    let too_old = found
+                        .age_at(swap.timestamp)
+                        .is_some_and(|age| age > max_age);
+                    launch = Some(found);
+                    too_old
+                }
+                LaunchLookup::PastWindow => true,
+                LaunchLookup::NotFound => false,
+            };
+            if too_old {
+                tracing::info!(
+                    "Skipping swap {} on a token older than {}s",
+                    swap.tx_hash,
+                    max_age
+                );
+                return Ok(SwapAnalysis::default());
+            }
+            launch_searched = true;
+        }
+        let prev_buys = self.fetch_prev_buys(swap, &signature, &mint).await?;
+        metrics::swap_scanned();
+
+        tracing::info!("Fetched {} previous buys", prev_buys.len());
+
+        let mut analysis = SwapAnalysis {
+            prev_buys: Vec::with_capacity(prev_buys.len()),
+            sandwiches: Vec::new(),
+        };
+        let token = if prev_buys.is_empty() {
+            TokenMetadata::default()
+        } else {
+            self.token_metadata(swap, &mint).await
+        };
+        if self.args.token_launch && !launch_searched && !prev_buys.is_empty() {
+            if let LaunchLookup::Found(found) =
+                self.token_launch(swap, &signature, &mint, None).await
+            {
+                launch = Some(found);
+            }
+        }
+        let migrated = match (launch.as_ref(), self.cached_swap_pool(swap)) {
+            (Some(launch), Some(pool)) if !prev_buys.is_empty() => {
+                match token::fetch_migrated(&self.rpc_client, &pool, launch.platform, delay_ms)
+                    .await
+                {
+                    Ok(migrated) => migrated,
+                    Err(e) => {
+                        tracing::warn!("Failed to check the migration of {}: {}", mint, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let token_age_secs = launch.as_ref().and_then(|l| l.age_at(swap.timestamp));
+        let mut front_runs = Vec::new();
+        let mut swap_slot = 0;
+        let target_sol_amount = if swap.token0_address == "native" || swap.token0_symbol == "SOL" {
+            swap.token0_amount
+        } else {
+            0.0
+        };
+        for buy in prev_buys.iter() {
+            let slot = match parsed_tx_slot(buy) {
+                Ok(slot) => slot,
+                Err(e) => {
+                    tracing::warn!("Skipping previous buy: {}", e);
+                    continue;
+                }
+            };
+            let block_diff =
+                get_block_diff(&self.rpc_client, swap, &signature, slot, delay_ms).await?;
+            let (sol_amount, token_amount) = buy_amounts(buy);
+            analysis.prev_buys.push((
+                buy.fee_payer.to_owned(),
+                PrevBuy {
+                    tx_hash: buy.signatures[0].to_owned(),
+                    slot,
+                    block_diff,
+                    mint: swap.token1_address.to_owned(),
+                    sol_amount,
+                    token_amount,
+                    target_sol_amount,
+                    symbol: token.symbol.to_owned(),
+                    name: token.name.to_owned(),
+                    decimals: token.decimals,
+                    token_created_slot: launch.as_ref().map(|l| l.created_slot),
+                    token_age_secs,
+                    launch_platform: launch.as_ref().map(|l| l.platform),
+                    migrated,
+                },
+            ));
+            if block_diff <= mev::MAX_SANDWICH_SLOT_DIFF {
+                swap_slot = slot + block_diff;
+                front_runs.push(FrontRun { tx: buy, slot });
+            }
+        }
+
+        if self.args.mev_check && !front_runs.is_empty() {
+            analysis.sandwiches =
+                mev::find_sandwiches(&self.rpc_client, swap, swap_slot, &front_runs, delay_ms)
+                    .await;
+
+            tracing::info!("Found {} sandwiches", analysis.sandwiches.len());
+        }
+        // Sleep to avoid rate limit
+        metrics::rate_limit_wait(delay_ms).await;
+
+        Ok(analysis)
+    }
+
+    /// Returns the metadata of the token bought in a swap, fetching it on first use.
+    ///
+    /// Tokens without Metaplex metadata keep the symbol reported by Cielo. The metadata only
+    /// annotates the report, so if it cannot be fetched, a warning is logged and the Cielo symbol
+    /// is returned without a name or decimals. Failures are not cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    /// * `mint` - The mint of the bought token.
+    async fn token_metadata(&mut self, swap: &models::feed::Swap, mint: &Pubkey) -> TokenMetadata {
+        let cached = self.tokens.contains_key(&swap.token1_address);
+        metrics::cache("token_metadata", cached as usize, !cached as usize);
+        if !cached {
+            let result = token::fetch_metadata(&self.rpc_client, mint).await;
+            metrics::rate_limit_wait(self.args.delay_ms).await;
+            let mut token = match result {
+                Ok(token) => token,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch the metadata of {}: {}", mint, e);
+                    return TokenMetadata {
+                        symbol: swap.token1_symbol.to_owned(),
+                        ..Default::default()
+                    };
+                }
+            };
+            if token.symbol.is_empty() {
+                token.symbol = swap.token1_symbol.to_owned();
+            }
+            self.tokens.insert(swap.token1_address.to_owned(), token);
+        }
+        self.tokens[&swap.token1_address].clone()
+    }
+
+    /// Returns the launch of the token bought in a swap, searching for it on first use.
+    ///
+    /// Only found launches are cached, so a token is searched again from each of its swaps until
+    /// its launch is found. A failed search is logged and reported as not found.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    /// * `signature` - The signature of the swap.
+    /// * `mint` - The mint of the bought token.
+    /// * `not_before` - Give up once the mint's history is older than this Unix timestamp.
+    async fn token_launch(
+        &mut self,
+        swap: &models::feed::Swap,
+        signature: &Signature,
+        mint: &Pubkey,
+        not_before: Option<i64>,
+    ) -> LaunchLookup {
+        if let Some(launch) = self.launches.get(&swap.token1_address) {
+            metrics::cache("token_launch", 1, 0);
+            return LaunchLookup::Found(launch.clone());
+        }
+        metrics::cache("token_launch", 0, 1);
+        let lookup = match token::fetch_launch(
+            &self.rpc_client,
+            mint,
+            signature,
+            not_before,
+            self.args.delay_ms,
         )
-        .unwrap(),
-    );
-    progress_bar.enable_steady_tick(Duration::from_millis(120));
-    progress_bar.set_message(format!(
-        "{} {}Scanning transaction history...",
-        console::style("[2/3]").bold().dim(),
-        crate::SCAN,
-    ));
-
-    let shyft_api = ShyftApi::new(&args.shyft_api_key, None, None, None, None, None)?;
-
-    // Use Shyft RPC if no RPC URL is provided
-    let rpc_url = args
-        .rpc_url
-        .as_ref()
-        .unwrap_or(&format!(
-            "https://rpc.shyft.to/?api_key={}",
-            args.shyft_api_key
-        ))
-        .to_owned();
-    let rpc_client = RpcClient::new_with_timeout(rpc_url, Duration::from_secs(10));
-
-    for item in fresh_swaps.iter() {
-        if let models::feed::Item::Swap(swap) = item {
-            let prev_buys =
-                fetch_prev_buys(args, &rpc_client, &shyft_api, swap, args.delay_ms).await?;
-
-            tracing::info!("Fetched {} previous buys", prev_buys.len());
-
-            for buy in prev_buys.iter() {
-                let block_diff = get_block_diff(&rpc_client, swap, buy, args.delay_ms).await?;
-                prev_wallets
-                    .entry(buy.fee_payer.to_owned())
-                    .or_insert_with(Vec::new)
-                    .push(PrevBuy {
-                        tx_hash: buy.signatures[0].to_owned(),
-                        block_diff,
-                    });
+        .await
+        {
+            Ok(lookup) => lookup,
+            Err(e) => {
+                tracing::warn!("Failed to find the launch of {}: {}", mint, e);
+                LaunchLookup::NotFound
             }
-            // Sleep to avoid rate limit
-            tokio::time::sleep(tokio::time::Duration::from_millis(args.delay_ms)).await;
+        };
+        if let LaunchLookup::Found(launch) = &lookup {
+            self.launches
+                .insert(swap.token1_address.to_owned(), launch.clone());
         }
-        progress_bar.inc(1);
+        lookup
     }
 
-    progress_bar.finish();
+    /// Returns the pool or bonding curve a swap traded against, if its parsed transaction is
+    /// cached and names one. See [`swap_pool`].
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    fn cached_swap_pool(&self, swap: &models::feed::Swap) -> Option<Pubkey> {
+        self.swap_txs
+            .get(&swap.tx_hash)
+            .and_then(Option::as_ref)
+            .and_then(swap_pool)
+    }
 
-    // Retain only wallets with more than one repeating previous buy
-    prev_wallets.retain(|_, buys| buys.len() > 1);
+    /// Resolves the address whose signatures are paged to find the buys before a swap, see
+    /// [`signatures_address`].
+    ///
+    /// The swap's transaction is parsed if it is not cached yet, which the swaps of a range and
+    /// those prefetched by a scan are.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    /// * `mint` - The mint of the bought token.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Shyft API request fails.
+    async fn resolve_signatures_address(
+        &mut self,
+        swap: &models::feed::Swap,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, PrevBuysFetchError> {
+        self.fetch_swap_txs(std::slice::from_ref(&swap.tx_hash))
+            .await?;
+        let tx = self.swap_txs.get(&swap.tx_hash).and_then(Option::as_ref);
+        Ok(signatures_address(tx, mint))
+    }
 
-    // Sort the repeating wallets by the number of previous buys in descending order
-    let mut repeating_wallets_vec: Vec<_> = prev_wallets
-        .into_iter()
-        .map(|(wallet, buys)| RepeatingWallet { wallet, txs: buys })
-        .collect();
-    repeating_wallets_vec.sort_by(|a, b| b.txs.len().cmp(&a.txs.len()));
+    /// Parses the transactions of swaps that are not cached yet, in batches.
+    ///
+    /// Prefetching the transactions of all the fresh swaps of a scan saves a request per swap
+    /// when resolving the pools to page the signatures of. Swaps that Shyft does not return are
+    /// cached as `None`, so that they are not requested again.
+    ///
+    /// # Arguments
+    ///
+    /// * `hashes` - The swap transaction hashes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a Shyft API request fails.
+    async fn fetch_swap_txs(&mut self, hashes: &[String]) -> Result<(), PrevBuysFetchError> {
+        let uncached: Vec<_> = hashes
+            .iter()
+            .filter(|hash| !self.swap_txs.contains_key(*hash))
+            .cloned()
+            .collect();
+        for chunk in uncached.chunks(range::PARSE_BATCH_SIZE) {
+            let parsed_txs = metrics::upstream(
+                "shyft",
+                self.shyft_api
+                    .get_transaction_parse_selected(chunk, Some(true), None)
+                    .await,
+            )?;
+            for hash in chunk {
+                self.swap_txs.insert(hash.to_owned(), None);
+            }
+            self.cache_swap_txs(parsed_txs);
+            metrics::rate_limit_wait(self.args.delay_ms).await;
+        }
+        Ok(())
+    }
 
-    Ok(repeating_wallets_vec)
+    /// Caches the parsed transactions of swaps by signature, whatever they traded.
+    ///
+    /// Transactions without a signature cannot be looked up and are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `parsed_txs` - The parsed swap transactions.
+    fn cache_swap_txs(&mut self, parsed_txs: Vec<ParsedTransactionDetails>) {
+        for tx in parsed_txs {
+            let Some(signature) = tx.signatures.first().cloned() else {
+                tracing::warn!("Dropping parsed transaction without signature");
+                continue;
+            };
+            self.swap_txs.insert(signature, Some(tx));
+        }
+    }
+
+    /// Caches parsed transactions by signature, keeping only the buys.
+    ///
+    /// Transactions without a signature cannot be looked up and are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `parsed_txs` - The parsed transactions.
+    fn cache_parsed_txs(&mut self, parsed_txs: Vec<ParsedTransactionDetails>) {
+        for tx in parsed_txs {
+            let Some(signature) = tx.signatures.first().cloned() else {
+                tracing::warn!("Dropping parsed transaction without signature");
+                continue;
+            };
+            self.parsed_txs.insert(signature, is_buy(&tx).then_some(tx));
+        }
+    }
+
+    /// Fetches previous buy transactions for a given swap.
+    ///
+    /// This function retrieves the transaction history of the pool the swap traded against
+    /// and filters the transactions to include only those that involve a swap where SOL is the
+    /// input token. Transactions parsed in earlier scans are taken from the cache. The signatures
+    /// are taken from the streamed mint history instead of the RPC if it covers the swap, unless
+    /// a lookback window is set, which the streamed history cannot tell the slots of.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap` - A reference to the swap transaction details.
+    /// * `signature` - The signature of the swap.
+    /// * `mint` - The mint of the bought token.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the Shyft API request fails, if the transaction parsing fails
+    /// or if the RPC returns a malformed signature.
+    async fn fetch_prev_buys(
+        &mut self,
+        swap: &models::feed::Swap,
+        signature: &Signature,
+        mint: &Pubkey,
+    ) -> Result<Vec<ParsedTransactionDetails>, crate::Error> {
+        let scan_tx_count = self.args.scan_tx_count as usize;
+        let lookback = self.lookback();
+        let streamed = self
+            .mint_history
+            .as_ref()
+            .filter(|_| lookback.is_none())
+            .and_then(|history| {
+                history.signatures_before(&swap.token1_address, &swap.tx_hash, scan_tx_count)
+            });
+        if self.mint_history.is_some() && lookback.is_none() {
+            metrics::cache(
+                "mint_history",
+                streamed.is_some() as usize,
+                streamed.is_none() as usize,
+            );
+        }
+        let mut successful_signatures = match streamed {
+            Some(signatures) => {
+                tracing::debug!("Using streamed signatures for {}", swap.token1_address);
+                signatures
+            }
+            None => {
+                let address = self.resolve_signatures_address(swap, mint).await?;
+                fetch_successful_signatures(
+                    &self.rpc_client,
+                    &address,
+                    swap,
+                    signature,
+                    if lookback.is_some() {
+                        self.args.max_scan_tx as usize
+                    } else {
+                        scan_tx_count
+                    },
+                    lookback,
+                    self.args.delay_ms,
+                )
+                .await?
+            }
+        };
+        tracing::info!(
+            "Fetched {} successful signatures",
+            successful_signatures.len()
+        );
+
+        if successful_signatures.is_empty() {
+            tracing::warn!("No successful signatures found");
+            return Ok(Vec::new());
+        }
+        if lookback.is_none() {
+            successful_signatures.truncate(scan_tx_count);
+        }
+
+        let uncached: Vec<_> = successful_signatures
+            .iter()
+            .filter(|s| !self.parsed_txs.contains_key(*s))
+            .cloned()
+            .collect();
+        tracing::debug!("{} signatures not in cache", uncached.len());
+        metrics::cache(
+            "parsed_txs",
+            successful_signatures.len() - uncached.len(),
+            uncached.len(),
+        );
+
+        for (i, chunk) in uncached.chunks(range::PARSE_BATCH_SIZE).enumerate() {
+            if i > 0 {
+                metrics::rate_limit_wait(self.args.delay_ms).await;
+            }
+            let parsed_txs = metrics::upstream(
+                "shyft",
+                self.shyft_api
+                    .get_transaction_parse_selected(chunk, Some(true), None)
+                    .await,
+            )
+            .map_err(PrevBuysFetchError::from)?;
+            self.cache_parsed_txs(parsed_txs);
+        }
+
+        Ok(successful_signatures
+            .iter()
+            .filter_map(|s| self.parsed_txs.get(s).cloned().flatten())
+            .collect())
+    }
+}
+
+/// Represents the window before a swap in which the previous buys are scanned.
+#[derive(Debug, Clone, Copy)]
+enum Lookback {
+    /// A number of slots before the swap.
+    Slots(u64),
+    /// A number of seconds before the swap.
+    Secs(u64),
+}
+
+impl Lookback {
+    /// Returns the start of the window before a swap.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot of the swap.
+    /// * `timestamp` - The block time of the swap.
+    fn start(&self, slot: u64, timestamp: i64) -> RangeBound {
+        match self {
+            Self::Slots(slots) => RangeBound::Slot(slot.saturating_sub(*slots)),
+            Self::Secs(secs) => RangeBound::Timestamp(timestamp.saturating_sub(*secs as i64)),
+        }
+    }
+}
+
+/// Why paging the signatures before a swap stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagingStop {
+    /// Enough successful signatures were found without a lookback window.
+    Count,
+    /// The signatures reached the start of the lookback window.
+    WindowEnd,
+    /// The lookback window holds more successful signatures than the limit.
+    Cap,
+}
+
+/// Decides whether to stop paging the signatures before a swap after a page.
+///
+/// # Arguments
+///
+/// * `found` - The number of successful signatures found so far.
+/// * `limit` - The number of successful signatures to find without a lookback window, or the
+///   maximum number to find in a window.
+/// * `window_end` - Whether the page reached the start of the lookback window, or `None`
+///   without a window.
+///
+/// # Returns
+///
+/// Why paging stops, or `None` to fetch the next page.
+pub fn signature_paging_stop(
+    found: usize,
+    limit: usize,
+    window_end: Option<bool>,
+) -> Option<PagingStop> {
+    match window_end {
+        None => (found >= limit).then_some(PagingStop::Count),
+        // A window with more signatures than the limit is cut, even on its last page
+        Some(end) if found > limit || (found == limit && !end) => Some(PagingStop::Cap),
+        Some(end) => end.then_some(PagingStop::WindowEnd),
+    }
 }
 
 /// Fetches fresh swap transactions for a given wallet.
 ///
-/// This function initializes a new Cielo API client and fetches the latest swap transactions
-/// for the specified wallet based on the provided arguments.
+/// This function fetches the latest swap transactions for the specified wallet based on the
+/// provided arguments.
 ///
 /// # Arguments
 ///
-/// * `args` - A reference to the arguments containing the API key, wallet address, and other parameters.
+/// * `cielo_api` - A reference to the Cielo API client.
+/// * `args` - A reference to the scan parameters.
+/// * `wallet` - The wallet to fetch swaps for.
 ///
 /// # Errors
 ///
-/// This function will return an error if the Cielo API client could not be built or the request fails.
+/// This function will return an error if the Cielo API request fails.
 async fn fetch_fresh_swaps(
-    args: &crate::Args,
+    cielo_api: &CieloApi,
+    args: &ScanArgs,
+    wallet: &Pubkey,
 ) -> Result<Vec<models::feed::Item>, cielo_rs_sdk::Error> {
-    let cielo_api = CieloApi::new(&args.cielo_api_key, None, None, None)?;
-    cielo_api
+    let result = cielo_api
         .get_feed(Filters {
-            wallet: Some(args.wallet.to_string()),
+            wallet: Some(wallet.to_string()),
             limit: Some(args.swap_num),
             chains: Some(vec!["solana".to_owned()]),
             tx_types: Some(vec![TxType::Swap]),
             new_trades: Some(true),
             ..Default::default()
         })
-        .await
-}
-
-/// Fetches previous buy transactions for a given swap.
-///
-/// This function retrieves the transaction history for the specified token address
-/// and filters the transactions to include only those that involve a swap where SOL is the input token.
-///
-/// # Arguments
-///
-/// * `args` - A reference to the arguments containing the API key, wallet address, and other parameters.
-/// * `rpc_client` - A reference to the Solana RPC client.
-/// * `shyft_api` - A reference to the Shyft API client.
-/// * `swap` - A reference to the swap transaction details.
-/// * `delay_ms` - The delay in milliseconds between requests.
-///
-/// # Errors
-///
-/// This function will return an error if the Shyft API request fails or if the transaction parsing fails.
-async fn fetch_prev_buys(
-    args: &crate::Args,
-    rpc_client: &RpcClient,
-    shyft_api: &ShyftApi,
-    swap: &models::feed::Swap,
-    delay_ms: u64,
-) -> Result<Vec<ParsedTransactionDetails>, PrevBuysFetchError> {
-    let successful_signatures =
-        fetch_successful_signatures(rpc_client, swap, args.scan_tx_count as usize, delay_ms)
-            .await?;
-    tracing::info!(
-        "Fetched {} successful signatures",
-        successful_signatures.len()
-    );
-
-    if successful_signatures.is_empty() {
-        tracing::warn!("No successful signatures found");
-        return Ok(Vec::new());
-    }
-
-    let parsed_txs = shyft_api
-        .get_transaction_parse_selected(
-            &successful_signatures
-                [..std::cmp::min(successful_signatures.len(), args.scan_tx_count as usize)],
-            Some(true),
-            None,
-        )
-        .await?;
-
-    Ok(filter_buys(parsed_txs))
+        .await;
+    metrics::upstream("cielo", result)
 }
 
 /// Fetches successful transaction signatures for a given swap.
 ///
-/// This function retrieves the transaction signatures for the specified token address
+/// This function retrieves the transaction signatures before the swap on the given address
 /// and filters the signatures to include only those that are successful.
 ///
+/// Without a lookback window, signatures are paged back until `limit` successful ones are found.
+/// With a window, they are paged back until the window is exhausted, or until more than `limit`
+/// successful ones are found, which is logged. If the feed did not report the slot
+/// or time of the swap, the window starts from the latest signature before it. See
+/// [`signature_paging_stop`].
+///
 /// # Arguments
 ///
 /// * `rpc_client` - A reference to the Solana RPC client.
+/// * `address` - The pool or mint address to page the signatures of.
 /// * `swap` - A reference to the swap transaction details.
-/// * `scan_tx_count` - The number of transaction signatures to scan.
+/// * `signature` - The signature of the swap.
+/// * `limit` - The number of transaction signatures to scan, or the maximum number with a
+///   lookback window.
+/// * `lookback` - The window before the swap to scan instead, if any.
 /// * `delay_ms` - The delay in milliseconds between requests.
 ///
 /// # Errors
 ///
-/// This function will return an error if the Solana RPC request fails.
+/// This function will return an error if the Solana RPC request fails or returns a malformed
+/// signature.
 async fn fetch_successful_signatures(
     rpc_client: &RpcClient,
+    address: &Pubkey,
     swap: &models::feed::Swap,
-    scan_tx_count: usize,
+    signature: &Signature,
+    limit: usize,
+    lookback: Option<Lookback>,
     delay_ms: u64,
-) -> Result<Vec<String>, solana_client::client_error::ClientError> {
+) -> Result<Vec<String>, crate::Error> {
     let mut successful_signatures = Vec::new();
+    let mut start = None;
 
-    let mut before_tx = Signature::from_str(&swap.tx_hash).unwrap();
-    while successful_signatures.len() < scan_tx_count {
-        let token1_address = if swap.token1_address == "native" {
-            "So11111111111111111111111111111111111111112"
-        } else {
-            &swap.token1_address
-        };
-        let tx_signatures = rpc_client
-            .get_signatures_for_address_with_config(
-                &Pubkey::from_str(token1_address).unwrap(),
-                GetConfirmedSignaturesForAddress2Config {
-                    before: Some(before_tx),
-                    until: None,
-                    limit: None,
-                    commitment: Some(CommitmentConfig::confirmed()),
-                },
-            )
-            .await?;
+    let mut before_tx = *signature;
+    loop {
+        let tx_signatures = metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_signatures_for_address_with_config(
+                    address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before: Some(before_tx),
+                        until: None,
+                        limit: None,
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await,
+        )
+        .map_err(PrevBuysFetchError::from)?;
 
         tracing::debug!("Fetched {} signatures", tx_signatures.len());
 
-        if tx_signatures.is_empty() {
+        let Some(last) = tx_signatures.last() else {
             break;
+        };
+        before_tx = Signature::from_str(&last.signature)
+            .map_err(|e| crate::Error::InvalidSignature(last.signature.to_owned(), e))?;
+
+        if let (Some(lookback), None) = (lookback, start) {
+            let latest = &tx_signatures[0];
+            start = Some(lookback.start(
+                if swap.block > 0 {
+                    swap.block
+                } else {
+                    latest.slot
+                },
+                if swap.timestamp > 0 {
+                    swap.timestamp
+                } else {
+                    latest.block_time.unwrap_or_default()
+                },
+            ));
         }
-        before_tx = Signature::from_str(&tx_signatures.last().unwrap().signature).unwrap();
+        let in_window = |slot, block_time| {
+            !start.is_some_and(|start: RangeBound| start.tx_is_older(slot, block_time))
+        };
 
         for signature in tx_signatures.iter() {
-            if signature.err.is_none() {
+            if signature.err.is_none() && in_window(signature.slot, signature.block_time) {
                 successful_signatures.push(signature.signature.to_string());
             }
         }
-        tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+        let window_end = lookback.map(|_| !in_window(last.slot, last.block_time));
+        match signature_paging_stop(successful_signatures.len(), limit, window_end) {
+            Some(PagingStop::Cap) => {
+                tracing::warn!(
+                    "Lookback window of {} cut at its latest {} transactions, raise --max-scan-tx",
+                    swap.tx_hash,
+                    limit
+                );
+                successful_signatures.truncate(limit);
+                break;
+            }
+            Some(_) => break,
+            None => {}
+        }
+        metrics::rate_limit_wait(delay_ms).await;
     }
 
     Ok(successful_signatures)
 }
 
-/// Filters transactions to include only those that involve a swap where SOL is the input token.
+/// Returns the pool or bonding curve a swap traded against, if its parsed transaction names one.
+///
+/// If the swap was routed through several pools, the last one is taken, as it paid out the
+/// bought token.
+///
+/// # Arguments
+///
+/// * `swap_tx` - The parsed transaction of the swap.
+pub fn swap_pool(swap_tx: &ParsedTransactionDetails) -> Option<Pubkey> {
+    mev::pool_addresses(swap_tx)
+        .pop()
+        .and_then(|pool| Pubkey::from_str(&pool).ok())
+}
+
+/// Resolves the address whose signatures are paged to find the buys before a swap.
+///
+/// This is the pool or bonding curve the swap traded against, see [`swap_pool`], since its
+/// history is made of swaps while the mint's history is flooded with transfers and account
+/// creations. The mint is used if the swap's transaction is unknown or names no pool.
+///
+/// # Arguments
+///
+/// * `swap_tx` - The parsed transaction of the swap, if known.
+/// * `mint` - The mint of the bought token.
+pub fn signatures_address(swap_tx: Option<&ParsedTransactionDetails>, mint: &Pubkey) -> Pubkey {
+    match swap_tx.and_then(swap_pool) {
+        Some(pool) => {
+            tracing::debug!("Paging signatures of pool {}", pool);
+            pool
+        }
+        None => {
+            tracing::debug!("No pool found, paging signatures of the mint");
+            *mint
+        }
+    }
+}
+
+/// Checks whether a transaction involves a swap where SOL is the input token.
+///
+/// # Arguments
+///
+/// * `tx` - A reference to the parsed transaction details.
+fn is_buy(tx: &ParsedTransactionDetails) -> bool {
+    tx.actions.iter().any(|a| {
+        a.action_type == "SWAP"
+            && serde_json::from_value::<parsed_transaction_details::Swap>(a.info.clone())
+                .map_or(false, |info| info.tokens_swapped.token_in.symbol == "SOL")
+    })
+}
+
+/// Returns the SOL spent and the tokens received in a buy transaction.
+///
+/// The swap of the fee payer is preferred over other swaps in the transaction.
 ///
 /// # Arguments
 ///
-/// * `txs` - A vector of parsed transaction details.
+/// * `tx` - A reference to the parsed transaction details.
 ///
 /// # Returns
 ///
-/// A vector of parsed transaction details that match the filter criteria.
-fn filter_buys(txs: Vec<ParsedTransactionDetails>) -> Vec<ParsedTransactionDetails> {
-    txs.into_iter()
-        .filter(|tx| {
-            tx.actions.iter().any(|a| {
-                a.action_type == "SWAP"
-                    && serde_json::from_value::<parsed_transaction_details::Swap>(a.info.clone())
-                        .map_or(false, |info| info.tokens_swapped.token_in.symbol == "SOL")
-            })
+/// The SOL and token amounts, or zeros if the transaction holds no swap with SOL.
+fn buy_amounts(tx: &ParsedTransactionDetails) -> (f64, f64) {
+    let swaps: Vec<_> = tx
+        .actions
+        .iter()
+        .filter(|a| a.action_type == "SWAP")
+        .filter_map(|a| {
+            serde_json::from_value::<parsed_transaction_details::Swap>(a.info.clone()).ok()
+        })
+        .filter(|info| info.tokens_swapped.token_in.symbol == "SOL")
+        .collect();
+    swaps
+        .iter()
+        .find(|info| info.swapper == tx.fee_payer)
+        .or(swaps.first())
+        .map_or((0.0, 0.0), |info| {
+            (
+                info.tokens_swapped.token_in.amount,
+                info.tokens_swapped.token_out.amount,
+            )
         })
-        .collect()
 }
 
 /// Calculates the block difference between a fresh swap and a previous buy transaction.
 ///
-/// This function fetches the block number of the previous buy transaction and compares it with the
-/// block number of the fresh swap. If the block number of the fresh swap is not available, it fetches
-/// it from the Solana RPC client.
+/// This function compares the slot of the previous buy transaction with the block number of the
+/// fresh swap. If the block number of the fresh swap is not available, it fetches it from the
+/// Solana RPC client.
 ///
 /// # Arguments
 ///
 /// * `rpc_client` - A reference to the Solana RPC client.
 /// * `fresh_swap` - A reference to the fresh swap transaction details.
-/// * `prev_buy` - A reference to the previous buy transaction details.
+/// * `signature` - The signature of the fresh swap.
+/// * `prev_buy_block` - The slot of the previous buy transaction.
 /// * `delay_ms` - The delay in milliseconds before fetching the fresh swap block number.
 ///
 /// # Errors
@@ -304,29 +1287,91 @@ fn filter_buys(txs: Vec<ParsedTransactionDetails>) -> Vec<ParsedTransactionDetai
 async fn get_block_diff(
     rpc_client: &RpcClient,
     fresh_swap: &models::feed::Swap,
-    prev_buy: &ParsedTransactionDetails,
+    signature: &Signature,
+    prev_buy_block: u64,
     delay_ms: u64,
 ) -> Result<u64, solana_client::client_error::ClientError> {
-    let raw_tx = prev_buy.raw.as_ref().expect("raw tx data not found");
-    let prev_buy_block = raw_tx["slot"].as_u64().expect("slot not found");
-
     // This is necessary because the Cielo API sometimes returns incorrect block data
     if let Some(block_diff) = fresh_swap.block.checked_sub(prev_buy_block) {
         Ok(block_diff)
     } else {
-        let signature = Signature::from_str(&fresh_swap.tx_hash).unwrap();
-        let fresh_swap_block = rpc_client
-            .get_transaction_with_config(
-                &signature,
-                RpcTransactionConfig {
-                    encoding: Some(UiTransactionEncoding::JsonParsed),
-                    commitment: Some(CommitmentConfig::confirmed()),
-                    max_supported_transaction_version: Some(0),
-                },
-            )
-            .await?
-            .slot;
-        tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
-        Ok(fresh_swap_block - prev_buy_block)
+        let fresh_swap_block = metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_transaction_with_config(
+                    signature,
+                    RpcTransactionConfig {
+                        encoding: Some(UiTransactionEncoding::JsonParsed),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                        max_supported_transaction_version: Some(0),
+                    },
+                )
+                .await,
+        )?
+        .slot;
+        metrics::rate_limit_wait(delay_ms).await;
+        Ok(fresh_swap_block.saturating_sub(prev_buy_block))
     }
 }
+
+/// Returns the distinct mints of the tokens the given wallets were detected buying.
+///
+/// # Arguments
+///
+/// * `wallets` - The detected wallets.
+fn traded_mints(wallets: &[&RepeatingWallet]) -> Vec<Pubkey> {
+    let mut mints: Vec<_> = wallets
+        .iter()
+        .flat_map(|w| w.txs.iter())
+        .filter_map(|t| Pubkey::from_str(&t.mint).ok())
+        .collect();
+    mints.sort();
+    mints.dedup();
+    mints
+}
+
+/// Returns the mint of the token bought in a swap.
+///
+/// Cielo reports SOL as `native`, which is mapped to the wrapped SOL mint.
+///
+/// # Arguments
+///
+/// * `swap` - A reference to the swap transaction details.
+///
+/// # Errors
+///
+/// This function will return an error if the token address is not a valid public key.
+fn mint_address(swap: &models::feed::Swap) -> Result<Pubkey, crate::Error> {
+    let token1_address = if swap.token1_address == "native" {
+        "So11111111111111111111111111111111111111112"
+    } else {
+        &swap.token1_address
+    };
+    Pubkey::from_str(token1_address)
+        .map_err(|e| crate::Error::InvalidMint(swap.token1_address.to_owned(), e))
+}
+
+/// Returns the slot a parsed transaction landed in, taken from its raw transaction data.
+///
+/// # Arguments
+///
+/// * `tx` - A reference to the parsed transaction details.
+///
+/// # Errors
+///
+/// This function will return an error if the raw transaction data is missing or holds no slot.
+pub fn parsed_tx_slot(tx: &ParsedTransactionDetails) -> Result<u64, crate::Error> {
+    tx.raw
+        .as_ref()
+        .and_then(|raw| raw["slot"].as_u64())
+        .ok_or_else(|| {
+            crate::Error::MissingSlot(tx.signatures.first().cloned().unwrap_or_default())
+        })
+}
+
+/// Returns the current time as a Unix timestamp in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
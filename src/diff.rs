@@ -0,0 +1,227 @@
+//! # Scan Diff
+//!
+//! This module compares two scans of the same wallet and reports how its leaders changed: new
+//! leaders, dropped leaders and leaders whose copy count or lag changed significantly.
+//!
+//! The scans can be saved JSON outputs or scans recorded in the SQLite store.
+
+use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+use crate::{load_scans, DiffArgs, RepeatingWallet, Store, TargetScan};
+
+/// Represents the changes of a wallet's leaders between two scans.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScanDiff {
+    /// The scanned wallet address, as recorded in the newer scan.
+    pub wallet: String,
+    /// The source of the older scan.
+    pub old: String,
+    /// The source of the newer scan.
+    pub new: String,
+    /// The leaders only detected in the newer scan, by copy count in descending order.
+    pub new_leaders: Vec<LeaderChange>,
+    /// The leaders only detected in the older scan, by copy count in descending order.
+    pub dropped_leaders: Vec<LeaderChange>,
+    /// The leaders detected in both scans whose copy count or lag changed significantly, by
+    /// change in copy count in descending order.
+    pub changed_leaders: Vec<LeaderChange>,
+    /// The number of leaders detected in both scans without significant changes.
+    pub unchanged_count: usize,
+}
+
+/// Represents a scan to diff with a description of where it was loaded from.
+#[derive(Debug, Clone)]
+pub struct LoadedScan {
+    /// The file path or stored scan id the scan was loaded from.
+    pub source: String,
+    /// The scan.
+    pub scan: TargetScan,
+}
+
+/// Represents a leader in the older and newer scan.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LeaderChange {
+    /// The leader wallet address.
+    pub wallet: String,
+    /// The number of copied swaps in the older scan.
+    pub old_copy_count: usize,
+    /// The number of copied swaps in the newer scan.
+    pub new_copy_count: usize,
+    /// The average lag in slots in the older scan, if detected.
+    pub old_avg_lag: Option<f64>,
+    /// The average lag in slots in the newer scan, if detected.
+    pub new_avg_lag: Option<f64>,
+}
+
+/// Compares the leaders of two scans.
+///
+/// Wallets flagged as MEV are not considered leaders.
+///
+/// # Arguments
+///
+/// * `old` - The older scan.
+/// * `new` - The newer scan.
+/// * `min_copy_change` - The minimum change in copy count to report a leader as changed.
+/// * `min_lag_change` - The minimum change in average lag in slots to report a leader as changed.
+///
+/// # Returns
+///
+/// The changes between the scans.
+pub fn diff(
+    old: &LoadedScan,
+    new: &LoadedScan,
+    min_copy_change: usize,
+    min_lag_change: f64,
+) -> ScanDiff {
+    let old_leaders = leaders(&old.scan.leaders);
+    let new_leaders = leaders(&new.scan.leaders);
+
+    let mut report = ScanDiff {
+        wallet: new.scan.wallet.to_owned(),
+        old: old.source.to_owned(),
+        new: new.source.to_owned(),
+        new_leaders: Vec::new(),
+        dropped_leaders: Vec::new(),
+        changed_leaders: Vec::new(),
+        unchanged_count: 0,
+    };
+
+    for (wallet, leader) in new_leaders.iter() {
+        let old_leader = old_leaders.get(wallet);
+        let change = LeaderChange {
+            wallet: wallet.to_string(),
+            old_copy_count: old_leader.map_or(0, |l| l.txs.len()),
+            new_copy_count: leader.txs.len(),
+            old_avg_lag: old_leader.map(|l| avg_lag(l)),
+            new_avg_lag: Some(avg_lag(leader)),
+        };
+        match (change.old_avg_lag, change.new_avg_lag) {
+            (None, _) => report.new_leaders.push(change),
+            (Some(old_lag), Some(new_lag))
+                if change.old_copy_count.abs_diff(change.new_copy_count) >= min_copy_change
+                    || (new_lag - old_lag).abs() >= min_lag_change =>
+            {
+                report.changed_leaders.push(change)
+            }
+            _ => report.unchanged_count += 1,
+        }
+    }
+    report.dropped_leaders = old_leaders
+        .iter()
+        .filter(|(wallet, _)| !new_leaders.contains_key(*wallet))
+        .map(|(wallet, leader)| LeaderChange {
+            wallet: wallet.to_string(),
+            old_copy_count: leader.txs.len(),
+            new_copy_count: 0,
+            old_avg_lag: Some(avg_lag(leader)),
+            new_avg_lag: None,
+        })
+        .collect();
+
+    report.new_leaders.sort_by(|a, b| {
+        b.new_copy_count
+            .cmp(&a.new_copy_count)
+            .then(a.wallet.cmp(&b.wallet))
+    });
+    report.dropped_leaders.sort_by(|a, b| {
+        b.old_copy_count
+            .cmp(&a.old_copy_count)
+            .then(a.wallet.cmp(&b.wallet))
+    });
+    report.changed_leaders.sort_by(|a, b| {
+        b.old_copy_count
+            .abs_diff(b.new_copy_count)
+            .cmp(&a.old_copy_count.abs_diff(a.new_copy_count))
+            .then(a.wallet.cmp(&b.wallet))
+    });
+
+    report
+}
+
+/// Loads the two scans to diff as given on the command line.
+///
+/// Each scan is a saved JSON output, or the id of a scan recorded in the database. Without
+/// scans, the two latest successful scans of `--wallet` in the database are compared. In a batch
+/// report, the scan of `--wallet` is selected.
+///
+/// # Arguments
+///
+/// * `args` - The diff arguments.
+///
+/// # Returns
+///
+/// The older and newer scans.
+///
+/// # Errors
+///
+/// This function will return an error if a scan cannot be loaded, the database is needed but not
+/// given, or a batch report holds several scans and no wallet is selected.
+pub fn load_diff_scans(args: &DiffArgs) -> Result<(LoadedScan, LoadedScan), crate::Error> {
+    let store = args.db.as_deref().map(Store::open).transpose()?;
+
+    let (Some(old), Some(new)) = (args.old.as_deref(), args.new.as_deref()) else {
+        let (Some(store), Some(wallet)) = (store.as_ref(), args.wallet.as_deref()) else {
+            return Err(crate::Error::MissingDatabase);
+        };
+        let ids = store.latest_scans(wallet, 2)?;
+        let [new, old] = ids[..] else {
+            return Err(crate::Error::NotEnoughScans(wallet.to_owned()));
+        };
+        return Ok((
+            LoadedScan {
+                source: format!("scan {}", old),
+                scan: store.load_scan(old)?,
+            },
+            LoadedScan {
+                source: format!("scan {}", new),
+                scan: store.load_scan(new)?,
+            },
+        ));
+    };
+
+    let load = |source: &str| -> Result<LoadedScan, crate::Error> {
+        let path = PathBuf::from(source);
+        if let (false, Ok(id)) = (path.exists(), i64::from_str(source)) {
+            let store = store.as_ref().ok_or(crate::Error::MissingDatabase)?;
+            return Ok(LoadedScan {
+                source: format!("scan {}", id),
+                scan: store.load_scan(id)?,
+            });
+        }
+
+        let mut scans = load_scans(std::slice::from_ref(&path))?;
+        if let (true, Some(wallet)) = (scans.len() > 1, args.wallet.as_deref()) {
+            scans.retain(|s| s.wallet == wallet);
+        }
+        if scans.len() != 1 {
+            return Err(crate::Error::AmbiguousScanOutput(path, scans.len()));
+        }
+        Ok(LoadedScan {
+            source: source.to_owned(),
+            scan: scans.remove(0),
+        })
+    };
+    Ok((load(old)?, load(new)?))
+}
+
+/// Indexes the leaders of a scan by wallet, leaving out MEV wallets.
+///
+/// # Arguments
+///
+/// * `leaders` - The detected wallets of a scan.
+fn leaders(leaders: &[RepeatingWallet]) -> HashMap<&str, &RepeatingWallet> {
+    leaders
+        .iter()
+        .filter(|l| !l.is_mev())
+        .map(|l| (l.wallet.as_str(), l))
+        .collect()
+}
+
+/// Returns the average lag in slots of a leader's copied swaps.
+///
+/// # Arguments
+///
+/// * `leader` - The leader.
+fn avg_lag(leader: &RepeatingWallet) -> f64 {
+    leader.txs.iter().map(|t| t.block_diff as f64).sum::<f64>() / leader.txs.len().max(1) as f64
+}
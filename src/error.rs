@@ -19,9 +19,127 @@ pub enum Error {
     #[error("Solana RPC client error: {0}")]
     RpcClient(#[from] solana_client::client_error::ClientError),
 
+    /// Error that occurs when there is an issue with the Solana PubSub client.
+    #[error("Solana PubSub client error: {0}")]
+    PubsubClient(#[from] solana_client::nonblocking::pubsub_client::PubsubClientError),
+
+    /// Error that occurs when there is an issue with the Yellowstone gRPC stream.
+    #[cfg(feature = "yellowstone")]
+    #[error("Yellowstone gRPC error: {0}")]
+    Geyser(String),
+
+    /// Error that occurs when the HTTP server fails.
+    #[error("HTTP server error: {0}")]
+    Http(#[from] hyper::Error),
+
     /// Error that occurs when fetching previous buy transactions fails.
     #[error("Failed to fetch previous buy transactions: {0}")]
     PrevBuysFetch(#[from] PrevBuysFetchError),
+
+    /// Error that occurs when the Cielo API returns no swaps for the scanned wallet.
+    #[error("Cielo API returned no swaps for wallet {0}")]
+    NoFreshSwaps(solana_sdk::pubkey::Pubkey),
+
+    /// Error that occurs when a transaction signature cannot be parsed.
+    #[error("Invalid transaction signature {0}: {1}")]
+    InvalidSignature(String, solana_sdk::signature::ParseSignatureError),
+
+    /// Error that occurs when the token mint address of a swap cannot be parsed.
+    #[error("Invalid token mint {0}: {1}")]
+    InvalidMint(String, solana_sdk::pubkey::ParsePubkeyError),
+
+    /// Error that occurs when a parsed transaction does not tell the slot it landed in.
+    #[error("Slot of transaction {0} not found in its raw data")]
+    MissingSlot(String),
+
+    /// Error that occurs when reading or writing files fails.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Error that occurs when reading or writing the SQLite store fails.
+    #[error("Database error: {0}")]
+    Store(#[from] rusqlite::Error),
+
+    /// Error that occurs when the SQLite store was created by a newer version.
+    #[error("Database schema version {0} is not supported, upgrade solana-copy-trade-detect")]
+    StoreVersion(i64),
+
+    /// Error that occurs when stored scans are requested without a database.
+    #[error("A database is required to load stored scans, pass --db")]
+    MissingDatabase,
+
+    /// Error that occurs when a scan is not found in the SQLite store.
+    #[error("Scan {0} not found in the database")]
+    ScanNotFound(i64),
+
+    /// Error that occurs when the SQLite store holds too few successful scans of a wallet to diff.
+    #[error("Fewer than two successful scans of {0} found in the database")]
+    NotEnoughScans(String),
+
+    /// Error that occurs when a scan to diff is a batch report without a selected wallet.
+    #[error("{0} holds {1} matching scans, select one with --wallet")]
+    AmbiguousScanOutput(std::path::PathBuf, usize),
+
+    /// Error that occurs when the configuration file is invalid.
+    #[error("Invalid config file {0}: {1}")]
+    InvalidConfig(std::path::PathBuf, String),
+
+    /// Error that occurs when the selected profile is not in the configuration file.
+    #[error("Profile {0} not found in the config file")]
+    UnknownProfile(String),
+
+    /// Error that occurs when no valid wallet is given to a subcommand.
+    #[error("No valid wallets found")]
+    NoWallets,
+
+    /// Error that occurs when a subcommand cannot write its output in the requested format.
+    #[error("The {0} format is not supported by {1}, use text or json")]
+    UnsupportedFormat(crate::OutputFormat, &'static str),
+
+    /// Error that occurs when a saved scan output cannot be parsed.
+    #[error("Invalid scan output {0}: {1}")]
+    InvalidScanOutput(std::path::PathBuf, serde_json::Error),
+}
+
+impl Error {
+    /// Returns a stable, machine-readable identifier of the error kind.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::FetchFreshSwaps(_) => "fetch_fresh_swaps",
+            Self::ShyftApi(_) => "shyft_api",
+            Self::RpcClient(_) => "rpc_client",
+            Self::PubsubClient(_) => "pubsub_client",
+            #[cfg(feature = "yellowstone")]
+            Self::Geyser(_) => "geyser",
+            Self::Http(_) => "http",
+            Self::PrevBuysFetch(_) => "prev_buys_fetch",
+            Self::NoFreshSwaps(_) => "no_fresh_swaps",
+            Self::InvalidSignature(..) => "invalid_signature",
+            Self::InvalidMint(..) => "invalid_mint",
+            Self::MissingSlot(_) => "missing_slot",
+            Self::Io(_) => "io",
+            Self::Store(_) => "store",
+            Self::StoreVersion(_) => "store_version",
+            Self::MissingDatabase => "missing_database",
+            Self::ScanNotFound(_) => "scan_not_found",
+            Self::NotEnoughScans(_) => "not_enough_scans",
+            Self::AmbiguousScanOutput(..) => "ambiguous_scan_output",
+            Self::InvalidConfig(..) => "invalid_config",
+            Self::UnknownProfile(_) => "unknown_profile",
+            Self::NoWallets => "no_wallets",
+            Self::UnsupportedFormat(..) => "unsupported_format",
+            Self::InvalidScanOutput(..) => "invalid_scan_output",
+        }
+    }
+
+    /// Returns whether the error is caused by malformed data about a single swap from Cielo,
+    /// Shyft or the RPC, in which case the swap is skipped instead of failing the scan.
+    pub fn is_malformed_data(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidSignature(..) | Self::InvalidMint(..) | Self::MissingSlot(_)
+        )
+    }
 }
 
 /// Represents the errors that can occur while fetching previous buy transactions.
@@ -0,0 +1,338 @@
+//! # Yellowstone gRPC Source
+//!
+//! This module streams transactions from a Yellowstone (Geyser) gRPC endpoint. It is only built
+//! with the `yellowstone` feature.
+//!
+//! A single subscription covers the watched wallets and the mints they just bought, and is
+//! updated as mints are watched and unwatched. The buys with SOL of the watched wallets are
+//! decoded from the streamed transactions and forwarded as [`StreamEvent::Swap`]s, so they are
+//! analysed without waiting for the feed. Their other transactions are forwarded as
+//! [`StreamEvent::Wallet`]s and the transactions on the watched mints as [`StreamEvent::Mint`]s,
+//! like the notifications of a [`SwapStream`](crate::SwapStream).
+
+use std::collections::{HashMap, HashSet};
+
+use futures_util::{SinkExt, StreamExt};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use yellowstone_grpc_client::GeyserGrpcClient;
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterTransactions, TokenBalance, TransactionStatusMeta,
+};
+
+use crate::{stream::EVENT_CAPACITY, StreamEvent};
+
+/// The wrapped SOL mint, which appears in most swaps and is not worth recording.
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Name of the subscription filter matching the watched wallets.
+const WALLETS_FILTER: &str = "wallets";
+
+/// Name of the subscription filter matching the watched mints.
+const MINTS_FILTER: &str = "mints";
+
+/// Represents a Yellowstone gRPC transaction stream.
+pub struct GeyserStream {
+    /// The receiver of the events of the watched wallets and mints.
+    receiver: mpsc::Receiver<StreamEvent>,
+    /// The task reading the stream.
+    task: JoinHandle<()>,
+    /// The watched wallets.
+    wallets: Vec<Pubkey>,
+    /// The watched mints.
+    mints: HashSet<Pubkey>,
+    /// Sends the updated subscription to the task reading the stream.
+    requests: watch::Sender<SubscribeRequest>,
+}
+
+impl GeyserStream {
+    /// Connects to a Yellowstone gRPC endpoint and subscribes to the wallets.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The gRPC endpoint URL.
+    /// * `x_token` - The access token of the endpoint, if required.
+    /// * `wallets` - The wallets to watch.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the connection or subscription fails.
+    pub async fn connect(
+        endpoint: &str,
+        x_token: Option<String>,
+        wallets: &[Pubkey],
+    ) -> Result<Self, crate::Error> {
+        let mut client = GeyserGrpcClient::build_from_shared(endpoint.to_owned())
+            .and_then(|builder| builder.x_token(x_token))
+            .map_err(|e| crate::Error::Geyser(e.to_string()))?
+            .connect()
+            .await
+            .map_err(|e| crate::Error::Geyser(e.to_string()))?;
+
+        let request = subscribe_request(wallets, &HashSet::new());
+        let (mut subscription, mut updates) = client
+            .subscribe_with_request(Some(request.clone()))
+            .await
+            .map_err(|e| crate::Error::Geyser(e.to_string()))?;
+        tracing::debug!("Subscribed to {} wallets on {}", wallets.len(), endpoint);
+
+        let (sender, receiver) = mpsc::channel(EVENT_CAPACITY);
+        let (requests, mut updated_requests) = watch::channel(request);
+        let task_wallets = wallets.to_vec();
+        let task = tokio::spawn(async move {
+            // The client must outlive the stream
+            let _client = client;
+            let mut mints = HashSet::new();
+
+            loop {
+                let update = tokio::select! {
+                    update = updates.next() => update,
+                    changed = updated_requests.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        let request = updated_requests.borrow_and_update().clone();
+                        mints = request
+                            .transactions
+                            .get(MINTS_FILTER)
+                            .map(|filter| filter.account_include.iter().cloned().collect())
+                            .unwrap_or_default();
+                        if let Err(e) = subscription.send(request).await {
+                            tracing::error!("Failed to update the Yellowstone gRPC subscription: {}", e);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                let update = match update {
+                    Some(Ok(update)) => update,
+                    Some(Err(e)) => {
+                        tracing::error!("Yellowstone gRPC stream failed: {}", e);
+                        break;
+                    }
+                    None => break,
+                };
+                let Some(UpdateOneof::Transaction(tx)) = update.update_oneof else {
+                    continue;
+                };
+                let Some(info) = tx.transaction else {
+                    continue;
+                };
+                let Ok(signature) = Signature::try_from(info.signature.as_slice()) else {
+                    continue;
+                };
+                let signature = signature.to_string();
+                let keys = info
+                    .transaction
+                    .as_ref()
+                    .and_then(|t| t.message.as_ref())
+                    .map(|m| m.account_keys.as_slice())
+                    .unwrap_or_default();
+
+                let mut events = Vec::new();
+                if update.filters.iter().any(|f| f == WALLETS_FILTER) {
+                    for wallet in task_wallets
+                        .iter()
+                        .filter(|w| keys.iter().any(|k| k.as_slice() == w.as_ref()))
+                    {
+                        let event = info
+                            .meta
+                            .as_ref()
+                            .and_then(|meta| decode_buy(wallet, &signature, tx.slot, keys, meta))
+                            .unwrap_or_else(|| StreamEvent::Wallet {
+                                wallet: *wallet,
+                                signature: signature.to_owned(),
+                                slot: tx.slot,
+                            });
+                        events.push(event);
+                    }
+                }
+                if let (true, Some(meta)) = (
+                    update.filters.iter().any(|f| f == MINTS_FILTER),
+                    info.meta.as_ref(),
+                ) {
+                    let traded: HashSet<_> = meta
+                        .pre_token_balances
+                        .iter()
+                        .chain(meta.post_token_balances.iter())
+                        .map(|b| b.mint.as_str())
+                        .filter(|m| mints.contains(*m))
+                        .collect();
+                    for mint in traded {
+                        let Ok(mint) = mint.parse() else {
+                            continue;
+                        };
+                        events.push(StreamEvent::Mint {
+                            mint,
+                            signature: signature.to_owned(),
+                            slot: tx.slot,
+                        });
+                    }
+                }
+
+                for event in events {
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            tracing::debug!("Yellowstone gRPC stream ended");
+        });
+
+        Ok(Self {
+            receiver,
+            task,
+            wallets: wallets.to_vec(),
+            mints: HashSet::new(),
+            requests,
+        })
+    }
+
+    /// Adds a mint to the subscription.
+    ///
+    /// Watching a mint that is already watched does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The mint to watch.
+    pub fn watch_mint(&mut self, mint: Pubkey) {
+        if self.mints.insert(mint) {
+            self.requests
+                .send_replace(subscribe_request(&self.wallets, &self.mints));
+        }
+    }
+
+    /// Removes a mint from the subscription.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The watched mint.
+    pub fn unwatch(&mut self, mint: &Pubkey) {
+        if self.mints.remove(mint) {
+            self.requests
+                .send_replace(subscribe_request(&self.wallets, &self.mints));
+        }
+    }
+
+    /// Waits for the next event of a watched wallet or mint.
+    ///
+    /// # Returns
+    ///
+    /// The next event, or `None` if the stream has ended.
+    pub async fn next(&mut self) -> Option<StreamEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for GeyserStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Builds the subscription to the transactions of the watched wallets and mints.
+///
+/// A filter without accounts would match every transaction, so the mints filter is left out
+/// while no mint is watched.
+///
+/// # Arguments
+///
+/// * `wallets` - The watched wallets.
+/// * `mints` - The watched mints.
+pub fn subscribe_request(wallets: &[Pubkey], mints: &HashSet<Pubkey>) -> SubscribeRequest {
+    let filter = |accounts: Vec<String>| SubscribeRequestFilterTransactions {
+        vote: Some(false),
+        failed: Some(false),
+        account_include: accounts,
+        ..Default::default()
+    };
+    let mut transactions = HashMap::from([(
+        WALLETS_FILTER.to_owned(),
+        filter(wallets.iter().map(|w| w.to_string()).collect()),
+    )]);
+    if !mints.is_empty() {
+        transactions.insert(
+            MINTS_FILTER.to_owned(),
+            filter(mints.iter().map(|m| m.to_string()).collect()),
+        );
+    }
+
+    SubscribeRequest {
+        transactions,
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    }
+}
+
+/// Decodes a buy with SOL of a wallet from the balance changes of a transaction.
+///
+/// The SOL spent includes wrapped SOL and the rent of the accounts opened by the swap, but not
+/// the fee if the wallet paid it. The bought token is the one whose balance of the wallet
+/// increased the most.
+///
+/// # Arguments
+///
+/// * `wallet` - The wallet.
+/// * `signature` - The transaction signature.
+/// * `slot` - The slot of the transaction.
+/// * `account_keys` - The static account keys of the transaction.
+/// * `meta` - The status of the transaction with its balances.
+///
+/// # Returns
+///
+/// The buy as a [`StreamEvent::Swap`], or `None` if the wallet did not spend SOL on a token.
+pub fn decode_buy(
+    wallet: &Pubkey,
+    signature: &str,
+    slot: u64,
+    account_keys: &[Vec<u8>],
+    meta: &TransactionStatusMeta,
+) -> Option<StreamEvent> {
+    let index = account_keys
+        .iter()
+        .position(|k| k.as_slice() == wallet.as_ref())?;
+    let owner = wallet.to_string();
+    let balance = |balances: &[TokenBalance], mint: &str| -> i128 {
+        balances
+            .iter()
+            .filter(|b| b.owner == owner && b.mint == mint)
+            .filter_map(|b| b.ui_token_amount.as_ref()?.amount.parse::<u64>().ok())
+            .map(i128::from)
+            .sum()
+    };
+
+    let fee = if index == 0 { meta.fee } else { 0 };
+    let native = i128::from(*meta.pre_balances.get(index)?)
+        - i128::from(*meta.post_balances.get(index)?)
+        - i128::from(fee);
+    let wrapped = balance(&meta.pre_token_balances, WSOL_MINT)
+        - balance(&meta.post_token_balances, WSOL_MINT);
+    let lamports = u64::try_from(native + wrapped).ok().filter(|l| *l > 0)?;
+
+    let (mint, token_amount, decimals) = meta
+        .post_token_balances
+        .iter()
+        .filter(|b| b.owner == owner && b.mint != WSOL_MINT)
+        .filter_map(|b| {
+            let increase = balance(&meta.post_token_balances, &b.mint)
+                - balance(&meta.pre_token_balances, &b.mint);
+            let decimals = u8::try_from(b.ui_token_amount.as_ref()?.decimals).ok()?;
+            Some((b.mint.as_str(), u64::try_from(increase).ok()?, decimals))
+        })
+        .filter(|(_, increase, _)| *increase > 0)
+        .max_by_key(|(_, increase, _)| *increase)?;
+
+    Some(StreamEvent::Swap {
+        wallet: *wallet,
+        signature: signature.to_owned(),
+        slot,
+        mint: mint.parse().ok()?,
+        lamports,
+        token_amount,
+        decimals,
+    })
+}
@@ -0,0 +1,469 @@
+//! # HTML Report
+//!
+//! This module renders the detected wallets of a scan as a single, self-contained HTML file that
+//! works offline: the styles and the script sorting the table are inlined and no external
+//! resources are loaded.
+//!
+//! The report holds a summary table of the leaders with sortable columns, an expandable list of
+//! copied buys per leader linking to the explorer, a table of the tokens the leaders were copied
+//! on, and a histogram of the lag of all copied buys.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use crate::{RepeatingWallet, TokenOverlap};
+
+/// The explorer used for transaction and account links.
+const EXPLORER_URL: &str = "https://solscan.io";
+
+/// Lags from this number of slots on are grouped in the last histogram bar.
+const MAX_HISTOGRAM_LAG: u64 = 20;
+
+/// The inlined styles of the report.
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #1f2328; }
+h1 { font-size: 1.4rem; }
+h2 { font-size: 1.1rem; margin-top: 2rem; }
+code, .mono { font-family: ui-monospace, monospace; font-size: 0.9em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #d0d7de; padding: 0.4rem 0.6rem; }
+th, td { text-align: left; vertical-align: top; }
+th { cursor: pointer; user-select: none; background: #f6f8fa; }
+th[aria-sort=ascending]::after { content: ' \\25B2'; }
+th[aria-sort=descending]::after { content: ' \\25BC'; }
+td.num { text-align: right; }
+tr.mev { color: #8c959f; }
+summary { cursor: pointer; }
+details ol { margin: 0.4rem 0; }
+a { color: #0969da; }
+.bar { display: flex; align-items: center; gap: 0.5rem; margin: 0.15rem 0; }
+.bar .label { width: 4rem; text-align: right; }
+.bar .fill { background: #54aeff; height: 1rem; }
+";
+
+/// The inlined script sorting a table by the clicked column.
+const SCRIPT: &str = "
+document.querySelectorAll('th[data-type]').forEach(th => {
+  th.addEventListener('click', () => {
+    const column = th.cellIndex;
+    const tbody = th.closest('table').tBodies[0];
+    const ascending = th.getAttribute('aria-sort') !== 'ascending';
+    th.closest('tr').querySelectorAll('th').forEach(h => h.removeAttribute('aria-sort'));
+    th.setAttribute('aria-sort', ascending ? 'ascending' : 'descending');
+    const key = row => {
+      const value = row.cells[column].dataset.value;
+      return th.dataset.type === 'number' ? parseFloat(value) : value;
+    };
+    const rows = Array.from(tbody.rows).sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+    });
+    rows.forEach(row => tbody.appendChild(row));
+  });
+});
+";
+
+/// Writes the detected wallets of a scan as an HTML report.
+///
+/// The score of a leader is its number of copied swaps relative to the leader with the most
+/// copied swaps. MEV wallets are listed but left out of the histogram. The PnL and captured
+/// return columns are only shown if the PnL or the outcomes of the leaders were analysed.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `wallet` - The scanned wallet address.
+/// * `leaders` - The detected wallets.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+pub fn write_html(
+    writer: &mut impl Write,
+    wallet: &str,
+    leaders: &[RepeatingWallet],
+) -> Result<(), io::Error> {
+    let wallet = escape(wallet);
+    let mev_count = leaders.iter().filter(|l| l.is_mev()).count();
+    let with_pnl = leaders.iter().any(|l| l.pnl.is_some());
+    let with_outcomes = leaders.iter().any(|l| l.outcomes.is_some());
+    let with_sizing = leaders.iter().any(|l| l.sizing.is_some());
+    let max_copies = leaders
+        .iter()
+        .map(|l| l.txs.len())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(
+        writer,
+        "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">"
+    )?;
+    writeln!(writer, "<title>Copy trades of {}</title>", wallet)?;
+    writeln!(writer, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(
+        writer,
+        "<h1>Copy trades of <a class=\"mono\" href=\"{}/account/{}\">{}</a></h1>",
+        EXPLORER_URL, wallet, wallet
+    )?;
+    writeln!(
+        writer,
+        "<p>Detected {} potential copied wallets, flagged {} wallets as MEV (sandwich).</p>",
+        leaders.len() - mev_count,
+        mev_count
+    )?;
+
+    writeln!(writer, "<h2>Leaders</h2>\n<table>\n<thead>\n<tr>")?;
+    let mut columns = vec![
+        ("Wallet", "text"),
+        ("Copied swaps", "number"),
+        ("Score", "number"),
+        ("Avg lag (slots)", "number"),
+        ("Min lag (slots)", "number"),
+        ("Cluster", "number"),
+        ("MEV", "text"),
+    ];
+    if with_pnl {
+        columns.extend([("Realised PnL (SOL)", "number"), ("Win rate", "number")]);
+    }
+    if with_outcomes {
+        columns.push(("Captured return", "number"));
+    }
+    if with_sizing {
+        columns.push(("Buy sizes", "text"));
+    }
+    for (title, kind) in columns {
+        writeln!(writer, "<th data-type=\"{}\">{}</th>", kind, title)?;
+    }
+    writeln!(writer, "</tr>\n</thead>\n<tbody>")?;
+
+    for leader in leaders.iter() {
+        let leader_wallet = escape(&leader.wallet);
+        let copies = leader.txs.len();
+        let score = copies as f64 / max_copies as f64;
+        let avg_lag =
+            leader.txs.iter().map(|t| t.block_diff as f64).sum::<f64>() / copies.max(1) as f64;
+        let min_lag = leader.txs.iter().map(|t| t.block_diff).min().unwrap_or(0);
+
+        writeln!(
+            writer,
+            "<tr{}>",
+            if leader.is_mev() {
+                " class=\"mev\""
+            } else {
+                ""
+            }
+        )?;
+        writeln!(
+            writer,
+            "<td data-value=\"{}\"><details><summary class=\"mono\">{}</summary>",
+            leader_wallet, leader_wallet
+        )?;
+        writeln!(
+            writer,
+            "<a href=\"{}/account/{}\">Wallet on explorer</a>\n<ol>",
+            EXPLORER_URL, leader_wallet
+        )?;
+        for tx in leader.txs.iter() {
+            let tx_hash = escape(&tx.tx_hash);
+            write!(
+                writer,
+                "<li><a class=\"mono\" href=\"{}/tx/{}\">{}</a> slot {}, {} slots ahead",
+                EXPLORER_URL, tx_hash, tx_hash, tx.slot, tx.block_diff
+            )?;
+            if !tx.symbol.is_empty() {
+                write!(writer, ", bought {}", escape(&tx.symbol))?;
+            }
+            if let Some(age) = tx.token_age_secs {
+                write!(writer, " ({} old)", duration(Some(age)))?;
+            }
+            if tx.sol_amount > 0.0 {
+                write!(writer, ", {:.4} SOL", tx.sol_amount)?;
+            }
+            if tx.target_sol_amount > 0.0 {
+                write!(writer, " vs. {:.4} SOL copied", tx.target_sol_amount)?;
+            }
+            writeln!(writer, "</li>")?;
+        }
+        writeln!(writer, "</ol>")?;
+        for sandwich in leader.sandwiches.iter() {
+            writeln!(
+                writer,
+                "<p>Sandwiched <a class=\"mono\" href=\"{0}/tx/{1}\">{1}</a> in slot {2}</p>",
+                EXPLORER_URL,
+                escape(&sandwich.target_tx_hash),
+                sandwich.slot
+            )?;
+        }
+        if let Some(outcomes) = leader.outcomes.as_ref() {
+            writeln!(
+                writer,
+                "<p>Shared tokens, scanned wallet vs. leader:</p>\n<ul>"
+            )?;
+            for token in outcomes.tokens.iter() {
+                writeln!(
+                    writer,
+                    "<li><a class=\"mono\" href=\"{}/token/{}\">{}</a> return {} vs. {}, \
+                     held {} vs. {}</li>",
+                    EXPLORER_URL,
+                    escape(&token.mint),
+                    escape(&token.mint),
+                    percent(token.target.roi),
+                    percent(token.leader.roi),
+                    duration(token.target.hold_secs),
+                    duration(token.leader.hold_secs)
+                )?;
+            }
+            writeln!(writer, "</ul>")?;
+        }
+        writeln!(writer, "</details></td>")?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0}\">{0}</td>",
+            copies
+        )?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{:.4}\">{:.0}%</td>",
+            score,
+            score * 100.0
+        )?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0:.2}\">{0:.1}</td>",
+            avg_lag
+        )?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0}\">{0}</td>",
+            min_lag
+        )?;
+        match leader.cluster {
+            Some(cluster) => writeln!(
+                writer,
+                "<td class=\"num\" data-value=\"{0}\">#{0}</td>",
+                cluster
+            )?,
+            None => writeln!(writer, "<td class=\"num\" data-value=\"-1\"></td>")?,
+        }
+        writeln!(
+            writer,
+            "<td data-value=\"{0}\">{0}</td>",
+            if leader.is_mev() { "yes" } else { "no" }
+        )?;
+        if with_pnl {
+            write_pnl_cells(writer, leader)?;
+        }
+        if with_outcomes {
+            match leader.outcomes.as_ref().and_then(|o| o.captured_return) {
+                Some(captured) => writeln!(
+                    writer,
+                    "<td class=\"num\" data-value=\"{:.4}\">{}</td>",
+                    captured,
+                    percent(Some(captured))
+                )?,
+                None => writeln!(writer, "<td class=\"num\" data-value=\"-Infinity\"></td>")?,
+            }
+        }
+        if with_sizing {
+            match leader.sizing.as_ref() {
+                Some(sizing) => writeln!(
+                    writer,
+                    "<td data-value=\"{0}\">{0}, {1:.2}x</td>",
+                    sizing.pattern, sizing.avg_ratio
+                )?,
+                None => writeln!(writer, "<td data-value=\"\"></td>")?,
+            }
+        }
+        writeln!(writer, "</tr>")?;
+    }
+    writeln!(writer, "</tbody>\n</table>")?;
+
+    write_tokens(writer, &crate::group_by_token(leaders))?;
+    write_histogram(writer, leaders)?;
+
+    writeln!(writer, "<script>{}</script>\n</body>\n</html>", SCRIPT)?;
+    writer.flush()
+}
+
+/// Writes the realised PnL and win rate cells of a leader, left empty if unknown.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `leader` - The leader.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_pnl_cells(writer: &mut impl Write, leader: &RepeatingWallet) -> Result<(), io::Error> {
+    let pnl = leader.pnl.as_ref();
+    match pnl {
+        Some(pnl) => writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0:.9}\">{0:.4}</td>",
+            pnl.realised_pnl_sol
+        )?,
+        None => writeln!(writer, "<td class=\"num\" data-value=\"-Infinity\"></td>")?,
+    }
+    match pnl.and_then(|p| p.win_rate) {
+        Some(win_rate) => writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{:.4}\">{:.0}%</td>",
+            win_rate,
+            win_rate * 100.0
+        ),
+        None => writeln!(writer, "<td class=\"num\" data-value=\"-1\"></td>"),
+    }
+}
+
+/// Writes the table of the tokens the leaders were copied on, leaving out MEV wallets.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `tokens` - The copied swaps grouped by token.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_tokens(writer: &mut impl Write, tokens: &[TokenOverlap]) -> Result<(), io::Error> {
+    writeln!(writer, "<h2>Tokens</h2>")?;
+    if tokens.is_empty() {
+        return writeln!(writer, "<p>No copied buys.</p>");
+    }
+    writeln!(writer, "<table>\n<thead>\n<tr>")?;
+    for (title, kind) in [
+        ("Token", "text"),
+        ("Name", "text"),
+        ("Platform", "text"),
+        ("Leaders", "number"),
+        ("Copied swaps", "number"),
+    ] {
+        writeln!(writer, "<th data-type=\"{}\">{}</th>", kind, title)?;
+    }
+    writeln!(writer, "</tr>\n</thead>\n<tbody>")?;
+    for token in tokens.iter() {
+        let mint = escape(&token.mint);
+        let symbol = escape(&token.symbol);
+        writeln!(
+            writer,
+            "<tr>\n<td data-value=\"{0}\"><a href=\"{1}/token/{2}\">{3}</a></td>",
+            if symbol.is_empty() { &mint } else { &symbol },
+            EXPLORER_URL,
+            mint,
+            if symbol.is_empty() {
+                format!("<span class=\"mono\">{}</span>", mint)
+            } else {
+                symbol.to_owned()
+            }
+        )?;
+        writeln!(
+            writer,
+            "<td data-value=\"{0}\">{0}</td>",
+            escape(&token.name)
+        )?;
+        writeln!(
+            writer,
+            "<td data-value=\"{0}\">{0}</td>",
+            token
+                .launch_platform
+                .map(|p| p.to_string())
+                .unwrap_or_default()
+        )?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0}\">{0}</td>",
+            token.leaders.len()
+        )?;
+        writeln!(
+            writer,
+            "<td class=\"num\" data-value=\"{0}\">{0}</td>\n</tr>",
+            token.copy_count
+        )?;
+    }
+    writeln!(writer, "</tbody>\n</table>")
+}
+
+/// Writes the histogram of the lag of the copied buys of all leaders, leaving out MEV wallets.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `leaders` - The detected wallets.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_histogram(writer: &mut impl Write, leaders: &[RepeatingWallet]) -> Result<(), io::Error> {
+    let mut counts = BTreeMap::new();
+    for tx in leaders
+        .iter()
+        .filter(|l| !l.is_mev())
+        .flat_map(|l| l.txs.iter())
+    {
+        *counts
+            .entry(tx.block_diff.min(MAX_HISTOGRAM_LAG))
+            .or_insert(0usize) += 1;
+    }
+
+    writeln!(writer, "<h2>Lag histogram</h2>")?;
+    let Some(max_lag) = counts.keys().last().copied() else {
+        return writeln!(writer, "<p>No copied buys.</p>");
+    };
+    let max_count = counts.values().max().copied().unwrap_or(1);
+    for lag in 0..=max_lag {
+        let count = counts.get(&lag).copied().unwrap_or(0);
+        writeln!(
+            writer,
+            "<div class=\"bar\"><span class=\"label\">{}{}</span>\
+             <span class=\"fill\" style=\"width: {:.1}%\"></span><span>{}</span></div>",
+            lag,
+            if lag == MAX_HISTOGRAM_LAG { "+" } else { "" },
+            count as f64 / max_count as f64 * 80.0,
+            count
+        )?;
+    }
+    Ok(())
+}
+
+/// Formats a ratio as a percentage, or `n/a` if unknown.
+///
+/// # Arguments
+///
+/// * `ratio` - The ratio.
+fn percent(ratio: Option<f64>) -> String {
+    ratio.map_or("n/a".to_owned(), |r| format!("{:.0}%", r * 100.0))
+}
+
+/// Formats a duration in seconds as minutes, or `n/a` if unknown.
+///
+/// # Arguments
+///
+/// * `secs` - The duration in seconds.
+fn duration(secs: Option<i64>) -> String {
+    secs.map_or("n/a".to_owned(), |s| format!("{:.1} min", s as f64 / 60.0))
+}
+
+/// Escapes text for use in HTML content and attribute values.
+///
+/// # Arguments
+///
+/// * `text` - The text to escape.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
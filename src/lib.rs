@@ -10,14 +10,60 @@
 )]
 
 mod args;
+mod batch;
+mod cluster;
+mod config;
 mod core;
+mod diff;
 mod error;
+#[cfg(feature = "yellowstone")]
+mod geyser;
+mod html;
 mod macros;
+mod metrics;
+mod mev;
+mod output;
+mod overlap;
+mod pnl;
+mod range;
+mod server;
+mod sizing;
+mod store;
+mod stream;
+mod token;
+mod watch;
+mod webhook;
 
-pub use args::Args;
+pub use args::{
+    Args, BatchArgs, Command, ConfigArgs, ConfigCommand, DiffArgs, ErrorFormat, OutputDestination,
+    OutputFormat, OverlapArgs, ReportArgs, ScanArgs, SchemaArgs, ServeArgs, WatchArgs, WebhookArgs,
+};
+pub use batch::{read_wallets, run_batch, BatchReport, TargetScan};
+pub use cluster::clusters;
+pub use config::{parse_args, Config, DEFAULT_CONFIG_FILE};
 use console::Emoji;
-pub use core::run;
+pub use core::{
+    parsed_tx_slot, run, signature_paging_stop, signatures_address, swap_pool, PagingStop, Scanner,
+    SwapAnalysis,
+};
+pub use diff::{diff, load_diff_scans, LeaderChange, LoadedScan, ScanDiff};
 pub use error::Error;
+#[cfg(feature = "yellowstone")]
+pub use geyser::{decode_buy, subscribe_request, GeyserStream};
+pub use html::write_html;
+pub use metrics::{render_metrics, serve_metrics};
+pub use mev::{is_sell, pool_addresses, touches_any};
+pub use output::{json_schema, write_found, write_report, ScanReport, SCHEMA_VERSION};
+pub use overlap::{load_scans, overlap, LeaderOverlap, OverlapReport};
+pub use pnl::{compare_outcomes, leader_pnl, Trade, TradeSide};
+pub use range::RangeBound;
+pub use server::{serve, Job, JobProgress, JobStatus, ScanRequest};
+pub use sizing::size_correlation;
+pub use store::Store;
+pub use stream::{MintHistory, StreamEvent, SwapStream};
+pub use token::{group_by_token, launch_page_outcome, parse_metadata, LaunchPage, TokenLaunch};
+pub use watch::{run_watch, Alert, AlertKind, LeaderStats, TargetStats, WatchEvent, WatchedWallet};
+pub use webhook::{Notification, Notifier};
 
 /// Emoji for file representation.
 pub static FILE: Emoji<'_, '_> = Emoji("📝", "");
@@ -26,22 +72,255 @@ pub static CHECK: Emoji<'_, '_> = Emoji("✅", "");
 /// Emoji for lightning representation.
 static LIGHTNING: Emoji<'_, '_> = Emoji("⚡️", "");
 /// Emoji for scanning representation.
-static SCAN: Emoji<'_, '_> = Emoji("🔍", "");
+pub static SCAN: Emoji<'_, '_> = Emoji("🔍", "");
 
 /// Represents a repeating wallet with its transactions.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RepeatingWallet {
     /// The wallet address.
     pub wallet: String,
     /// The transactions.
     pub txs: Vec<PrevBuy>,
+    /// The sandwiches the wallet executed around the scanned wallet's swaps, which flag it as MEV.
+    #[serde(default)]
+    pub sandwiches: Vec<Sandwich>,
+    /// The SOL transfers that funded the wallet, if funding was traced.
+    #[serde(default)]
+    pub funding: Vec<Funding>,
+    /// The id of the funding cluster the wallet belongs to, if any.
+    #[serde(default)]
+    pub cluster: Option<usize>,
+    /// The realised profit of the wallet on the scanned tokens, if it was analysed.
+    #[serde(default)]
+    pub pnl: Option<LeaderPnl>,
+    /// How the scanned wallet and the wallet exited the tokens they both bought, if compared.
+    #[serde(default)]
+    pub outcomes: Option<OutcomeComparison>,
+    /// How the scanned wallet's buy sizes relate to the wallet's, if enough sizes are known.
+    #[serde(default)]
+    pub sizing: Option<SizeCorrelation>,
+}
+
+impl RepeatingWallet {
+    /// Returns whether the wallet was caught sandwiching the scanned wallet's swaps.
+    pub fn is_mev(&self) -> bool {
+        !self.sandwiches.is_empty()
+    }
 }
 
 /// Represents a previous buy transaction with its hash and block difference.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct PrevBuy {
     /// The transaction hash.
     pub tx_hash: String,
+    /// The slot the buy landed in.
+    #[serde(default)]
+    pub slot: u64,
     /// The difference in blocks.
     pub block_diff: u64,
+    /// The mint of the bought token, as reported for the scanned wallet's swap.
+    #[serde(default)]
+    pub mint: String,
+    /// The SOL spent on the buy, 0 if unknown.
+    #[serde(default)]
+    pub sol_amount: f64,
+    /// The amount of tokens received, 0 if unknown.
+    #[serde(default)]
+    pub token_amount: f64,
+    /// The SOL the scanned wallet spent on its swap, 0 if unknown or not paid in SOL.
+    #[serde(default)]
+    pub target_sol_amount: f64,
+    /// The symbol of the bought token, empty if unknown.
+    #[serde(default)]
+    pub symbol: String,
+    /// The name of the bought token, empty if unknown.
+    #[serde(default)]
+    pub name: String,
+    /// The decimals of the bought token, if known.
+    #[serde(default)]
+    pub decimals: Option<u8>,
+    /// The slot the bought token was created in, if found.
+    #[serde(default)]
+    pub token_created_slot: Option<u64>,
+    /// The age of the bought token at the scanned wallet's swap in seconds, if known.
+    #[serde(default)]
+    pub token_age_secs: Option<i64>,
+    /// The platform the bought token was launched on, if found.
+    #[serde(default)]
+    pub launch_platform: Option<LaunchPlatform>,
+    /// Whether the bought token had left its launchpad at the scanned wallet's swap, if known.
+    #[serde(default)]
+    pub migrated: Option<bool>,
+}
+
+/// The platform a token was launched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchPlatform {
+    /// The Pump.fun bonding curve.
+    PumpFun,
+    /// The Raydium LaunchLab bonding curve, used by LetsBonk among others.
+    RaydiumLaunchLab,
+    /// The Moonshot bonding curve.
+    Moonshot,
+    /// The Meteora Dynamic Bonding Curve, used by Believe among others.
+    MeteoraDbc,
+    /// Any other program, e.g. a token created directly with a Raydium or Meteora pool.
+    Other,
+}
+
+/// Represents a token the scanned wallet and its leaders both bought.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TokenOverlap {
+    /// The token mint address.
+    pub mint: String,
+    /// The token symbol, empty if unknown.
+    pub symbol: String,
+    /// The token name, empty if unknown.
+    pub name: String,
+    /// The token decimals, if known.
+    pub decimals: Option<u8>,
+    /// The platform the token was launched on, if found.
+    #[serde(default)]
+    pub launch_platform: Option<LaunchPlatform>,
+    /// The slot the token was created in, if found.
+    #[serde(default)]
+    pub token_created_slot: Option<u64>,
+    /// The detected wallets that bought the token before the scanned wallet.
+    pub leaders: Vec<String>,
+    /// The number of copied swaps on the token across the detected wallets.
+    pub copy_count: usize,
+}
+
+/// The relation between the buy sizes of the scanned wallet and a detected wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizePattern {
+    /// The scanned wallet spends the same amount whatever the detected wallet spends.
+    FixedSize,
+    /// The scanned wallet spends a constant share of what the detected wallet spends.
+    Proportional,
+    /// The sizes follow no pattern.
+    Uncorrelated,
+}
+
+/// Represents how the buy sizes of the scanned wallet relate to a detected wallet's.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SizeCorrelation {
+    /// The detected relation.
+    pub pattern: SizePattern,
+    /// The number of copied buys with known sizes on both sides.
+    pub pairs: usize,
+    /// The average SOL spent by the scanned wallet per copied buy.
+    pub avg_target_sol: f64,
+    /// The average ratio of the scanned wallet's size to the detected wallet's size.
+    pub avg_ratio: f64,
+    /// The coefficient of variation of the scanned wallet's sizes.
+    pub target_size_cv: f64,
+    /// The coefficient of variation of the size ratios.
+    pub ratio_cv: f64,
+}
+
+/// Represents the realised profit of a wallet on the scanned tokens.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct LeaderPnl {
+    /// The realised profit across all tokens in SOL.
+    pub realised_pnl_sol: f64,
+    /// The share of the sold tokens that were sold at a profit, if any were sold.
+    pub win_rate: Option<f64>,
+    /// The average time from the first buy to the last sell of the sold tokens in seconds.
+    pub avg_hold_secs: Option<f64>,
+    /// The trades per token.
+    pub tokens: Vec<TokenPnl>,
+}
+
+/// Represents the trades of a wallet on a single token.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct TokenPnl {
+    /// The token mint address.
+    pub mint: String,
+    /// The number of buys.
+    pub buy_count: usize,
+    /// The number of sells.
+    pub sell_count: usize,
+    /// The SOL spent on buys.
+    pub sol_spent: f64,
+    /// The SOL received from sells.
+    pub sol_received: f64,
+    /// The average price paid in SOL per token, if bought.
+    pub entry_price: Option<f64>,
+    /// The average price received in SOL per token, if sold.
+    pub exit_price: Option<f64>,
+    /// The realised profit in SOL, against the average cost of the sold tokens.
+    pub realised_pnl_sol: f64,
+    /// The realised profit relative to the cost of the sold tokens, if any were sold.
+    pub roi: Option<f64>,
+    /// The amount of tokens still held after the last trade.
+    pub tokens_held: f64,
+    /// The time from the first buy to the last sell in seconds, if sold.
+    pub hold_secs: Option<i64>,
+}
+
+/// Represents how the scanned wallet and a detected wallet traded the tokens they both bought.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct OutcomeComparison {
+    /// The trades of both wallets per token.
+    pub tokens: Vec<TokenComparison>,
+    /// The average return of the scanned wallet on the tokens both wallets sold.
+    pub target_avg_roi: Option<f64>,
+    /// The average return of the detected wallet on the tokens both wallets sold.
+    pub leader_avg_roi: Option<f64>,
+    /// The share of the detected wallet's average return captured by the scanned wallet, if the
+    /// detected wallet made a profit.
+    pub captured_return: Option<f64>,
+}
+
+/// Represents the trades of the scanned wallet and a detected wallet on a single token.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TokenComparison {
+    /// The token mint address.
+    pub mint: String,
+    /// The trades of the scanned wallet.
+    pub target: TokenPnl,
+    /// The trades of the detected wallet.
+    pub leader: TokenPnl,
+}
+
+/// Represents a sandwich around one of the scanned wallet's swaps.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Sandwich {
+    /// The scanned wallet's swap transaction hash.
+    pub target_tx_hash: String,
+    /// The front-running buy transaction hash.
+    pub front_run_tx_hash: String,
+    /// The back-running sell transaction hash.
+    pub back_run_tx_hash: String,
+    /// The slot of the scanned wallet's swap.
+    pub slot: u64,
+}
+
+/// Represents a SOL transfer that funded a wallet.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Funding {
+    /// The address the SOL came from.
+    pub source: String,
+    /// The amount transferred in lamports.
+    pub lamports: u64,
+    /// The transfer transaction hash.
+    pub tx_hash: String,
+    /// Whether the transfer is among the first transactions of the wallet's history.
+    pub initial: bool,
+}
+
+/// Represents a group of wallets sharing a funding source, reported as a single entity.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WalletCluster {
+    /// The cluster id, as referenced by the member wallets.
+    pub id: usize,
+    /// The member wallet addresses.
+    pub wallets: Vec<String>,
+    /// The addresses that funded the member wallets.
+    pub funders: Vec<String>,
+    /// The number of copied swaps across all member wallets.
+    pub copy_count: usize,
 }
@@ -1,13 +1,19 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, BufWriter, IsTerminal, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
-use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
-use solana_copy_trade_detect::{get_spinner, Args, RepeatingWallet};
+use solana_copy_trade_detect::{
+    get_spinner, print_if_terminal, write_found, write_report, Args, BatchArgs, BatchReport,
+    Command, Config, ConfigArgs, ConfigCommand, DiffArgs, ErrorFormat, Notification, Notifier,
+    OutputDestination, OutputFormat, OverlapArgs, OverlapReport, RepeatingWallet, ReportArgs,
+    ScanDiff, Scanner, WatchArgs, WatchEvent,
+};
+use tokio::task::JoinSet;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 #[tokio::main]
@@ -21,46 +27,767 @@ async fn main() {
     let file_layer = tracing_subscriber::fmt::layer()
         .json()
         .with_writer(file_writer)
-        .with_filter(EnvFilter::new("solana_copy_trade_detect::core=debug"));
+        .with_filter(EnvFilter::new("solana_copy_trade_detect=debug"));
 
     tracing_subscriber::registry().with(file_layer).init();
 
-    let args = Args::parse();
-    let result = solana_copy_trade_detect::run(&args).await;
-
-    match result {
-        Ok(repeating_wallets) => {
-            if io::stdout().is_terminal() {
-                let file_path = args
-                    .output_file
-                    .unwrap_or(PathBuf::from(format!("{}.txt", args.wallet)));
-
-                let spinner = get_spinner!(format!(
-                    "{} {}Writing output to {}",
-                    console::style("[3/3]").bold().dim(),
-                    solana_copy_trade_detect::FILE,
-                    file_path.display()
-                ));
-
-                write_to_file(repeating_wallets, &file_path).expect("Failed to write to file");
-                spinner.finish();
-
-                println!("\t\t{}Done!", solana_copy_trade_detect::CHECK);
-            } else {
-                println!("{}", serde_json::to_string(&repeating_wallets).unwrap());
+    let args = match solana_copy_trade_detect::parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match args.command {
+        Some(Command::Batch(batch_args)) => batch(batch_args).await,
+        Some(Command::Config(ConfigArgs {
+            command: ConfigCommand::Show { show_secrets },
+        })) => match Config::load(args.config.as_deref(), args.profile.as_deref()) {
+            Ok(config) => print!("{}", config.show(show_secrets)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Diff(diff_args)) => diff(diff_args),
+        Some(Command::Overlap(overlap_args)) => overlap(overlap_args),
+        Some(Command::Serve(serve_args)) => {
+            if let Err(e) = solana_copy_trade_detect::serve(serve_args).await {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
             }
         }
-        Err(e) => {
-            eprintln!("Error: {:?}", e);
+        Some(Command::Schema(schema_args)) => {
+            match solana_copy_trade_detect::json_schema(schema_args.format) {
+                Some(schema) => println!("{}", serde_json::to_string_pretty(&schema).unwrap()),
+                None => {
+                    eprintln!(
+                        "Error: The {} format is meant for humans and has no schema",
+                        schema_args.format
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::Watch(watch_args)) => watch(watch_args).await,
+        None => scan(args).await,
+    }
+}
+
+/// Scans a single wallet and writes its output.
+///
+/// The output is written in the requested format to the requested destination, whether or not
+/// the output is a terminal. JSON, JSON Lines and CSV go to stdout by default, text and HTML to a
+/// file named after the wallet. JSON Lines are written as the copied buys are found, followed by
+/// the detected wallets. Errors are printed to stderr in the requested format and exit with a
+/// non-zero code.
+///
+/// # Arguments
+///
+/// * `args` - The command line arguments.
+async fn scan(args: Args) {
+    let (Some(scan_args), Some(wallet)) = (args.scan.as_ref(), args.wallet) else {
+        unreachable!("clap requires the scan arguments when no subcommand is given");
+    };
+    let mut scanner = match Scanner::new(scan_args) {
+        Ok(scanner) => scanner,
+        Err(e) => fail(args.error_format, &e),
+    };
+    if args.quiet {
+        // Report the progress nowhere instead of drawing progress bars
+        scanner = scanner.with_progress(|_, _| {});
+    }
+
+    let destination = args.output.unwrap_or(match args.format {
+        OutputFormat::Json | OutputFormat::Jsonl | OutputFormat::Csv
+            if args.output_file.is_none() =>
+        {
+            OutputDestination::Stdout
+        }
+        _ => OutputDestination::File,
+    });
+    let file_path = (destination != OutputDestination::Stdout).then(|| {
+        args.output_file.clone().unwrap_or(PathBuf::from(format!(
+            "{}.{}",
+            wallet,
+            args.format.extension()
+        )))
+    });
+
+    // JSON Lines stream the copied buys as they are found, so the file is created up front
+    let mut jsonl_file = None;
+    if args.format == OutputFormat::Jsonl {
+        if let Some(file_path) = file_path.as_ref() {
+            match File::create(file_path) {
+                Ok(file) => jsonl_file = Some(Arc::new(Mutex::new(BufWriter::new(file)))),
+                Err(e) => fail(args.error_format, &e.into()),
+            }
+        }
+        let file = jsonl_file.clone();
+        let stdout = destination != OutputDestination::File;
+        let target = wallet.to_string();
+        scanner = scanner.with_found(move |leader, buy| {
+            if let Some(file) = file.as_ref() {
+                let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                if let Err(e) = write_found(&mut *file, &target, leader, buy) {
+                    tracing::warn!("Failed to write the buy {}: {}", buy.tx_hash, e);
+                }
+            }
+            if stdout {
+                if let Err(e) = write_found(&mut io::stdout().lock(), &target, leader, buy) {
+                    tracing::warn!("Failed to write the buy {}: {}", buy.tx_hash, e);
+                }
+            }
+        });
+    }
+
+    let repeating_wallets = match scanner.scan(&wallet).await {
+        Ok(repeating_wallets) => repeating_wallets,
+        Err(e) => fail(args.error_format, &e),
+    };
+    let wallet = wallet.to_string();
+
+    if let Some(notifier) = Notifier::new(&args.webhook) {
+        notifier
+            .notify(&Notification::Report {
+                wallet: wallet.clone(),
+                leaders: repeating_wallets.clone(),
+            })
+            .await;
+    }
+
+    if let Some(file_path) = file_path {
+        let spinner = (!args.quiet).then(|| {
+            get_spinner!(format!(
+                "{} {}Writing output to {}",
+                console::style("[3/3]").bold().dim(),
+                solana_copy_trade_detect::FILE,
+                file_path.display()
+            ))
+        });
+        let written = match jsonl_file {
+            Some(file) => {
+                let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                write_output(&mut *file, args.format, &wallet, &repeating_wallets)
+            }
+            None => File::create(&file_path).and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                write_output(&mut writer, args.format, &wallet, &repeating_wallets)
+            }),
+        };
+        if let Err(e) = written {
+            fail(args.error_format, &e.into());
+        }
+        if let Some(spinner) = spinner {
+            spinner.finish();
+        }
+    }
+    if destination != OutputDestination::File {
+        let written = write_output(
+            &mut io::stdout().lock(),
+            args.format,
+            &wallet,
+            &repeating_wallets,
+        );
+        if let Err(e) = written {
+            fail(args.error_format, &e.into());
+        }
+    } else if !args.quiet {
+        println!("\t\t{}Done!", solana_copy_trade_detect::CHECK);
+    }
+}
+
+/// Prints an error to stderr in the requested format and exits with a non-zero code.
+///
+/// # Arguments
+///
+/// * `format` - The format of the error message.
+/// * `error` - The error.
+fn fail(format: ErrorFormat, error: &solana_copy_trade_detect::Error) -> ! {
+    match (format, error) {
+        (ErrorFormat::Json, _) => eprintln!(
+            "{}",
+            serde_json::json!({
+                "error": { "kind": error.kind(), "message": error.to_string() }
+            })
+        ),
+        (ErrorFormat::Text, solana_copy_trade_detect::Error::NoFreshSwaps(wallet)) => {
+            eprintln!(
+                "\n{}",
+                console::style("Error: Cielo API returned no swaps for the given wallet.")
+                    .red()
+                    .bold()
+            );
+            eprintln!("This may happen if the wallet is not on your watchlist or is a bot wallet that Cielo does not support. Please check the wallet page on Cielo.");
+            eprintln!("https://app.cielo.finance/profile/{}\n", wallet);
+            eprintln!("Exiting...");
+        }
+        (ErrorFormat::Text, _) => {
+            eprintln!("Error: {:?}", error);
             eprintln!("Reach out to @Lezend on Discord for support");
         }
     }
+    std::process::exit(1);
 }
 
-/// Writes the repeating wallets and their transactions to a file.
+/// Writes the detected wallets of a scan in any output format.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `format` - The output format.
+/// * `wallet` - The scanned wallet address.
+/// * `repeating_wallets` - The detected wallets.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_output(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    wallet: &str,
+    repeating_wallets: &[RepeatingWallet],
+) -> Result<(), io::Error> {
+    match format {
+        OutputFormat::Text => write_text(writer, repeating_wallets),
+        _ => write_report(writer, format, wallet, repeating_wallets),
+    }
+}
+
+/// Runs the `batch` subcommand.
+///
+/// This function reads the wallets to scan, scans them and writes the combined report and the
+/// per-wallet outputs to the output directory. The combined report is also printed to stdout, as
+/// a summary of the shared leaders in text or in full as JSON. Errors are printed to stderr in the
+/// requested format and exit with a non-zero code.
+///
+/// # Arguments
+///
+/// * `args` - The batch arguments.
+async fn batch(args: BatchArgs) {
+    let error_format = args.report.error_format;
+    if !matches!(args.report.format, OutputFormat::Text | OutputFormat::Json) {
+        fail(
+            error_format,
+            &solana_copy_trade_detect::Error::UnsupportedFormat(args.report.format, "batch"),
+        );
+    }
+    let wallets = match solana_copy_trade_detect::read_wallets(args.input.as_deref()) {
+        Ok(wallets) => wallets,
+        Err(e) => fail(error_format, &e.into()),
+    };
+    if wallets.is_empty() {
+        fail(error_format, &solana_copy_trade_detect::Error::NoWallets);
+    }
+
+    let report = match solana_copy_trade_detect::run_batch(&args, &wallets).await {
+        Ok(report) => report,
+        Err(e) => fail(error_format, &e),
+    };
+
+    let destination = args.report.output.unwrap_or(OutputDestination::Both);
+    if destination != OutputDestination::Stdout {
+        let spinner = get_spinner!(format!(
+            "{} {}Writing outputs to {}",
+            console::style("[3/3]").bold().dim(),
+            solana_copy_trade_detect::FILE,
+            args.output_dir.display()
+        ));
+        if let Err(e) = write_batch_outputs(&report, &args.output_dir) {
+            fail(error_format, &e.into());
+        }
+        spinner.finish();
+    }
+
+    if let Some(notifier) = Notifier::new(&args.webhook) {
+        notifier.notify(&Notification::Batch(report.clone())).await;
+    }
+
+    if destination == OutputDestination::File {
+        return;
+    }
+    if args.report.format == OutputFormat::Json {
+        let written = serde_json::to_string(&report)
+            .map_err(io::Error::from)
+            .and_then(|report| writeln!(io::stdout().lock(), "{}", report));
+        if let Err(e) = written {
+            fail(error_format, &e.into());
+        }
+        return;
+    }
+    for leader in report.overlap.leaders.iter() {
+        println!(
+            "{} {} is copied by {} wallets",
+            console::style("Shared leader:").yellow().bold(),
+            leader.wallet,
+            leader.target_count
+        );
+    }
+    println!("\t\t{}Done!", solana_copy_trade_detect::CHECK);
+}
+
+/// Writes the outputs of a batch scan to a directory.
+///
+/// This function writes the combined report as `report.txt` and `report.json`, and the result of
+/// every successfully scanned wallet as `<wallet>.txt` and `<wallet>.json`.
+///
+/// # Arguments
+///
+/// * `report` - The batch report.
+/// * `dir` - The output directory, created if it does not exist.
+///
+/// # Errors
+///
+/// This function will return an error if the directory or any of the files cannot be written.
+fn write_batch_outputs(report: &BatchReport, dir: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(dir)?;
+
+    for target in report.targets.iter().filter(|t| t.error.is_none()) {
+        write_to_file(&target.leaders, &dir.join(format!("{}.txt", target.wallet)))?;
+        fs::write(
+            dir.join(format!("{}.json", target.wallet)),
+            serde_json::to_string_pretty(&target.leaders)?,
+        )?;
+    }
+
+    fs::write(
+        dir.join("report.json"),
+        serde_json::to_string_pretty(report)?,
+    )?;
+
+    let mut writer = BufWriter::new(File::create(dir.join("report.txt"))?);
+    let failed = report.targets.iter().filter(|t| t.error.is_some()).count();
+    writeln!(
+        writer,
+        "Scanned {} wallets ({} failed)",
+        report.targets.len(),
+        failed
+    )?;
+    writeln!(writer, "----------------------------------------")?;
+    write_overlap(&mut writer, &report.overlap)?;
+
+    for target in report.targets.iter() {
+        writeln!(writer, "----------------------------------------")?;
+        writeln!(writer, "Wallet: {}", target.wallet)?;
+        match &target.error {
+            Some(error) => writeln!(writer, "Error: {}", error)?,
+            None => {
+                for leader in target.leaders.iter() {
+                    let shared = report
+                        .overlap
+                        .leaders
+                        .iter()
+                        .any(|s| s.wallet == leader.wallet);
+                    writeln!(
+                        writer,
+                        "{} {} ({} copied swaps{})",
+                        if shared { "*" } else { "-" },
+                        leader.wallet,
+                        leader.txs.len(),
+                        if leader.is_mev() { ", MEV" } else { "" }
+                    )?;
+                }
+            }
+        }
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Runs the `overlap` subcommand.
+///
+/// This function loads saved scan outputs and aggregates them into an overlap matrix. The matrix
+/// is written as a table or as JSON to the output file, stdout or both. Errors are printed to
+/// stderr in the requested format and exit with a non-zero code.
+///
+/// # Arguments
+///
+/// * `args` - The overlap arguments.
+fn overlap(args: OverlapArgs) {
+    let (file_path, stdout) = report_destination(&args.report, args.output_file, "overlap");
+    let targets = match solana_copy_trade_detect::load_scans(&args.inputs) {
+        Ok(targets) => targets,
+        Err(e) => fail(args.report.error_format, &e),
+    };
+    let report = solana_copy_trade_detect::overlap(&targets, args.min_targets);
+
+    let format = args.report.format;
+    write_report_output(
+        &args.report,
+        file_path,
+        stdout,
+        "Overlap",
+        |mut writer| match format {
+            OutputFormat::Text => write_overlap(&mut writer, &report),
+            _ => writeln!(writer, "{}", serde_json::to_string(&report)?),
+        },
+    );
+}
+
+/// Runs the `diff` subcommand.
+///
+/// This function loads two scans of a wallet and reports how its leaders changed. The diff is
+/// written as text or as JSON to the output file, stdout or both. Errors are printed to stderr in
+/// the requested format and exit with a non-zero code.
+///
+/// # Arguments
+///
+/// * `args` - The diff arguments.
+fn diff(args: DiffArgs) {
+    let (file_path, stdout) = report_destination(&args.report, args.output_file.clone(), "diff");
+    let (old, new) = match solana_copy_trade_detect::load_diff_scans(&args) {
+        Ok(scans) => scans,
+        Err(e) => fail(args.report.error_format, &e),
+    };
+    if old.scan.wallet != new.scan.wallet {
+        tracing::warn!(
+            "Comparing scans of {} and {}",
+            old.scan.wallet,
+            new.scan.wallet
+        );
+    }
+    let report =
+        solana_copy_trade_detect::diff(&old, &new, args.min_copy_change, args.min_lag_change);
+
+    let format = args.report.format;
+    write_report_output(
+        &args.report,
+        file_path,
+        stdout,
+        "Diff",
+        |mut writer| match format {
+            OutputFormat::Text => write_diff(&mut writer, &report),
+            _ => writeln!(writer, "{}", serde_json::to_string(&report)?),
+        },
+    );
+}
+
+/// Resolves where a report subcommand writes its output.
+///
+/// The output goes to the output file if one is given and to stdout otherwise, unless a
+/// destination is requested. A file destination without an output file writes to a file named
+/// after the subcommand with the format extension. Only the text and JSON formats are supported,
+/// any other format fails with an error in the requested format.
+///
+/// # Arguments
 ///
-/// This function creates a new file at the specified path and writes the details
-/// of each repeating wallet, including the number of repeating transactions and their signatures.
+/// * `report` - The output options of the subcommand.
+/// * `output_file` - The output file, if given.
+/// * `name` - The name of the subcommand.
+///
+/// # Returns
+///
+/// The file to write to, if any, and whether to write to stdout.
+fn report_destination(
+    report: &ReportArgs,
+    output_file: Option<PathBuf>,
+    name: &'static str,
+) -> (Option<PathBuf>, bool) {
+    if !matches!(report.format, OutputFormat::Text | OutputFormat::Json) {
+        fail(
+            report.error_format,
+            &solana_copy_trade_detect::Error::UnsupportedFormat(report.format, name),
+        );
+    }
+    let destination = report.output.unwrap_or(match output_file {
+        Some(_) => OutputDestination::File,
+        None => OutputDestination::Stdout,
+    });
+    let file_path = (destination != OutputDestination::Stdout).then(|| {
+        output_file.unwrap_or(PathBuf::from(format!(
+            "{}.{}",
+            name,
+            report.format.extension()
+        )))
+    });
+    (file_path, destination != OutputDestination::File)
+}
+
+/// Writes the output of a report subcommand to its file and stdout.
+///
+/// Errors are printed to stderr in the requested format and exit with a non-zero code.
+///
+/// # Arguments
+///
+/// * `report` - The output options of the subcommand.
+/// * `file_path` - The file to write to, if any.
+/// * `stdout` - Whether to write to stdout.
+/// * `title` - The title of the output, shown once it is written to the file.
+/// * `write` - Writes the output to a writer.
+fn write_report_output(
+    report: &ReportArgs,
+    file_path: Option<PathBuf>,
+    stdout: bool,
+    title: &str,
+    write: impl Fn(&mut dyn Write) -> Result<(), io::Error>,
+) {
+    if let Some(file_path) = file_path {
+        let written = File::create(&file_path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()
+        });
+        if let Err(e) = written {
+            fail(report.error_format, &e.into());
+        }
+        if !stdout {
+            print_if_terminal!(
+                "{}{} written to {}",
+                solana_copy_trade_detect::FILE,
+                title,
+                file_path.display()
+            );
+        }
+    }
+    if stdout {
+        if let Err(e) = write(&mut io::stdout().lock()) {
+            fail(report.error_format, &e.into());
+        }
+    }
+}
+
+/// Runs the `watch` subcommand.
+///
+/// This function polls the watched wallets until interrupted with Ctrl-C. Alerts and analysed
+/// swaps are printed as they happen, styled in the text format or as JSON lines otherwise. The
+/// final statistics are printed on exit. Errors are printed to stderr in the requested format and
+/// exit with a non-zero code.
+///
+/// # Arguments
+///
+/// * `args` - The watch arguments.
+async fn watch(args: WatchArgs) {
+    let text = match args.format {
+        OutputFormat::Text => true,
+        OutputFormat::Json | OutputFormat::Jsonl => false,
+        format => fail(
+            args.error_format,
+            &solana_copy_trade_detect::Error::UnsupportedFormat(format, "watch"),
+        ),
+    };
+    let mut wallets = args.wallets.clone();
+    if let Some(input) = args.input.as_deref() {
+        match solana_copy_trade_detect::read_wallets(Some(input)) {
+            Ok(read) => wallets.extend(read.into_iter().filter(|w| !args.wallets.contains(w))),
+            Err(e) => fail(args.error_format, &e.into()),
+        }
+    }
+    if wallets.is_empty() {
+        fail(
+            args.error_format,
+            &solana_copy_trade_detect::Error::NoWallets,
+        );
+    }
+
+    let notifier = Notifier::new(&args.webhook);
+    let mut deliveries = JoinSet::new();
+    if text {
+        println!(
+            "{}Watching {} wallets every {}s, press Ctrl-C to stop",
+            solana_copy_trade_detect::SCAN,
+            wallets.len(),
+            args.interval_secs
+        );
+    }
+
+    let on_event = |event: &WatchEvent| {
+        if let (Some(notifier), WatchEvent::Alert(alert)) = (notifier.as_ref(), event) {
+            let notifier = notifier.clone();
+            let alert = alert.clone();
+            // Reap the delivered alerts so that a long watch does not pile them up
+            while deliveries.try_join_next().is_some() {}
+            deliveries.spawn(async move {
+                notifier.notify(&Notification::Alert(alert)).await;
+            });
+        }
+        if !text {
+            println!("{}", serde_json::to_string(event).unwrap());
+            return;
+        }
+        match event {
+            WatchEvent::Swap {
+                target,
+                tx_hash,
+                prev_buys,
+            } => println!(
+                "{}",
+                console::style(format!(
+                    "{} swap {} preceded by {} buys",
+                    target, tx_hash, prev_buys
+                ))
+                .dim()
+            ),
+            WatchEvent::Alert(alert) => println!(
+                "{} {} copied by {} in {}/{} swaps ({:.0}%)",
+                console::style("Alert:").yellow().bold(),
+                alert.target,
+                alert.leader,
+                alert.copy_count,
+                alert.swaps_seen,
+                alert.score * 100.0
+            ),
+            WatchEvent::MintTrade {
+                target,
+                mint,
+                signature,
+                ..
+            } => println!(
+                "{}",
+                console::style(format!(
+                    "{} trade {} on mint {} bought by {}",
+                    console::style("Mint:").cyan(),
+                    signature,
+                    mint,
+                    target
+                ))
+                .dim()
+            ),
+        }
+    };
+
+    let stats = match solana_copy_trade_detect::run_watch(&args, &wallets, on_event).await {
+        Ok(stats) => stats,
+        Err(e) => fail(args.error_format, &e),
+    };
+    // Wait for the alerts still being delivered
+    while deliveries.join_next().await.is_some() {}
+
+    if text {
+        for target in stats.iter() {
+            println!(
+                "\n{} ({} swaps analysed)",
+                console::style(&target.wallet).bold(),
+                target.swaps_seen
+            );
+            for leader in target.leaders.iter() {
+                println!(
+                    "  {} copied {} times ({:.0}%), avg block diff {:.1}{}",
+                    leader.wallet,
+                    leader.copy_count,
+                    leader.score * 100.0,
+                    leader.avg_block_diff,
+                    if leader.mev { ", MEV" } else { "" }
+                );
+            }
+        }
+        println!("\t\t{}Done!", solana_copy_trade_detect::CHECK);
+    } else {
+        println!("{}", serde_json::to_string(&stats).unwrap());
+    }
+}
+
+/// Writes an overlap matrix as a table.
+///
+/// Every row is a leader and every column a scanned wallet, with the number of copied swaps in
+/// each cell. The scanned wallets are listed with their column number above the table.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write the table to.
+/// * `report` - The overlap report.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_overlap(writer: &mut impl Write, report: &OverlapReport) -> Result<(), io::Error> {
+    writeln!(
+        writer,
+        "Detected {} leaders shared across {} wallets",
+        report.leaders.len(),
+        report.targets.len()
+    )?;
+    for (column, target) in report.targets.iter().enumerate() {
+        writeln!(writer, "T{:<4} {}", column + 1, target)?;
+    }
+    if report.leaders.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(writer)?;
+    write!(writer, "{:<44} {:>7} {:>6}", "Leader", "Targets", "Swaps")?;
+    for column in 0..report.targets.len() {
+        write!(writer, " {:>5}", format!("T{}", column + 1))?;
+    }
+    writeln!(writer)?;
+
+    for leader in report.leaders.iter() {
+        write!(
+            writer,
+            "{:<44} {:>7} {:>6}",
+            leader.wallet, leader.target_count, leader.copy_count
+        )?;
+        for hits in leader.hits.iter() {
+            match hits {
+                0 => write!(writer, " {:>5}", ".")?,
+                hits => write!(writer, " {:>5}", hits)?,
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a scan diff as text.
+///
+/// New, dropped and changed leaders are listed in sections with their copy count and average lag
+/// in both scans.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write the diff to.
+/// * `report` - The scan diff.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_diff(writer: &mut impl Write, report: &ScanDiff) -> Result<(), io::Error> {
+    writeln!(writer, "Leaders of {}", report.wallet)?;
+    writeln!(writer, "Old: {}", report.old)?;
+    writeln!(writer, "New: {}", report.new)?;
+    writeln!(
+        writer,
+        "{} new, {} dropped, {} changed, {} unchanged",
+        report.new_leaders.len(),
+        report.dropped_leaders.len(),
+        report.changed_leaders.len(),
+        report.unchanged_count
+    )?;
+
+    let lag = |lag: Option<f64>| lag.map_or("-".to_owned(), |lag| format!("{:.1}", lag));
+    for (title, leaders) in [
+        ("New leaders", &report.new_leaders),
+        ("Dropped leaders", &report.dropped_leaders),
+        ("Changed leaders", &report.changed_leaders),
+    ] {
+        if leaders.is_empty() {
+            continue;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "{}", title)?;
+        writeln!(
+            writer,
+            "{:<44} {:>6} {:>6} {:>8} {:>8}",
+            "Leader", "Swaps", "Was", "Lag", "Was"
+        )?;
+        for leader in leaders.iter() {
+            writeln!(
+                writer,
+                "{:<44} {:>6} {:>6} {:>8} {:>8}",
+                leader.wallet,
+                leader.new_copy_count,
+                leader.old_copy_count,
+                lag(leader.new_avg_lag),
+                lag(leader.old_avg_lag)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the repeating wallets and their transactions to a file.
 ///
 /// # Arguments
 ///
@@ -70,28 +797,137 @@ async fn main() {
 /// # Errors
 ///
 /// This function will return an error if the file cannot be created or written to.
-fn write_to_file(
-    repeating_wallets: Vec<RepeatingWallet>,
-    file_path: &PathBuf,
-) -> Result<(), io::Error> {
+fn write_to_file(repeating_wallets: &[RepeatingWallet], file_path: &Path) -> Result<(), io::Error> {
     let file = File::create(file_path)?;
-    let mut writer = BufWriter::new(file);
+    write_text(&mut BufWriter::new(file), repeating_wallets)
+}
 
+/// Writes the repeating wallets and their transactions as a text report.
+///
+/// This function writes the details of each repeating wallet, including the number of repeating
+/// transactions and their signatures. Wallets flagged as MEV are listed with the sandwiches they
+/// were caught executing, and wallets sharing a funding source are summarized as clusters.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `repeating_wallets` - A vector of repeating wallets with their transactions.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+fn write_text(
+    writer: &mut impl Write,
+    repeating_wallets: &[RepeatingWallet],
+) -> Result<(), io::Error> {
+    let mev_count = repeating_wallets.iter().filter(|w| w.is_mev()).count();
     writeln!(
         writer,
         "Detected {} potential copied wallets",
-        repeating_wallets.len()
+        repeating_wallets.len() - mev_count
     )?;
+    if mev_count > 0 {
+        writeln!(writer, "Flagged {} wallets as MEV (sandwich)", mev_count)?;
+    }
+
+    for cluster in solana_copy_trade_detect::clusters(repeating_wallets) {
+        writeln!(writer, "----------------------------------------")?;
+        writeln!(writer, "Cluster #{}", cluster.id)?;
+        writeln!(writer, "Wallets: {}", cluster.wallets.join(", "))?;
+        writeln!(writer, "Funded by: {}", cluster.funders.join(", "))?;
+        writeln!(writer, "Number of copied swaps: {}", cluster.copy_count)?;
+    }
+
+    for token in solana_copy_trade_detect::group_by_token(repeating_wallets) {
+        writeln!(writer, "----------------------------------------")?;
+        match token.symbol.is_empty() {
+            true => writeln!(writer, "Token: {}", token.mint)?,
+            false if token.name.is_empty() => {
+                writeln!(writer, "Token: {} ({})", token.symbol, token.mint)?
+            }
+            false => writeln!(
+                writer,
+                "Token: {} - {} ({})",
+                token.symbol, token.name, token.mint
+            )?,
+        }
+        if let Some(platform) = token.launch_platform {
+            match token.token_created_slot {
+                Some(slot) => writeln!(
+                    writer,
+                    "Launched on: {} in slot {}",
+                    platform.to_string().replace('_', " "),
+                    slot
+                )?,
+                None => writeln!(
+                    writer,
+                    "Launched on: {}",
+                    platform.to_string().replace('_', " ")
+                )?,
+            }
+        }
+        writeln!(writer, "Leaders: {}", token.leaders.join(", "))?;
+        writeln!(writer, "Number of copied swaps: {}", token.copy_count)?;
+    }
 
-    for item in repeating_wallets {
+    for item in repeating_wallets.iter() {
         writeln!(writer, "----------------------------------------")?;
         writeln!(writer, "Wallet: {}", item.wallet)?;
+        if let Some(cluster) = item.cluster {
+            writeln!(writer, "Cluster: #{}", cluster)?;
+        }
+        if item.is_mev() {
+            writeln!(writer, "MEV: sandwiched {} swaps", item.sandwiches.len())?;
+            writeln!(
+                writer,
+                "Sandwiches: {}",
+                serde_json::to_string_pretty(&item.sandwiches)?
+            )?;
+        }
         writeln!(writer, "Number of copied swaps: {}", item.txs.len())?;
         writeln!(
             writer,
             "Swaps: {}",
             serde_json::to_string_pretty(&item.txs)?
         )?;
+        if let Some(sizing) = item.sizing.as_ref() {
+            writeln!(
+                writer,
+                "Buy sizes: {} over {} buys, the wallet spent {:.2}x the leader on average ({:.4} SOL)",
+                sizing.pattern.to_string().replace('_', " "),
+                sizing.pairs,
+                sizing.avg_ratio,
+                sizing.avg_target_sol
+            )?;
+        }
+        if let Some(pnl) = item.pnl.as_ref() {
+            writeln!(writer, "Realised PnL: {:.4} SOL", pnl.realised_pnl_sol)?;
+            if let Some(win_rate) = pnl.win_rate {
+                writeln!(writer, "Win rate: {:.0}%", win_rate * 100.0)?;
+            }
+            if let Some(avg_hold_secs) = pnl.avg_hold_secs {
+                writeln!(writer, "Average hold: {:.0}s", avg_hold_secs)?;
+            }
+            writeln!(
+                writer,
+                "PnL per token: {}",
+                serde_json::to_string_pretty(&pnl.tokens)?
+            )?;
+        }
+        if let Some(outcomes) = item.outcomes.as_ref() {
+            if let Some(captured_return) = outcomes.captured_return {
+                writeln!(
+                    writer,
+                    "Copy outcome: the wallet captured {:.0}% of the leader's return",
+                    captured_return * 100.0
+                )?;
+            }
+            writeln!(
+                writer,
+                "Outcomes per token: {}",
+                serde_json::to_string_pretty(&outcomes.tokens)?
+            )?;
+        }
         writer.flush()?;
     }
 
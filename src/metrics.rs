@@ -0,0 +1,417 @@
+//! # Metrics
+//!
+//! This module collects operational metrics of the scanner and renders them in the Prometheus
+//! text exposition format.
+//!
+//! The metrics are process-wide. They are served on `/metrics` by the HTTP API server and, if
+//! `--metrics-bind` is given, by a small dedicated server in watch mode.
+//!
+//! | Metric                                    | Type      | Labels               |
+//! |-------------------------------------------|-----------|----------------------|
+//! | `copytrade_upstream_requests_total`       | counter   | `provider`, `status` |
+//! | `copytrade_retries_total`                 | counter   | `target`             |
+//! | `copytrade_rate_limit_waits_total`        | counter   |                      |
+//! | `copytrade_rate_limit_wait_seconds_total` | counter   |                      |
+//! | `copytrade_cache_requests_total`          | counter   | `cache`, `result`    |
+//! | `copytrade_swaps_scanned_total`           | counter   |                      |
+//! | `copytrade_candidates_total`              | counter   |                      |
+//! | `copytrade_scan_duration_seconds`         | histogram | `kind`               |
+
+use std::{
+    collections::BTreeMap, convert::Infallible, fmt::Write, net::SocketAddr, sync::Mutex,
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+
+/// The content type of the Prometheus text exposition format.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Upper bounds of the scan duration buckets in seconds.
+const DURATION_BUCKETS: [f64; 10] = [0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Upstream requests by provider (`cielo`, `shyft`, `rpc`) and status (`ok`, `error`).
+static UPSTREAM_REQUESTS: Counter = Counter::new(
+    "copytrade_upstream_requests_total",
+    "Requests to upstream APIs by provider and status.",
+    &["provider", "status"],
+);
+
+/// Retried deliveries by target.
+static RETRIES: Counter = Counter::new(
+    "copytrade_retries_total",
+    "Retried requests by target.",
+    &["target"],
+);
+
+/// Waits inserted between upstream requests to avoid rate limits.
+static RATE_LIMIT_WAITS: Counter = Counter::new(
+    "copytrade_rate_limit_waits_total",
+    "Waits inserted between upstream requests to avoid rate limits.",
+    &[],
+);
+
+/// Time spent waiting to avoid rate limits.
+static RATE_LIMIT_WAIT_SECONDS: Counter = Counter::new(
+    "copytrade_rate_limit_wait_seconds_total",
+    "Time spent waiting to avoid rate limits.",
+    &[],
+);
+
+/// Cache lookups by cache and result (`hit`, `miss`).
+static CACHE_REQUESTS: Counter = Counter::new(
+    "copytrade_cache_requests_total",
+    "Cache lookups by cache and result.",
+    &["cache", "result"],
+);
+
+/// Analysed swaps of scanned wallets.
+static SWAPS_SCANNED: Counter = Counter::new(
+    "copytrade_swaps_scanned_total",
+    "Analysed swaps of scanned wallets.",
+    &[],
+);
+
+/// Detected potential leaders.
+static CANDIDATES: Counter = Counter::new(
+    "copytrade_candidates_total",
+    "Detected potential leaders.",
+    &[],
+);
+
+/// Durations of scans (`scan`) and watch mode polls (`poll`).
+static SCAN_DURATION: Histogram = Histogram::new(
+    "copytrade_scan_duration_seconds",
+    "Durations of scans and watch mode polls.",
+    &["kind"],
+);
+
+/// Represents a counter with a value per label set.
+struct Counter {
+    /// The metric name.
+    name: &'static str,
+    /// The metric description.
+    help: &'static str,
+    /// The label names.
+    labels: &'static [&'static str],
+    /// The values by label values.
+    values: Mutex<BTreeMap<Vec<String>, f64>>,
+}
+
+impl Counter {
+    /// Creates a counter.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The metric name.
+    /// * `help` - The metric description.
+    /// * `labels` - The label names.
+    const fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Increments the counter.
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - The label values, in the order of the label names.
+    /// * `by` - The increment.
+    fn add(&self, labels: &[&str], by: f64) {
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        *values
+            .entry(labels.iter().map(|l| l.to_string()).collect())
+            .or_default() += by;
+    }
+
+    /// Renders the counter in the text exposition format.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The output.
+    fn render(&self, out: &mut String) {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} counter", self.name);
+        if values.is_empty() && self.labels.is_empty() {
+            let _ = writeln!(out, "{} 0", self.name);
+        }
+        for (label_values, value) in values.iter() {
+            let _ = writeln!(
+                out,
+                "{}{} {}",
+                self.name,
+                label_set(self.labels, label_values, None),
+                value
+            );
+        }
+    }
+}
+
+/// Represents the observations of a histogram for one label set.
+#[derive(Default)]
+struct Observations {
+    /// The number of observations per bucket, not cumulative.
+    buckets: [u64; DURATION_BUCKETS.len()],
+    /// The sum of the observations.
+    sum: f64,
+    /// The number of observations.
+    count: u64,
+}
+
+/// Represents a histogram of durations with observations per label set.
+struct Histogram {
+    /// The metric name.
+    name: &'static str,
+    /// The metric description.
+    help: &'static str,
+    /// The label names.
+    labels: &'static [&'static str],
+    /// The observations by label values.
+    values: Mutex<BTreeMap<Vec<String>, Observations>>,
+}
+
+impl Histogram {
+    /// Creates a histogram.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The metric name.
+    /// * `help` - The metric description.
+    /// * `labels` - The label names.
+    const fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records an observation.
+    ///
+    /// # Arguments
+    ///
+    /// * `labels` - The label values, in the order of the label names.
+    /// * `value` - The observed value.
+    fn observe(&self, labels: &[&str], value: f64) {
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        let observations = values
+            .entry(labels.iter().map(|l| l.to_string()).collect())
+            .or_default();
+        if let Some(bucket) = DURATION_BUCKETS.iter().position(|le| value <= *le) {
+            observations.buckets[bucket] += 1;
+        }
+        observations.sum += value;
+        observations.count += 1;
+    }
+
+    /// Renders the histogram in the text exposition format.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The output.
+    fn render(&self, out: &mut String) {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} histogram", self.name);
+        for (label_values, observations) in values.iter() {
+            let mut cumulative = 0;
+            for (le, count) in DURATION_BUCKETS.iter().zip(observations.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    self.name,
+                    label_set(self.labels, label_values, Some(&le.to_string())),
+                    cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{} {}",
+                self.name,
+                label_set(self.labels, label_values, Some("+Inf")),
+                observations.count
+            );
+            let labels = label_set(self.labels, label_values, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, observations.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, observations.count);
+        }
+    }
+}
+
+/// Formats a label set, e.g. `{provider="rpc",status="ok"}`.
+///
+/// # Arguments
+///
+/// * `names` - The label names.
+/// * `values` - The label values.
+/// * `le` - The upper bound of a histogram bucket, if any.
+fn label_set(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut labels: Vec<_> = names
+        .iter()
+        .zip(values.iter())
+        .map(|(name, value)| {
+            format!(
+                "{}=\"{}\"",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect();
+    if let Some(le) = le {
+        labels.push(format!("le=\"{}\"", le));
+    }
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+/// Records the outcome of an upstream request and passes its result through.
+///
+/// # Arguments
+///
+/// * `provider` - The upstream provider, `cielo`, `shyft` or `rpc`.
+/// * `result` - The result of the request.
+pub(crate) fn upstream<T, E>(provider: &str, result: Result<T, E>) -> Result<T, E> {
+    let status = if result.is_ok() { "ok" } else { "error" };
+    UPSTREAM_REQUESTS.add(&[provider, status], 1.0);
+    result
+}
+
+/// Records a retried request.
+///
+/// # Arguments
+///
+/// * `target` - The kind of target the request was sent to.
+pub(crate) fn retry(target: &str) {
+    RETRIES.add(&[target], 1.0);
+}
+
+/// Waits between upstream requests to avoid rate limits, recording the wait.
+///
+/// # Arguments
+///
+/// * `delay_ms` - The delay in milliseconds.
+pub(crate) async fn rate_limit_wait(delay_ms: u64) {
+    RATE_LIMIT_WAITS.add(&[], 1.0);
+    RATE_LIMIT_WAIT_SECONDS.add(&[], delay_ms as f64 / 1000.0);
+    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+}
+
+/// Records cache lookups.
+///
+/// # Arguments
+///
+/// * `cache` - The cache name.
+/// * `hits` - The number of hits.
+/// * `misses` - The number of misses.
+pub(crate) fn cache(cache: &str, hits: usize, misses: usize) {
+    if hits > 0 {
+        CACHE_REQUESTS.add(&[cache, "hit"], hits as f64);
+    }
+    if misses > 0 {
+        CACHE_REQUESTS.add(&[cache, "miss"], misses as f64);
+    }
+}
+
+/// Records an analysed swap.
+pub(crate) fn swap_scanned() {
+    SWAPS_SCANNED.add(&[], 1.0);
+}
+
+/// Records detected potential leaders.
+///
+/// # Arguments
+///
+/// * `count` - The number of leaders.
+pub(crate) fn candidates(count: usize) {
+    CANDIDATES.add(&[], count as f64);
+}
+
+/// Records the duration of a scan or poll.
+///
+/// # Arguments
+///
+/// * `kind` - `scan` or `poll`.
+/// * `duration` - The duration.
+pub(crate) fn scan_duration(kind: &str, duration: Duration) {
+    SCAN_DURATION.observe(&[kind], duration.as_secs_f64());
+}
+
+/// Renders every metric in the Prometheus text exposition format.
+pub fn render_metrics() -> String {
+    let mut out = String::new();
+    for counter in [
+        &UPSTREAM_REQUESTS,
+        &RETRIES,
+        &RATE_LIMIT_WAITS,
+        &RATE_LIMIT_WAIT_SECONDS,
+        &CACHE_REQUESTS,
+        &SWAPS_SCANNED,
+        &CANDIDATES,
+    ] {
+        counter.render(&mut out);
+    }
+    SCAN_DURATION.render(&mut out);
+    out
+}
+
+/// Builds a response with the rendered metrics.
+pub(crate) fn response() -> Response<Body> {
+    let mut response = Response::new(Body::from(render_metrics()));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static(CONTENT_TYPE),
+    );
+    response
+}
+
+/// Serves the metrics on `/metrics` until the returned future is dropped.
+///
+/// # Arguments
+///
+/// * `bind` - The address to listen on.
+///
+/// # Errors
+///
+/// This function will return an error if the address cannot be bound.
+pub fn serve_metrics(
+    bind: SocketAddr,
+) -> Result<impl std::future::Future<Output = Result<(), hyper::Error>>, crate::Error> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|request: Request<Body>| async move {
+            let response = match (request.method(), request.uri().path()) {
+                (&Method::GET, "/metrics") => response(),
+                (_, "/metrics") => status(StatusCode::METHOD_NOT_ALLOWED),
+                _ => status(StatusCode::NOT_FOUND),
+            };
+            Ok::<_, Infallible>(response)
+        }))
+    });
+
+    let server = Server::try_bind(&bind)?.serve(make_service);
+    tracing::info!("Serving metrics on {}", server.local_addr());
+    Ok(server)
+}
+
+/// Builds an empty response with a status.
+///
+/// # Arguments
+///
+/// * `status` - The response status.
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,219 @@
+//! # MEV Detection
+//!
+//! This module detects sandwich patterns around the wallet's swaps.
+//!
+//! A wallet that buys right before the scanned wallet in the same or adjacent slot looks exactly
+//! like a copy trade leader. If that wallet also sells the same token on the same pool right after
+//! the scanned wallet's swap, it is front-running the swap rather than being copied.
+
+use cielo_rs_sdk::models;
+use shyft_rs_sdk::models::parsed_transaction_details::ParsedTransactionDetails;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcBlockConfig};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::{
+    EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionDetails, UiConfirmedBlock,
+    UiLoadedAddresses, UiMessage, UiTransactionEncoding, UiTransactionTokenBalance,
+};
+
+use crate::Sandwich;
+
+/// Maximum number of slots between a front-running buy, the wallet's swap and the back-running
+/// sell for them to be considered a sandwich.
+pub(crate) const MAX_SANDWICH_SLOT_DIFF: u64 = 1;
+
+/// A buy that landed shortly before the wallet's swap and may be the front leg of a sandwich.
+pub(crate) struct FrontRun<'a> {
+    /// The parsed buy transaction.
+    pub tx: &'a ParsedTransactionDetails,
+    /// The slot the buy landed in.
+    pub slot: u64,
+}
+
+/// Looks for sandwiches around a fresh swap.
+///
+/// The blocks from the swap's slot up to [`MAX_SANDWICH_SLOT_DIFF`] slots later are fetched and
+/// searched for sells of the swapped token by each front-running wallet. Sells in the swap's own
+/// slot only count if they land after the swap.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `fresh_swap` - A reference to the fresh swap transaction details.
+/// * `swap_slot` - The slot the fresh swap landed in.
+/// * `front_runs` - The buys that landed shortly before the fresh swap.
+/// * `delay_ms` - The delay in milliseconds between requests.
+///
+/// # Returns
+///
+/// A vector of detected sandwiches together with the wallet that executed them. Blocks that
+/// cannot be fetched (e.g. skipped slots) are logged and ignored, since they cannot contain a
+/// back-running sell.
+pub(crate) async fn find_sandwiches(
+    rpc_client: &RpcClient,
+    fresh_swap: &models::feed::Swap,
+    swap_slot: u64,
+    front_runs: &[FrontRun<'_>],
+    delay_ms: u64,
+) -> Vec<(String, Sandwich)> {
+    let mut sandwiches = Vec::new();
+    // A wallet may front-run on several pools, so every buy is matched on its own
+    let mut pending: Vec<&FrontRun> = front_runs
+        .iter()
+        .filter(|f| swap_slot.saturating_sub(f.slot) <= MAX_SANDWICH_SLOT_DIFF)
+        .collect();
+
+    for slot in swap_slot..=swap_slot + MAX_SANDWICH_SLOT_DIFF {
+        if pending.is_empty() {
+            break;
+        }
+
+        let block = match fetch_block(rpc_client, slot).await {
+            Ok(block) => block,
+            Err(e) => {
+                tracing::warn!("Failed to fetch block {}: {}", slot, e);
+                continue;
+            }
+        };
+        crate::metrics::rate_limit_wait(delay_ms).await;
+
+        let transactions = block.transactions.unwrap_or_default();
+        // Only transactions after the fresh swap can back-run it within its own slot
+        let start = if slot == swap_slot {
+            transactions
+                .iter()
+                .position(|tx| first_signature(tx) == Some(fresh_swap.tx_hash.as_str()))
+                .map_or(0, |index| index + 1)
+        } else {
+            0
+        };
+
+        pending.retain(|front_run| {
+            if slot.saturating_sub(front_run.slot) > MAX_SANDWICH_SLOT_DIFF {
+                return false;
+            }
+            let wallet = front_run.tx.fee_payer.as_str();
+            let pools = pool_addresses(front_run.tx);
+            let back_run = transactions[start..].iter().find(|tx| {
+                is_sell(tx, wallet, &fresh_swap.token1_address) && touches_any(tx, &pools)
+            });
+            match back_run.and_then(first_signature) {
+                Some(back_run_tx_hash) => {
+                    sandwiches.push((
+                        wallet.to_string(),
+                        Sandwich {
+                            target_tx_hash: fresh_swap.tx_hash.to_owned(),
+                            front_run_tx_hash: front_run.tx.signatures[0].to_owned(),
+                            back_run_tx_hash: back_run_tx_hash.to_owned(),
+                            slot: swap_slot,
+                        },
+                    ));
+                    false
+                }
+                None => true,
+            }
+        });
+    }
+
+    sandwiches
+}
+
+/// Fetches a full block with raw JSON encoded transactions.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `slot` - The slot of the block to fetch.
+///
+/// # Errors
+///
+/// This function will return an error if the Solana RPC request fails or the slot was skipped.
+async fn fetch_block(
+    rpc_client: &RpcClient,
+    slot: u64,
+) -> Result<UiConfirmedBlock, solana_client::client_error::ClientError> {
+    let result = rpc_client
+        .get_block_with_config(
+            slot,
+            RpcBlockConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                transaction_details: Some(TransactionDetails::Full),
+                rewards: Some(false),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await;
+    crate::metrics::upstream("rpc", result)
+}
+
+/// Returns the first signature of an encoded transaction, which identifies it.
+fn first_signature(tx: &EncodedTransactionWithStatusMeta) -> Option<&str> {
+    match &tx.transaction {
+        EncodedTransaction::Json(ui_tx) => ui_tx.signatures.first().map(String::as_str),
+        _ => None,
+    }
+}
+
+/// Extracts the liquidity pool addresses from the swap actions of a parsed transaction.
+pub fn pool_addresses(tx: &ParsedTransactionDetails) -> Vec<String> {
+    tx.actions
+        .iter()
+        .filter(|a| a.action_type == "SWAP")
+        .filter_map(|a| a.info.get("swaps").and_then(serde_json::Value::as_array))
+        .flatten()
+        .filter_map(|s| s.get("liquidity_pool_address")?.as_str())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Checks whether a successful transaction decreased the wallet's balance of the given token.
+pub fn is_sell(tx: &EncodedTransactionWithStatusMeta, wallet: &str, mint: &str) -> bool {
+    let Some(meta) = tx.meta.as_ref().filter(|m| m.err.is_none()) else {
+        return false;
+    };
+
+    let balance = |balances: Option<&Vec<UiTransactionTokenBalance>>| -> u128 {
+        balances
+            .into_iter()
+            .flatten()
+            .filter(|b| {
+                let owner: Option<&String> = b.owner.as_ref().into();
+                b.mint == mint && owner.is_some_and(|owner| owner == wallet)
+            })
+            .filter_map(|b| b.ui_token_amount.amount.parse::<u128>().ok())
+            .sum()
+    };
+
+    let pre = balance(meta.pre_token_balances.as_ref().into());
+    let post = balance(meta.post_token_balances.as_ref().into());
+    post < pre
+}
+
+/// Checks whether a transaction references any of the given accounts.
+///
+/// An empty list of accounts always matches, so that sandwiches are still detected when the pool
+/// of the front-running buy is unknown.
+pub fn touches_any(tx: &EncodedTransactionWithStatusMeta, accounts: &[String]) -> bool {
+    if accounts.is_empty() {
+        return true;
+    }
+
+    let static_keys = match &tx.transaction {
+        EncodedTransaction::Json(ui_tx) => match &ui_tx.message {
+            UiMessage::Raw(message) => message.account_keys.as_slice(),
+            UiMessage::Parsed(_) => &[],
+        },
+        _ => &[],
+    };
+    let loaded_keys = tx
+        .meta
+        .as_ref()
+        .and_then(|m| Option::<&UiLoadedAddresses>::from(m.loaded_addresses.as_ref()))
+        .map(|loaded| loaded.writable.as_slice())
+        .unwrap_or_default();
+
+    static_keys
+        .iter()
+        .chain(loaded_keys)
+        .any(|key| accounts.contains(key))
+}
@@ -0,0 +1,465 @@
+//! # Output Formats
+//!
+//! This module writes the detected wallets of a scan in the machine-readable output formats and
+//! describes them with JSON Schema.
+//!
+//! * `json`: a single report object with the scanned wallet and the detected wallets.
+//! * `jsonl`: one line per copied buy of a repeating wallet, streamed as it is found, followed by
+//!   one line per detected wallet. Every line is tagged with the scanned wallet and an `event`.
+//! * `csv`: one row per detected wallet and copied swap. The analyses of the wallets and of the
+//!   tokens are only written in the JSON formats, so that every column is about the swap.
+//! * `html`: a self-contained report, see [`write_html`](crate::write_html).
+//!
+//! Every format carries [`SCHEMA_VERSION`], which is bumped whenever a field is removed or its
+//! meaning changes. New fields may be added without a bump.
+
+use std::io::{self, Write};
+
+use crate::{OutputFormat, PrevBuy, RepeatingWallet, TokenOverlap};
+
+/// The version of the output schema.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The columns of the CSV format.
+const CSV_COLUMNS: [&str; 13] = [
+    "schema_version",
+    "target",
+    "wallet",
+    "mev",
+    "cluster",
+    "copy_count",
+    "tx_hash",
+    "slot",
+    "block_diff",
+    "mint",
+    "sol_amount",
+    "token_amount",
+    "target_sol_amount",
+];
+
+/// Represents the report of a single scan in the `json` format.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ScanReport {
+    /// The version of the output schema.
+    pub schema_version: u32,
+    /// The scanned wallet address.
+    pub wallet: String,
+    /// The detected wallets.
+    pub leaders: Vec<RepeatingWallet>,
+    /// The copied swaps grouped by token.
+    #[serde(default)]
+    pub tokens: Vec<TokenOverlap>,
+}
+
+/// Represents a detected wallet line of the `jsonl` format.
+#[derive(Debug, serde::Serialize)]
+struct LeaderLine<'a> {
+    /// The version of the output schema.
+    schema_version: u32,
+    /// The kind of line, always `leader`.
+    event: &'static str,
+    /// The scanned wallet address.
+    target: &'a str,
+    /// The detected wallet.
+    #[serde(flatten)]
+    leader: &'a RepeatingWallet,
+}
+
+/// Represents a copied buy line of the `jsonl` format.
+#[derive(Debug, serde::Serialize)]
+struct BuyLine<'a> {
+    /// The version of the output schema.
+    schema_version: u32,
+    /// The kind of line, always `buy`.
+    event: &'static str,
+    /// The scanned wallet address.
+    target: &'a str,
+    /// The wallet that made the buy.
+    wallet: &'a str,
+    /// The buy.
+    #[serde(flatten)]
+    buy: &'a PrevBuy,
+}
+
+/// Writes the detected wallets of a scan in a machine-readable format.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `format` - The output format. The text format is written by the caller.
+/// * `wallet` - The scanned wallet address.
+/// * `leaders` - The detected wallets.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+pub fn write_report(
+    writer: &mut impl Write,
+    format: OutputFormat,
+    wallet: &str,
+    leaders: &[RepeatingWallet],
+) -> Result<(), io::Error> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Html => crate::write_html(writer, wallet, leaders)?,
+        OutputFormat::Json => {
+            let report = ScanReport {
+                schema_version: SCHEMA_VERSION,
+                wallet: wallet.to_owned(),
+                leaders: leaders.to_vec(),
+                tokens: crate::group_by_token(leaders),
+            };
+            serde_json::to_writer(&mut *writer, &report)?;
+            writeln!(writer)?;
+        }
+        OutputFormat::Jsonl => {
+            for leader in leaders.iter() {
+                let line = LeaderLine {
+                    schema_version: SCHEMA_VERSION,
+                    event: "leader",
+                    target: wallet,
+                    leader,
+                };
+                serde_json::to_writer(&mut *writer, &line)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+        }
+        OutputFormat::Csv => {
+            writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+            for leader in leaders.iter() {
+                for tx in leader.txs.iter() {
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                        SCHEMA_VERSION,
+                        csv_field(wallet),
+                        csv_field(&leader.wallet),
+                        leader.is_mev(),
+                        leader.cluster.map(|c| c.to_string()).unwrap_or_default(),
+                        leader.txs.len(),
+                        csv_field(&tx.tx_hash),
+                        tx.slot,
+                        tx.block_diff,
+                        csv_field(&tx.mint),
+                        tx.sol_amount,
+                        tx.token_amount,
+                        tx.target_sol_amount
+                    )?;
+                }
+            }
+        }
+    }
+    writer.flush()
+}
+
+/// Writes a copied buy of a repeating wallet as a line of the `jsonl` format.
+///
+/// The buys are written as the scan finds them, before the detected wallets are written with
+/// [`write_report`].
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write to.
+/// * `target` - The scanned wallet address.
+/// * `wallet` - The wallet that made the buy.
+/// * `buy` - The buy.
+///
+/// # Errors
+///
+/// This function will return an error if writing fails.
+pub fn write_found(
+    writer: &mut impl Write,
+    target: &str,
+    wallet: &str,
+    buy: &PrevBuy,
+) -> Result<(), io::Error> {
+    let line = BuyLine {
+        schema_version: SCHEMA_VERSION,
+        event: "buy",
+        target,
+        wallet,
+        buy,
+    };
+    serde_json::to_writer(&mut *writer, &line)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+///
+/// # Arguments
+///
+/// * `value` - The field value.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Returns the JSON Schema of an output format.
+///
+/// The schema of the `csv` format describes one row, with every column as a string as read
+/// from the file.
+///
+/// # Arguments
+///
+/// * `format` - The output format.
+///
+/// # Returns
+///
+/// The JSON Schema, or `None` for the text and HTML formats, which are meant for humans only.
+pub fn json_schema(format: OutputFormat) -> Option<serde_json::Value> {
+    let title = format!(
+        "solana-copy-trade-detect {} output v{}",
+        format, SCHEMA_VERSION
+    );
+    let version = serde_json::json!({ "type": "integer", "const": SCHEMA_VERSION });
+
+    let mut schema = match format {
+        OutputFormat::Text | OutputFormat::Html => return None,
+        OutputFormat::Json => serde_json::json!({
+            "type": "object",
+            "required": ["schema_version", "wallet", "leaders"],
+            "properties": {
+                "schema_version": version,
+                "wallet": { "type": "string", "description": "The scanned wallet address" },
+                "leaders": { "type": "array", "items": { "$ref": "#/$defs/leader" } },
+                "tokens": {
+                    "type": "array",
+                    "description": "The copied swaps grouped by token",
+                    "items": { "$ref": "#/$defs/token_overlap" },
+                },
+            },
+        }),
+        OutputFormat::Jsonl => serde_json::json!({
+            "description": "One line per copied buy of a repeating wallet as it is found, then one line per detected wallet",
+            "oneOf": [
+                {
+                    "allOf": [
+                        { "$ref": "#/$defs/prev_buy" },
+                        {
+                            "type": "object",
+                            "required": ["schema_version", "event", "target", "wallet"],
+                            "properties": {
+                                "schema_version": version,
+                                "event": { "const": "buy" },
+                                "target": { "type": "string", "description": "The scanned wallet address" },
+                                "wallet": { "type": "string", "description": "The wallet that made the buy" },
+                            },
+                        },
+                    ],
+                },
+                {
+                    "allOf": [
+                        { "$ref": "#/$defs/leader" },
+                        {
+                            "type": "object",
+                            "required": ["schema_version", "event", "target"],
+                            "properties": {
+                                "schema_version": version,
+                                "event": { "const": "leader" },
+                                "target": { "type": "string", "description": "The scanned wallet address" },
+                            },
+                        },
+                    ],
+                },
+            ],
+        }),
+        OutputFormat::Csv => serde_json::json!({
+            "description": "One row per detected wallet and copied swap, with a header row",
+            "type": "object",
+            "required": CSV_COLUMNS,
+            "properties": {
+                "schema_version": { "type": "string", "const": SCHEMA_VERSION.to_string() },
+                "target": { "type": "string", "description": "The scanned wallet address" },
+                "wallet": { "type": "string", "description": "The detected wallet address" },
+                "mev": { "enum": ["true", "false"], "description": "Whether the wallet sandwiched the scanned wallet" },
+                "cluster": { "type": "string", "description": "The funding cluster id, empty if none" },
+                "copy_count": { "type": "string", "description": "The number of copied swaps of the wallet" },
+                "tx_hash": { "type": "string", "description": "The copied buy transaction hash" },
+                "slot": { "type": "string", "description": "The slot of the buy" },
+                "block_diff": { "type": "string", "description": "The lag behind the scanned wallet's swap in slots" },
+                "mint": { "type": "string", "description": "The bought token mint address" },
+                "sol_amount": { "type": "string", "description": "The SOL spent on the buy, 0 if unknown" },
+                "token_amount": { "type": "string", "description": "The amount of tokens received, 0 if unknown" },
+                "target_sol_amount": { "type": "string", "description": "The SOL the scanned wallet spent on its swap, 0 if unknown" },
+            },
+        }),
+    };
+
+    schema["$schema"] = "https://json-schema.org/draft/2020-12/schema".into();
+    schema["title"] = title.into();
+    if format != OutputFormat::Csv {
+        schema["$defs"] = definitions();
+    }
+    Some(schema)
+}
+
+/// Returns the JSON Schema definitions of the detected wallets and tokens.
+fn definitions() -> serde_json::Value {
+    serde_json::json!({
+        "leader": {
+            "type": "object",
+            "required": ["wallet", "txs", "sandwiches", "funding", "cluster"],
+            "properties": {
+                "wallet": { "type": "string", "description": "The detected wallet address" },
+                "txs": {
+                    "type": "array",
+                    "description": "The buys that preceded the scanned wallet's swaps",
+                    "items": { "$ref": "#/$defs/prev_buy" },
+                },
+                "sandwiches": {
+                    "type": "array",
+                    "description": "The sandwiches around the scanned wallet's swaps, which flag the wallet as MEV",
+                    "items": { "$ref": "#/$defs/sandwich" },
+                },
+                "funding": { "type": "array", "items": { "$ref": "#/$defs/funding" } },
+                "cluster": { "type": ["integer", "null"], "description": "The funding cluster id" },
+                "pnl": {
+                    "oneOf": [{ "$ref": "#/$defs/leader_pnl" }, { "type": "null" }],
+                    "description": "The realised profit on the scanned tokens, if analysed",
+                },
+                "outcomes": {
+                    "oneOf": [{ "$ref": "#/$defs/outcome_comparison" }, { "type": "null" }],
+                    "description": "The trades of the scanned wallet and the wallet on shared tokens, if compared",
+                },
+                "sizing": {
+                    "oneOf": [{ "$ref": "#/$defs/size_correlation" }, { "type": "null" }],
+                    "description": "The relation between the buy sizes, if enough sizes are known",
+                },
+            },
+        },
+        "token_overlap": {
+            "type": "object",
+            "required": ["mint", "symbol", "name", "decimals", "leaders", "copy_count"],
+            "properties": {
+                "mint": { "type": "string" },
+                "symbol": { "type": "string" },
+                "name": { "type": "string" },
+                "decimals": { "type": ["integer", "null"], "minimum": 0, "maximum": 255 },
+                "launch_platform": { "enum": ["pump_fun", "raydium_launch_lab", "moonshot", "meteora_dbc", "other", null] },
+                "token_created_slot": { "type": ["integer", "null"], "minimum": 0 },
+                "leaders": {
+                    "type": "array",
+                    "description": "The detected wallets that bought the token first",
+                    "items": { "type": "string" },
+                },
+                "copy_count": { "type": "integer", "minimum": 1 },
+            },
+        },
+        "size_correlation": {
+            "type": "object",
+            "required": ["pattern", "pairs", "avg_target_sol", "avg_ratio", "target_size_cv", "ratio_cv"],
+            "properties": {
+                "pattern": { "enum": ["fixed_size", "proportional", "uncorrelated"] },
+                "pairs": { "type": "integer", "minimum": 2 },
+                "avg_target_sol": { "type": "number", "minimum": 0 },
+                "avg_ratio": { "type": "number", "minimum": 0, "description": "The scanned wallet's size relative to the wallet's" },
+                "target_size_cv": { "type": "number", "minimum": 0 },
+                "ratio_cv": { "type": "number", "minimum": 0 },
+            },
+        },
+        "outcome_comparison": {
+            "type": "object",
+            "required": ["tokens", "target_avg_roi", "leader_avg_roi", "captured_return"],
+            "properties": {
+                "tokens": { "type": "array", "items": { "$ref": "#/$defs/token_comparison" } },
+                "target_avg_roi": { "type": ["number", "null"] },
+                "leader_avg_roi": { "type": ["number", "null"] },
+                "captured_return": {
+                    "type": ["number", "null"],
+                    "description": "The scanned wallet's average return relative to the wallet's",
+                },
+            },
+        },
+        "token_comparison": {
+            "type": "object",
+            "required": ["mint", "target", "leader"],
+            "properties": {
+                "mint": { "type": "string" },
+                "target": { "$ref": "#/$defs/token_pnl" },
+                "leader": { "$ref": "#/$defs/token_pnl" },
+            },
+        },
+        "leader_pnl": {
+            "type": "object",
+            "required": ["realised_pnl_sol", "win_rate", "avg_hold_secs", "tokens"],
+            "properties": {
+                "realised_pnl_sol": { "type": "number" },
+                "win_rate": { "type": ["number", "null"], "minimum": 0, "maximum": 1 },
+                "avg_hold_secs": { "type": ["number", "null"], "minimum": 0 },
+                "tokens": { "type": "array", "items": { "$ref": "#/$defs/token_pnl" } },
+            },
+        },
+        "token_pnl": {
+            "type": "object",
+            "required": [
+                "mint", "buy_count", "sell_count", "sol_spent", "sol_received", "entry_price",
+                "exit_price", "realised_pnl_sol", "roi", "tokens_held", "hold_secs",
+            ],
+            "properties": {
+                "mint": { "type": "string" },
+                "buy_count": { "type": "integer", "minimum": 0 },
+                "sell_count": { "type": "integer", "minimum": 0 },
+                "sol_spent": { "type": "number", "minimum": 0 },
+                "sol_received": { "type": "number", "minimum": 0 },
+                "entry_price": { "type": ["number", "null"], "description": "The average buy price in SOL per token" },
+                "exit_price": { "type": ["number", "null"], "description": "The average sell price in SOL per token" },
+                "realised_pnl_sol": { "type": "number" },
+                "roi": { "type": ["number", "null"], "description": "The realised PnL relative to the cost of the sold tokens" },
+                "tokens_held": { "type": "number", "minimum": 0 },
+                "hold_secs": { "type": ["integer", "null"], "minimum": 0 },
+            },
+        },
+        "prev_buy": {
+            "type": "object",
+            "required": ["tx_hash", "slot", "block_diff"],
+            "properties": {
+                "tx_hash": { "type": "string" },
+                "slot": { "type": "integer", "minimum": 0 },
+                "block_diff": { "type": "integer", "minimum": 0, "description": "The lag in slots" },
+                "mint": { "type": "string", "description": "The bought token mint address" },
+                "sol_amount": { "type": "number", "minimum": 0, "description": "The SOL spent, 0 if unknown" },
+                "token_amount": { "type": "number", "minimum": 0, "description": "The tokens received, 0 if unknown" },
+                "target_sol_amount": { "type": "number", "minimum": 0, "description": "The SOL the scanned wallet spent, 0 if unknown" },
+                "symbol": { "type": "string", "description": "The bought token symbol, empty if unknown" },
+                "name": { "type": "string", "description": "The bought token name, empty if unknown" },
+                "decimals": { "type": ["integer", "null"], "minimum": 0, "maximum": 255 },
+                "token_created_slot": { "type": ["integer", "null"], "minimum": 0 },
+                "token_age_secs": {
+                    "type": ["integer", "null"],
+                    "minimum": 0,
+                    "description": "The token age at the scanned wallet's swap",
+                },
+                "launch_platform": { "enum": ["pump_fun", "raydium_launch_lab", "moonshot", "meteora_dbc", "other", null] },
+                "migrated": {
+                    "type": ["boolean", "null"],
+                    "description": "Whether the token had left its launchpad at the scanned wallet's swap",
+                },
+            },
+        },
+        "sandwich": {
+            "type": "object",
+            "required": ["target_tx_hash", "front_run_tx_hash", "back_run_tx_hash", "slot"],
+            "properties": {
+                "target_tx_hash": { "type": "string" },
+                "front_run_tx_hash": { "type": "string" },
+                "back_run_tx_hash": { "type": "string" },
+                "slot": { "type": "integer", "minimum": 0 },
+            },
+        },
+        "funding": {
+            "type": "object",
+            "required": ["source", "lamports", "tx_hash", "initial"],
+            "properties": {
+                "source": { "type": "string" },
+                "lamports": { "type": "integer", "minimum": 0 },
+                "tx_hash": { "type": "string" },
+                "initial": { "type": "boolean" },
+            },
+        },
+    })
+}
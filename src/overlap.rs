@@ -0,0 +1,150 @@
+//! # Cross-Target Overlap
+//!
+//! This module aggregates the results of several scans into a matrix of scanned wallets (targets)
+//! and the leaders detected behind them.
+//!
+//! A leader that precedes many distinct targets is usually the wallet worth copying, so leaders
+//! are ranked by the number of targets they were detected behind. The scans can come from a
+//! batch run or from saved JSON outputs.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::{RepeatingWallet, ScanReport, TargetScan};
+
+/// Represents the overlap of leaders across several scanned wallets.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OverlapReport {
+    /// The scanned wallet addresses, which are the columns of the matrix.
+    pub targets: Vec<String>,
+    /// The ranked leaders, which are the rows of the matrix.
+    pub leaders: Vec<LeaderOverlap>,
+}
+
+/// Represents one leader row of the overlap matrix.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LeaderOverlap {
+    /// The leader wallet address.
+    pub wallet: String,
+    /// The number of copied swaps per scanned wallet, in the order of [`OverlapReport::targets`].
+    pub hits: Vec<usize>,
+    /// The number of distinct scanned wallets the leader was detected behind.
+    pub target_count: usize,
+    /// The number of copied swaps across all scanned wallets.
+    pub copy_count: usize,
+}
+
+/// Aggregates scan results into an overlap matrix.
+///
+/// Wallets flagged as MEV are not considered leaders, and failed scans are left out of the matrix.
+///
+/// # Arguments
+///
+/// * `targets` - The results per scanned wallet.
+/// * `min_targets` - The minimum number of scanned wallets a leader must be detected behind.
+///
+/// # Returns
+///
+/// The overlap report, with leaders sorted by the number of scanned wallets they were detected
+/// behind, then by their copy count, in descending order.
+pub fn overlap(targets: &[TargetScan], min_targets: usize) -> OverlapReport {
+    let targets: Vec<_> = targets.iter().filter(|t| t.error.is_none()).collect();
+
+    let mut leaders: HashMap<&str, LeaderOverlap> = HashMap::new();
+    for (column, target) in targets.iter().enumerate() {
+        for leader in target.leaders.iter().filter(|l| !l.is_mev()) {
+            let row = leaders
+                .entry(leader.wallet.as_str())
+                .or_insert_with(|| LeaderOverlap {
+                    wallet: leader.wallet.to_owned(),
+                    hits: vec![0; targets.len()],
+                    target_count: 0,
+                    copy_count: 0,
+                });
+            if row.hits[column] == 0 {
+                row.target_count += 1;
+            }
+            row.hits[column] += leader.txs.len();
+            row.copy_count += leader.txs.len();
+        }
+    }
+
+    let mut leaders: Vec<_> = leaders
+        .into_values()
+        .filter(|l| l.target_count >= min_targets)
+        .collect();
+    leaders.sort_by(|a, b| {
+        b.target_count
+            .cmp(&a.target_count)
+            .then(b.copy_count.cmp(&a.copy_count))
+            .then(a.wallet.cmp(&b.wallet))
+    });
+
+    OverlapReport {
+        targets: targets.iter().map(|t| t.wallet.to_owned()).collect(),
+        leaders,
+    }
+}
+
+/// Loads scan results from saved JSON outputs.
+///
+/// Combined batch reports, `json` reports and single wallet outputs are accepted. A single
+/// wallet output does not record the scanned wallet, so it is taken from the file name, as
+/// written by the `batch` subcommand (`<wallet>.json`). A wallet loaded more than once keeps its
+/// first scan, so a batch report and its per-wallet outputs can be passed together.
+///
+/// # Arguments
+///
+/// * `paths` - The JSON files to load.
+///
+/// # Errors
+///
+/// This function will return an error if a file cannot be read or is not a scan output.
+pub fn load_scans(paths: &[PathBuf]) -> Result<Vec<TargetScan>, crate::Error> {
+    /// The supported formats of saved scan outputs.
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum ScanOutput {
+        /// A combined batch report.
+        Batch {
+            /// The results per scanned wallet.
+            targets: Vec<TargetScan>,
+        },
+        /// A versioned report of a single scan.
+        Report(ScanReport),
+        /// The detected wallets of a single scan.
+        Single(Vec<RepeatingWallet>),
+    }
+
+    let mut targets: Vec<TargetScan> = Vec::new();
+    for path in paths.iter() {
+        let content = fs::read_to_string(path)?;
+        let scans = match serde_json::from_str(&content)
+            .map_err(|e| crate::Error::InvalidScanOutput(path.to_owned(), e))?
+        {
+            ScanOutput::Batch { targets } => targets,
+            ScanOutput::Report(report) => vec![TargetScan {
+                wallet: report.wallet,
+                leaders: report.leaders,
+                error: None,
+            }],
+            ScanOutput::Single(leaders) => vec![TargetScan {
+                wallet: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string()),
+                leaders,
+                error: None,
+            }],
+        };
+
+        for scan in scans {
+            if targets.iter().any(|t| t.wallet == scan.wallet) {
+                tracing::debug!("Skipping duplicate scan of {}", scan.wallet);
+            } else {
+                targets.push(scan);
+            }
+        }
+    }
+
+    Ok(targets)
+}
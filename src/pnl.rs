@@ -0,0 +1,344 @@
+//! # Leader Profitability
+//!
+//! This module reconstructs the trades of detected wallets on the scanned tokens and computes
+//! their realised profit, to tell whether a leader is worth following.
+//!
+//! The trades of a wallet on a token are found in the history of its associated token accounts,
+//! for both the SPL Token and Token-2022 programs, which only holds transactions touching that
+//! token. The swaps with SOL among them are parsed with Shyft.
+//!
+//! The trades of the scanned wallet are reconstructed the same way to compare how it and a
+//! leader exited the tokens they both bought.
+
+use std::collections::{BTreeMap, HashSet};
+
+use shyft_rs_sdk::{
+    models::parsed_transaction_details::{self, ParsedTransactionDetails},
+    ShyftApi,
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey, pubkey::Pubkey};
+
+use crate::{metrics, range, LeaderPnl, OutcomeComparison, TokenComparison, TokenPnl};
+
+/// The associated token account program.
+const ASSOCIATED_TOKEN_PROGRAM: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// The SPL Token and Token-2022 programs.
+const TOKEN_PROGRAMS: [Pubkey; 2] = [
+    pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+    pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+];
+
+/// Maximum number of signatures fetched per token account. Older trades are left out.
+const MAX_ACCOUNT_SIGNATURES: usize = 1_000;
+
+/// The side of a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    /// A buy of the token with SOL.
+    Buy,
+    /// A sell of the token for SOL.
+    Sell,
+}
+
+/// Represents a swap of a token against SOL.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Trade {
+    /// The transaction hash.
+    pub tx_hash: String,
+    /// The token mint address.
+    pub mint: String,
+    /// The side of the trade.
+    pub side: TradeSide,
+    /// The SOL spent or received.
+    pub sol_amount: f64,
+    /// The amount of tokens bought or sold.
+    pub token_amount: f64,
+    /// The block time of the trade, if known.
+    pub timestamp: Option<i64>,
+}
+
+/// Compares how the scanned wallet and a leader traded the tokens they both bought.
+///
+/// The average returns only cover the tokens both wallets sold, so that they are comparable.
+/// The captured return is the scanned wallet's average return relative to the leader's, e.g.
+/// `0.6` if the scanned wallet made 60% of the leader's return. It is left out if the leader
+/// did not make a profit, as the ratio is meaningless then.
+///
+/// # Arguments
+///
+/// * `target_trades` - The trades of the scanned wallet, in any order.
+/// * `leader_trades` - The trades of the leader, in any order.
+///
+/// # Returns
+///
+/// The trades of both wallets per token, by mint, and their average returns.
+pub fn compare_outcomes(target_trades: &[Trade], leader_trades: &[Trade]) -> OutcomeComparison {
+    let target = leader_pnl(target_trades);
+    let leader = leader_pnl(leader_trades);
+
+    let tokens: Vec<_> = leader
+        .tokens
+        .into_iter()
+        .filter_map(|leader| {
+            let target = target.tokens.iter().find(|t| t.mint == leader.mint)?;
+            (target.buy_count > 0 && leader.buy_count > 0).then(|| TokenComparison {
+                mint: leader.mint.to_owned(),
+                target: target.clone(),
+                leader,
+            })
+        })
+        .collect();
+
+    let returns: Vec<_> = tokens
+        .iter()
+        .filter_map(|t| Some((t.target.roi?, t.leader.roi?)))
+        .collect();
+    let average = |values: Vec<f64>| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let target_avg_roi = average(returns.iter().map(|r| r.0).collect());
+    let leader_avg_roi = average(returns.iter().map(|r| r.1).collect());
+    OutcomeComparison {
+        tokens,
+        target_avg_roi,
+        leader_avg_roi,
+        captured_return: target_avg_roi
+            .zip(leader_avg_roi.filter(|roi| *roi > 0.0))
+            .map(|(target, leader)| target / leader),
+    }
+}
+
+/// Computes the realised profit of a wallet from its trades.
+///
+/// The trades of every token are replayed in time order. Sells realise the difference between
+/// the SOL received and the average cost of the sold tokens. Tokens sold beyond the bought
+/// amount, e.g. received by transfer, have no known cost and are left out.
+///
+/// # Arguments
+///
+/// * `trades` - The trades of the wallet, in any order.
+///
+/// # Returns
+///
+/// The profit per token, by mint, and across all tokens.
+pub fn leader_pnl(trades: &[Trade]) -> LeaderPnl {
+    let mut by_mint: BTreeMap<&str, Vec<&Trade>> = BTreeMap::new();
+    for trade in trades.iter() {
+        by_mint.entry(&trade.mint).or_default().push(trade);
+    }
+
+    let tokens: Vec<_> = by_mint
+        .into_iter()
+        .map(|(mint, mut trades)| {
+            trades.sort_by_key(|t| t.timestamp);
+            token_pnl(mint, &trades)
+        })
+        .collect();
+
+    let sold: Vec<_> = tokens.iter().filter(|t| t.roi.is_some()).collect();
+    let holds: Vec<_> = tokens.iter().filter_map(|t| t.hold_secs).collect();
+    LeaderPnl {
+        realised_pnl_sol: tokens.iter().map(|t| t.realised_pnl_sol).sum(),
+        win_rate: (!sold.is_empty()).then(|| {
+            sold.iter().filter(|t| t.realised_pnl_sol > 0.0).count() as f64 / sold.len() as f64
+        }),
+        avg_hold_secs: (!holds.is_empty())
+            .then(|| holds.iter().sum::<i64>() as f64 / holds.len() as f64),
+        tokens,
+    }
+}
+
+/// Computes the realised profit of a wallet on a single token.
+///
+/// # Arguments
+///
+/// * `mint` - The token mint address.
+/// * `trades` - The trades of the token in time order.
+fn token_pnl(mint: &str, trades: &[&Trade]) -> TokenPnl {
+    let mut pnl = TokenPnl {
+        mint: mint.to_owned(),
+        ..Default::default()
+    };
+    let mut cost = 0.0;
+    let mut sold_cost = 0.0;
+    let mut first_buy = None;
+    let mut last_sell = None;
+    let mut tokens_bought = 0.0;
+    let mut tokens_sold = 0.0;
+
+    for trade in trades.iter() {
+        match trade.side {
+            TradeSide::Buy => {
+                pnl.buy_count += 1;
+                pnl.sol_spent += trade.sol_amount;
+                pnl.tokens_held += trade.token_amount;
+                tokens_bought += trade.token_amount;
+                cost += trade.sol_amount;
+                first_buy = first_buy.or(trade.timestamp);
+            }
+            TradeSide::Sell => {
+                pnl.sell_count += 1;
+                pnl.sol_received += trade.sol_amount;
+                tokens_sold += trade.token_amount;
+                last_sell = trade.timestamp.or(last_sell);
+                let sold = trade.token_amount.min(pnl.tokens_held);
+                if sold <= 0.0 || trade.token_amount <= 0.0 {
+                    continue;
+                }
+                let cost_of_sold = cost * sold / pnl.tokens_held;
+                pnl.realised_pnl_sol += trade.sol_amount * sold / trade.token_amount - cost_of_sold;
+                cost -= cost_of_sold;
+                sold_cost += cost_of_sold;
+                pnl.tokens_held -= sold;
+            }
+        }
+    }
+
+    pnl.roi = (sold_cost > 0.0).then(|| pnl.realised_pnl_sol / sold_cost);
+    pnl.entry_price = (tokens_bought > 0.0).then(|| pnl.sol_spent / tokens_bought);
+    pnl.exit_price = (tokens_sold > 0.0).then(|| pnl.sol_received / tokens_sold);
+    if let (Some(first_buy), Some(last_sell)) = (first_buy, last_sell) {
+        pnl.hold_secs = Some((last_sell - first_buy).max(0));
+    }
+    pnl
+}
+
+/// Fetches the trades of a wallet on the given tokens.
+///
+/// The signatures of the wallet's associated token accounts of each token are fetched, up to
+/// [`MAX_ACCOUNT_SIGNATURES`] per account, and the successful transactions are parsed in
+/// batches.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `shyft_api` - A reference to the Shyft API client.
+/// * `wallet` - The wallet to fetch trades for.
+/// * `mints` - The token mint addresses.
+/// * `delay_ms` - The delay in milliseconds between requests.
+///
+/// # Errors
+///
+/// This function will return an error if an RPC or Shyft request fails.
+pub(crate) async fn fetch_trades(
+    rpc_client: &RpcClient,
+    shyft_api: &ShyftApi,
+    wallet: &Pubkey,
+    mints: &[Pubkey],
+    delay_ms: u64,
+) -> Result<Vec<Trade>, crate::Error> {
+    let mut signatures = Vec::new();
+    let mut seen = HashSet::new();
+    for mint in mints.iter() {
+        for account in token_accounts(wallet, mint) {
+            let page = metrics::upstream(
+                "rpc",
+                rpc_client
+                    .get_signatures_for_address_with_config(
+                        &account,
+                        GetConfirmedSignaturesForAddress2Config {
+                            before: None,
+                            until: None,
+                            limit: Some(MAX_ACCOUNT_SIGNATURES),
+                            commitment: Some(CommitmentConfig::confirmed()),
+                        },
+                    )
+                    .await,
+            )?;
+            metrics::rate_limit_wait(delay_ms).await;
+            signatures.extend(
+                page.into_iter()
+                    .filter(|s| s.err.is_none() && seen.insert(s.signature.to_owned()))
+                    .map(|s| s.signature),
+            );
+        }
+    }
+    tracing::debug!(
+        "Fetched {} signatures of the token accounts of {}",
+        signatures.len(),
+        wallet
+    );
+
+    let wallet = wallet.to_string();
+    let mints: Vec<_> = mints.iter().map(Pubkey::to_string).collect();
+    let mut trades = Vec::new();
+    for chunk in signatures.chunks(range::PARSE_BATCH_SIZE) {
+        let parsed_txs = metrics::upstream(
+            "shyft",
+            shyft_api
+                .get_transaction_parse_selected(chunk, Some(true), None)
+                .await,
+        )?;
+        for tx in parsed_txs.iter() {
+            trades.extend(to_trades(tx, &wallet, &mints));
+        }
+        metrics::rate_limit_wait(delay_ms).await;
+    }
+    Ok(trades)
+}
+
+/// Returns the associated token accounts of a wallet for a mint under both token programs.
+///
+/// # Arguments
+///
+/// * `wallet` - The wallet owning the accounts.
+/// * `mint` - The token mint.
+fn token_accounts(wallet: &Pubkey, mint: &Pubkey) -> Vec<Pubkey> {
+    TOKEN_PROGRAMS
+        .iter()
+        .map(|program| {
+            Pubkey::find_program_address(
+                &[wallet.as_ref(), program.as_ref(), mint.as_ref()],
+                &ASSOCIATED_TOKEN_PROGRAM,
+            )
+            .0
+        })
+        .collect()
+}
+
+/// Extracts the swaps of a wallet between SOL and the given tokens from a parsed transaction.
+///
+/// # Arguments
+///
+/// * `tx` - The parsed transaction.
+/// * `wallet` - The wallet that made the swaps.
+/// * `mints` - The token mint addresses.
+fn to_trades(tx: &ParsedTransactionDetails, wallet: &str, mints: &[String]) -> Vec<Trade> {
+    let Some(tx_hash) = tx.signatures.first() else {
+        return Vec::new();
+    };
+    let timestamp = tx.raw.as_ref().and_then(|raw| raw["blockTime"].as_i64());
+
+    tx.actions
+        .iter()
+        .filter(|a| a.action_type == "SWAP")
+        .filter_map(|a| {
+            serde_json::from_value::<parsed_transaction_details::Swap>(a.info.clone()).ok()
+        })
+        .filter(|info| info.swapper == wallet)
+        .filter_map(|info| {
+            let (token_in, token_out) =
+                (info.tokens_swapped.token_in, info.tokens_swapped.token_out);
+            let (side, sol, token) = if token_in.symbol == "SOL" {
+                (TradeSide::Buy, token_in, token_out)
+            } else if token_out.symbol == "SOL" {
+                (TradeSide::Sell, token_out, token_in)
+            } else {
+                return None;
+            };
+            mints.contains(&token.token_address).then(|| Trade {
+                tx_hash: tx_hash.to_owned(),
+                mint: token.token_address,
+                side,
+                sol_amount: sol.amount,
+                token_amount: token.amount,
+                timestamp,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,302 @@
+//! # Scan Range
+//!
+//! This module restricts a scan to the swaps of a period instead of the latest ones.
+//!
+//! The bounds of the period are inclusive Unix timestamps, UTC dates or slots. The Cielo feed
+//! only serves the latest swaps, so the swaps of a period are found by paging the scanned
+//! wallet's signatures with `getSignaturesForAddress`, newest first, and parsing the buys among
+//! them with Shyft.
+
+use std::{fmt, str::FromStr};
+
+use cielo_rs_sdk::models::feed::Swap;
+use shyft_rs_sdk::{
+    models::parsed_transaction_details::{self, ParsedTransactionDetails},
+    ShyftApi,
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+
+use crate::metrics;
+
+/// The number of signatures requested per page.
+const SIGNATURES_PAGE_SIZE: usize = 1_000;
+
+/// The number of transactions parsed per Shyft request.
+pub(crate) const PARSE_BATCH_SIZE: usize = 100;
+
+/// Represents a bound of a scan range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeBound {
+    /// A Unix timestamp in seconds.
+    Timestamp(i64),
+    /// A slot.
+    Slot(u64),
+}
+
+impl RangeBound {
+    /// Returns whether a transaction landed after the bound.
+    ///
+    /// Transactions without a block time are never after a timestamp bound.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot of the transaction.
+    /// * `block_time` - The block time of the transaction, if known.
+    fn tx_is_newer(&self, slot: u64, block_time: Option<i64>) -> bool {
+        match self {
+            Self::Timestamp(timestamp) => block_time.is_some_and(|time| time > *timestamp),
+            Self::Slot(bound) => slot > *bound,
+        }
+    }
+
+    /// Returns whether a transaction landed before the bound.
+    ///
+    /// Transactions without a block time are never before a timestamp bound.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The slot of the transaction.
+    /// * `block_time` - The block time of the transaction, if known.
+    pub(crate) fn tx_is_older(&self, slot: u64, block_time: Option<i64>) -> bool {
+        match self {
+            Self::Timestamp(timestamp) => block_time.is_some_and(|time| time < *timestamp),
+            Self::Slot(bound) => slot < *bound,
+        }
+    }
+}
+
+impl FromStr for RangeBound {
+    type Err = String;
+
+    /// Parses a bound given as `slot:<SLOT>`, a Unix timestamp in seconds, or a UTC date as
+    /// `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SSZ`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(slot) = value.strip_prefix("slot:") {
+            return slot
+                .parse()
+                .map(Self::Slot)
+                .map_err(|_| format!("invalid slot {}", slot));
+        }
+        if let Ok(timestamp) = value.parse() {
+            return Ok(Self::Timestamp(timestamp));
+        }
+        parse_date(value).map(Self::Timestamp).ok_or(format!(
+            "expected slot:<SLOT>, a Unix timestamp or a date as YYYY-MM-DD[THH:MM:SSZ], got {}",
+            value
+        ))
+    }
+}
+
+impl serde::Serialize for RangeBound {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for RangeBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timestamp(timestamp) => write!(f, "{}", timestamp),
+            Self::Slot(slot) => write!(f, "slot:{}", slot),
+        }
+    }
+}
+
+/// Fetches the buys of a wallet within a range, newest first.
+///
+/// The signatures of the wallet are paged back from the newest one, and the successful ones
+/// within the range are parsed. Paging stops at the start of the range or of the history, or
+/// after `max_pages` pages, in which case a warning is logged and the older buys of the range
+/// are left out. Only the first buy of every token within the paged history is kept, like the
+/// fresh trades of the Cielo feed, and all of them are returned along with their parsed
+/// transactions, so that they are not parsed again.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `shyft_api` - A reference to the Shyft API client.
+/// * `wallet` - The wallet to fetch buys for.
+/// * `since` - The start of the range, if any.
+/// * `until` - The end of the range, if any.
+/// * `max_pages` - The maximum number of signature pages to fetch.
+/// * `delay_ms` - The delay in milliseconds between requests.
+///
+/// # Returns
+///
+/// The buys and their parsed transactions.
+///
+/// # Errors
+///
+/// This function will return an error if an RPC or Shyft request fails.
+pub(crate) async fn fetch_swaps_in_range(
+    rpc_client: &RpcClient,
+    shyft_api: &ShyftApi,
+    wallet: &Pubkey,
+    since: Option<RangeBound>,
+    until: Option<RangeBound>,
+    max_pages: usize,
+    delay_ms: u64,
+) -> Result<(Vec<Swap>, Vec<ParsedTransactionDetails>), crate::Error> {
+    let mut swaps: Vec<(Swap, ParsedTransactionDetails)> = Vec::new();
+    let mut before = None;
+    for page_num in 1.. {
+        let page = metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_signatures_for_address_with_config(
+                    wallet,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURES_PAGE_SIZE),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await,
+        )?;
+        tracing::debug!("Fetched {} signatures of {}", page.len(), wallet);
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(
+            Signature::from_str(&last.signature)
+                .map_err(|e| crate::Error::InvalidSignature(last.signature.to_owned(), e))?,
+        );
+        let exhausted = page.len() < SIGNATURES_PAGE_SIZE
+            || since.is_some_and(|since| since.tx_is_older(last.slot, last.block_time));
+
+        let signatures: Vec<_> = page
+            .into_iter()
+            .filter(|s| {
+                s.err.is_none()
+                    && !until.is_some_and(|until| until.tx_is_newer(s.slot, s.block_time))
+                    && !since.is_some_and(|since| since.tx_is_older(s.slot, s.block_time))
+            })
+            .collect();
+        for chunk in signatures.chunks(PARSE_BATCH_SIZE) {
+            let hashes: Vec<_> = chunk.iter().map(|s| s.signature.to_owned()).collect();
+            let parsed_txs = metrics::upstream(
+                "shyft",
+                shyft_api
+                    .get_transaction_parse_selected(&hashes, Some(true), None)
+                    .await,
+            )?;
+            // Signatures are newest first, so the first buy of a token is the last one seen
+            for tx in parsed_txs {
+                let status = tx
+                    .signatures
+                    .first()
+                    .and_then(|hash| chunk.iter().find(|s| s.signature == *hash));
+                if let Some(swap) = status.and_then(|status| to_swap(&tx, wallet, status)) {
+                    swaps.retain(|(s, _)| s.token1_address != swap.token1_address);
+                    swaps.push((swap, tx));
+                }
+            }
+            metrics::rate_limit_wait(delay_ms).await;
+        }
+
+        if exhausted {
+            break;
+        }
+        if page_num == max_pages {
+            tracing::warn!(
+                "Range of {} cut at its latest {} signatures, raise --max-range-pages",
+                wallet,
+                page_num * SIGNATURES_PAGE_SIZE
+            );
+            break;
+        }
+        metrics::rate_limit_wait(delay_ms).await;
+    }
+    tracing::info!(
+        "Found {} buys of {} between {:?} and {:?}",
+        swaps.len(),
+        wallet,
+        since,
+        until
+    );
+
+    swaps.sort_by_key(|(swap, _)| std::cmp::Reverse(swap.block));
+    Ok(swaps.into_iter().unzip())
+}
+
+/// Converts a parsed transaction of a wallet into a swap if it is a buy with SOL.
+///
+/// # Arguments
+///
+/// * `tx` - The parsed transaction.
+/// * `wallet` - The wallet that made the transaction.
+/// * `status` - The signature status with the slot and block time of the transaction.
+fn to_swap(
+    tx: &ParsedTransactionDetails,
+    wallet: &Pubkey,
+    status: &RpcConfirmedTransactionStatusWithSignature,
+) -> Option<Swap> {
+    let wallet = wallet.to_string();
+    tx.actions
+        .iter()
+        .filter(|a| a.action_type == "SWAP")
+        .filter_map(|a| {
+            serde_json::from_value::<parsed_transaction_details::Swap>(a.info.clone()).ok()
+        })
+        .find(|info| info.swapper == wallet && info.tokens_swapped.token_in.symbol == "SOL")
+        .map(|info| {
+            let (token_in, token_out) =
+                (info.tokens_swapped.token_in, info.tokens_swapped.token_out);
+            Swap {
+                wallet,
+                tx_hash: status.signature.to_owned(),
+                block: status.slot,
+                timestamp: status.block_time.unwrap_or_default(),
+                token0_address: token_in.token_address,
+                token0_amount: token_in.amount,
+                token0_symbol: token_in.symbol,
+                token1_address: token_out.token_address,
+                token1_amount: token_out.amount,
+                token1_symbol: token_out.symbol,
+            }
+        })
+}
+
+/// Parses a UTC date given as `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SSZ` into a Unix timestamp.
+///
+/// # Arguments
+///
+/// * `value` - The date.
+fn parse_date(value: &str) -> Option<i64> {
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time.strip_suffix('Z')?)),
+        None => (value, None),
+    };
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let seconds = match time {
+        Some(time) => {
+            let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+            let (hour, minute, second) =
+                (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+            if hour > 23 || minute > 59 || second > 60 {
+                return None;
+            }
+            hour * 3_600 + minute * 60 + second
+        }
+        None => 0,
+    };
+
+    // Days since the Unix epoch in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some(days * 86_400 + seconds)
+}
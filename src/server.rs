@@ -0,0 +1,457 @@
+//! # HTTP API Server
+//!
+//! This module serves a small REST API to run scans on demand.
+//!
+//! Submitted scans are queued as jobs and run in the background, with at most `max_concurrent`
+//! jobs running at the same time. Every job uses its own [`Scanner`], built from the server's scan
+//! parameters with the overrides of the request. The report of every successful scan is sent to
+//! the configured webhooks.
+//!
+//! | Method | Path                 | Description                                        |
+//! |--------|----------------------|----------------------------------------------------|
+//! | `POST` | `/scans`             | Submit a scan, see [`ScanRequest`]                 |
+//! | `GET`  | `/scans`             | List the jobs                                      |
+//! | `GET`  | `/scans/{id}`        | Get the status and progress of a job, see [`Job`]  |
+//! | `GET`  | `/scans/{id}/result` | Get the detected wallets of a finished job         |
+//! | `GET`  | `/health`            | Check that the server is up                        |
+//! | `GET`  | `/metrics`           | Get the metrics in Prometheus text format          |
+
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    io::IsTerminal,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{
+    body::HttpBody,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::Semaphore;
+
+use crate::{Notification, Notifier, RepeatingWallet, Scanner, ServeArgs};
+
+/// Maximum number of finished jobs kept in memory. The oldest are dropped first.
+const MAX_FINISHED_JOBS: usize = 1000;
+
+/// Maximum size of a request body in bytes.
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// Represents a scan submitted to the server.
+///
+/// Parameters that are not given default to the server's scan parameters.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ScanRequest {
+    /// The wallet to scan.
+    pub wallet: String,
+    /// Number of recent fresh swaps from the wallet to consider (max 100).
+    pub swap_num: Option<u32>,
+    /// Number of transactions to scan for each swap (max 100).
+    pub scan_tx_count: Option<u32>,
+    /// Check same-slot buyers for sandwich (MEV) patterns.
+    pub mev_check: Option<bool>,
+    /// Trace the SOL funding of detected wallets and cluster them.
+    pub cluster_funding: Option<bool>,
+    /// Reconstruct the trades of detected wallets and report their PnL.
+    pub leader_pnl: Option<bool>,
+    /// Compare the trade outcomes of the wallet and the detected wallets.
+    pub compare_outcomes: Option<bool>,
+    /// Annotate copied buys with the age and launch platform of the bought token.
+    pub token_launch: Option<bool>,
+    /// Only analyse swaps on tokens created at most this many seconds before the swap.
+    pub max_token_age: Option<u64>,
+}
+
+/// The status of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for a free slot.
+    Queued,
+    /// Scanning.
+    Running,
+    /// Finished successfully.
+    Done,
+    /// Finished with an error.
+    Failed,
+}
+
+/// Represents the progress of a running job.
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+pub struct JobProgress {
+    /// The number of analysed swaps.
+    pub swaps_done: usize,
+    /// The total number of swaps to analyse, known once the swaps are fetched.
+    pub swaps_total: usize,
+}
+
+/// Represents a scan job.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Job {
+    /// The job id.
+    pub id: u64,
+    /// The scanned wallet address.
+    pub wallet: String,
+    /// The job status.
+    pub status: JobStatus,
+    /// The scan progress.
+    pub progress: JobProgress,
+    /// The error message if the job failed.
+    pub error: Option<String>,
+    /// The time the job was submitted, as a Unix timestamp in seconds.
+    pub submitted_at: u64,
+    /// The time the job started running, as a Unix timestamp in seconds.
+    pub started_at: Option<u64>,
+    /// The time the job finished, as a Unix timestamp in seconds.
+    pub finished_at: Option<u64>,
+    /// The detected wallets once the job is done.
+    #[serde(skip)]
+    pub result: Option<Vec<RepeatingWallet>>,
+}
+
+/// The state shared by the request handlers and the jobs.
+struct State {
+    /// The server arguments.
+    args: ServeArgs,
+    /// The jobs by id, in submission order.
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    /// The id of the next job.
+    next_id: Mutex<u64>,
+    /// The permits of the running jobs.
+    slots: Arc<Semaphore>,
+    /// The webhook notifier, if any webhook is configured.
+    notifier: Option<Notifier>,
+}
+
+impl State {
+    /// Updates a job.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The job id.
+    /// * `update` - Called with the job if it still exists.
+    fn update(&self, id: u64, update: impl FnOnce(&mut Job)) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(job) = jobs.get_mut(&id) {
+            update(job);
+        }
+    }
+}
+
+/// Runs the HTTP API server until interrupted with Ctrl-C.
+///
+/// # Arguments
+///
+/// * `args` - The server arguments containing the default scan parameters.
+///
+/// # Errors
+///
+/// This function will return an error if the address cannot be bound or the server fails.
+pub async fn serve(args: ServeArgs) -> Result<(), crate::Error> {
+    let bind = args.bind;
+    let state = Arc::new(State {
+        slots: Arc::new(Semaphore::new(args.max_concurrent)),
+        notifier: Notifier::new(&args.webhook),
+        args,
+        jobs: Mutex::new(BTreeMap::new()),
+        next_id: Mutex::new(1),
+    });
+
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request))) }
+    });
+
+    let server = Server::try_bind(&bind)?.serve(make_service);
+    tracing::info!("Listening on {}", server.local_addr());
+    crate::print_if_terminal!("Listening on http://{}", server.local_addr());
+
+    server
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Routes a request to its handler.
+///
+/// # Arguments
+///
+/// * `state` - The shared state.
+/// * `request` - The request.
+async fn handle(state: Arc<State>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let segments: Vec<_> = request
+        .uri()
+        .path()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect();
+    let segments: Vec<_> = segments.iter().map(String::as_str).collect();
+    let method = request.method().clone();
+    tracing::debug!("{} {}", method, request.uri().path());
+
+    let response = match (&method, segments.as_slice()) {
+        (&Method::GET, ["health"]) => json(StatusCode::OK, &serde_json::json!({"status": "ok"})),
+        (&Method::GET, ["metrics"]) => crate::metrics::response(),
+        (&Method::POST, ["scans"]) => submit(&state, request).await,
+        (&Method::GET, ["scans"]) => {
+            let jobs = state.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            json(StatusCode::OK, &jobs.values().collect::<Vec<_>>())
+        }
+        (&Method::GET, ["scans", id]) => match find_job(&state, id) {
+            Some(job) => json(StatusCode::OK, &job),
+            None => error(StatusCode::NOT_FOUND, "Job not found"),
+        },
+        (&Method::GET, ["scans", id, "result"]) => match find_job(&state, id) {
+            Some(Job {
+                result: Some(result),
+                ..
+            }) => json(StatusCode::OK, &result),
+            Some(job) => error(
+                StatusCode::CONFLICT,
+                &job.error
+                    .unwrap_or_else(|| format!("Job is {:?}", job.status).to_lowercase()),
+            ),
+            None => error(StatusCode::NOT_FOUND, "Job not found"),
+        },
+        (_, ["health"] | ["metrics"] | ["scans"] | ["scans", _] | ["scans", _, "result"]) => {
+            error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+        }
+        _ => error(StatusCode::NOT_FOUND, "Not found"),
+    };
+
+    Ok(response)
+}
+
+/// Reads a request body of at most [`MAX_BODY_BYTES`].
+///
+/// A body announced as larger is rejected before it is read. Chunked bodies do not announce
+/// their size, so the limit is also enforced while reading.
+///
+/// # Arguments
+///
+/// * `body` - The request body.
+///
+/// # Errors
+///
+/// This function will return the error response if the body is too large or fails to be read.
+async fn read_body(mut body: Body) -> Result<Vec<u8>, Response<Body>> {
+    let too_large = || error(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large");
+    if body
+        .size_hint()
+        .upper()
+        .is_some_and(|size| size > MAX_BODY_BYTES)
+    {
+        return Err(too_large());
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error(StatusCode::BAD_REQUEST, &e.to_string()))?;
+        if (bytes.len() + chunk.len()) as u64 > MAX_BODY_BYTES {
+            return Err(too_large());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Handles the submission of a scan.
+///
+/// # Arguments
+///
+/// * `state` - The shared state.
+/// * `request` - The request with a [`ScanRequest`] body.
+async fn submit(state: &Arc<State>, request: Request<Body>) -> Response<Body> {
+    let body = match read_body(request.into_body()).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let scan: ScanRequest = match serde_json::from_slice(&body) {
+        Ok(scan) => scan,
+        Err(e) => return error(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e)),
+    };
+    let Ok(wallet) = Pubkey::from_str(&scan.wallet) else {
+        return error(StatusCode::BAD_REQUEST, "Invalid wallet address");
+    };
+
+    let mut scan_args = state.args.scan.clone();
+    if let Some(swap_num) = scan.swap_num {
+        scan_args.swap_num = swap_num;
+    }
+    if let Some(scan_tx_count) = scan.scan_tx_count {
+        scan_args.scan_tx_count = scan_tx_count;
+    }
+    if !(1..=100).contains(&scan_args.swap_num) || !(1..=100).contains(&scan_args.scan_tx_count) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "swap_num and scan_tx_count must be between 1 and 100",
+        );
+    }
+    scan_args.mev_check = scan.mev_check.unwrap_or(scan_args.mev_check);
+    scan_args.cluster_funding = scan.cluster_funding.unwrap_or(scan_args.cluster_funding);
+    scan_args.leader_pnl = scan.leader_pnl.unwrap_or(scan_args.leader_pnl);
+    scan_args.compare_outcomes = scan.compare_outcomes.unwrap_or(scan_args.compare_outcomes);
+    scan_args.token_launch = scan.token_launch.unwrap_or(scan_args.token_launch);
+    scan_args.max_token_age = scan.max_token_age.or(scan_args.max_token_age);
+
+    let job = {
+        let mut jobs = state.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let queued = jobs
+            .values()
+            .filter(|j| j.status == JobStatus::Queued)
+            .count();
+        if queued >= state.args.max_queued {
+            return error(StatusCode::TOO_MANY_REQUESTS, "Too many queued scans");
+        }
+
+        let finished: Vec<_> = jobs
+            .values()
+            .filter(|j| matches!(j.status, JobStatus::Done | JobStatus::Failed))
+            .map(|j| j.id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS - 1))
+        {
+            jobs.remove(id);
+        }
+
+        let mut next_id = state.next_id.lock().unwrap_or_else(|e| e.into_inner());
+        let job = Job {
+            id: *next_id,
+            wallet: wallet.to_string(),
+            status: JobStatus::Queued,
+            progress: JobProgress::default(),
+            error: None,
+            submitted_at: now(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+        };
+        *next_id += 1;
+        jobs.insert(job.id, job.clone());
+        job
+    };
+    tracing::info!("Queued scan {} of {}", job.id, wallet);
+
+    let id = job.id;
+    let state = state.clone();
+    tokio::spawn(async move {
+        let Ok(_permit) = state.slots.clone().acquire_owned().await else {
+            return;
+        };
+        state.update(id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(now());
+        });
+
+        let progress_state = state.clone();
+        let scan = tokio::spawn(async move {
+            match Scanner::new(&scan_args) {
+                Ok(scanner) => {
+                    scanner
+                        .with_progress(move |swaps_done, swaps_total| {
+                            progress_state.update(id, |job| {
+                                job.progress = JobProgress {
+                                    swaps_done,
+                                    swaps_total,
+                                }
+                            });
+                        })
+                        .scan(&wallet)
+                        .await
+                }
+                Err(e) => Err(e),
+            }
+        });
+        // A panicking scan fails its job instead of leaving it running
+        let result = match scan.await {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Scan task failed: {}", e)),
+        };
+
+        if let Err(e) = result.as_ref() {
+            tracing::error!("Scan {} of {} failed: {}", id, wallet, e);
+        }
+        let notification = match (result.as_ref(), state.notifier.as_ref()) {
+            (Ok(leaders), Some(_)) => Some(Notification::Report {
+                wallet: wallet.to_string(),
+                leaders: leaders.clone(),
+            }),
+            _ => None,
+        };
+        state.update(id, |job| {
+            job.finished_at = Some(now());
+            match result {
+                Ok(leaders) => {
+                    job.status = JobStatus::Done;
+                    job.result = Some(leaders);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                }
+            }
+        });
+
+        // Deliver after the job is marked done, so retries do not delay the result
+        if let (Some(notifier), Some(notification)) = (state.notifier.as_ref(), notification) {
+            notifier.notify(&notification).await;
+        }
+    });
+
+    json(StatusCode::ACCEPTED, &job)
+}
+
+/// Looks up a job by the id in a request path.
+///
+/// # Arguments
+///
+/// * `state` - The shared state.
+/// * `id` - The job id as given in the path.
+fn find_job(state: &State, id: &str) -> Option<Job> {
+    let id = id.parse().ok()?;
+    let jobs = state.jobs.lock().unwrap_or_else(|e| e.into_inner());
+    jobs.get(&id).cloned()
+}
+
+/// Builds a JSON response.
+///
+/// # Arguments
+///
+/// * `status` - The response status.
+/// * `body` - The value to serialize as the response body.
+fn json(status: StatusCode, body: &impl serde::Serialize) -> Response<Body> {
+    let mut response = Response::new(Body::from(serde_json::to_vec(body).unwrap_or_default()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// Builds a JSON error response.
+///
+/// # Arguments
+///
+/// * `status` - The response status.
+/// * `message` - The error message.
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, &serde_json::json!({ "error": message }))
+}
+
+/// Returns the current time as a Unix timestamp in seconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,101 @@
+//! # Buy Sizes
+//!
+//! This module relates the buy sizes of the scanned wallet to those of a detected wallet.
+//!
+//! Copy trading bots are configured either with a fixed amount per trade or with a share of the
+//! leader's amount. A consistent relation over the copied buys is strong evidence that the
+//! scanned wallet runs a bot against that leader, while uncorrelated sizes hint at a manual
+//! trader or a coincidence.
+
+use std::fmt;
+
+use crate::{PrevBuy, SizeCorrelation, SizePattern};
+
+/// Minimum number of copied buys with known sizes to relate the sizes.
+const MIN_SIZE_PAIRS: usize = 2;
+
+/// Maximum coefficient of variation of the scanned wallet's sizes for a fixed-size copy.
+const FIXED_SIZE_MAX_CV: f64 = 0.05;
+
+/// Maximum coefficient of variation of the size ratios for a proportional copy.
+const PROPORTIONAL_MAX_CV: f64 = 0.1;
+
+impl fmt::Display for SizePattern {
+    /// Writes the pattern as named in the JSON output.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FixedSize => "fixed_size",
+            Self::Proportional => "proportional",
+            Self::Uncorrelated => "uncorrelated",
+        })
+    }
+}
+
+/// Relates the buy sizes of the scanned wallet to those of a detected wallet.
+///
+/// Only the copied buys with a known size on both sides are considered. The scanned wallet
+/// copies with a fixed size if its sizes barely vary, and proportionally if the ratio between
+/// the sizes barely varies. Fixed sizes take precedence, as both hold when the detected wallet
+/// always buys the same amount as well.
+///
+/// # Arguments
+///
+/// * `buys` - The copied buys of the detected wallet.
+///
+/// # Returns
+///
+/// The relation between the sizes, or `None` if fewer than two copied buys have known sizes.
+pub fn size_correlation(buys: &[PrevBuy]) -> Option<SizeCorrelation> {
+    let pairs: Vec<_> = buys
+        .iter()
+        .filter(|b| b.sol_amount > 0.0 && b.target_sol_amount > 0.0)
+        .map(|b| (b.target_sol_amount, b.sol_amount))
+        .collect();
+    if pairs.len() < MIN_SIZE_PAIRS {
+        return None;
+    }
+
+    let target_sizes: Vec<_> = pairs.iter().map(|(target, _)| *target).collect();
+    let ratios: Vec<_> = pairs
+        .iter()
+        .map(|(target, leader)| target / leader)
+        .collect();
+    let target_size_cv = coefficient_of_variation(&target_sizes);
+    let ratio_cv = coefficient_of_variation(&ratios);
+
+    let pattern = if target_size_cv <= FIXED_SIZE_MAX_CV {
+        SizePattern::FixedSize
+    } else if ratio_cv <= PROPORTIONAL_MAX_CV {
+        SizePattern::Proportional
+    } else {
+        SizePattern::Uncorrelated
+    };
+    Some(SizeCorrelation {
+        pattern,
+        pairs: pairs.len(),
+        avg_target_sol: mean(&target_sizes),
+        avg_ratio: mean(&ratios),
+        target_size_cv,
+        ratio_cv,
+    })
+}
+
+/// Returns the mean of non-empty values.
+///
+/// # Arguments
+///
+/// * `values` - The values.
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Returns the standard deviation of positive values relative to their mean.
+///
+/// # Arguments
+///
+/// * `values` - The values.
+fn coefficient_of_variation(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    variance.sqrt() / mean
+}
@@ -0,0 +1,378 @@
+//! # SQLite Store
+//!
+//! This module persists scans to an SQLite database, so their history can be queried with SQL and
+//! relationships compared over time.
+//!
+//! Every scan of a [`Scanner`](crate::Scanner) opened with `--db` is recorded, including failed
+//! ones, in five tables:
+//!
+//! * `scans`: the scanned wallet, the scan parameters, timing and error of every scan. The main
+//!   parameters have their own columns, and all of them are in the `params` JSON object.
+//! * `swaps`: the fresh swaps of the scanned wallet that were analysed.
+//! * `prev_buys`: every buy observed before an analysed swap, with its slot, lag in slots and
+//!   the SOL and token amounts of both buys.
+//! * `candidates`: the detected wallets with their copy count, average lag, MEV flag and cluster.
+//! * `sandwiches`: the sandwiches the detected wallets executed around the analysed swaps.
+//!
+//! Recorded scans can be loaded back as [`TargetScan`]s, e.g. to diff them.
+
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{PrevBuy, RepeatingWallet, Sandwich, ScanArgs, TargetScan};
+
+/// The version of the schema, stored in `PRAGMA user_version`.
+const SCHEMA_VERSION: i64 = 1;
+
+/// How long to wait for a lock held by another connection, in milliseconds.
+const BUSY_TIMEOUT_MS: u64 = 5_000;
+
+/// The database schema.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    wallet TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL,
+    swap_num INTEGER NOT NULL,
+    scan_tx_count INTEGER NOT NULL,
+    mev_check INTEGER NOT NULL,
+    cluster_funding INTEGER NOT NULL,
+    params TEXT NOT NULL,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS scans_wallet ON scans (wallet, started_at);
+
+CREATE TABLE IF NOT EXISTS swaps (
+    scan_id INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+    tx_hash TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    token_in TEXT NOT NULL,
+    amount_in REAL NOT NULL,
+    token_out TEXT NOT NULL,
+    amount_out REAL NOT NULL,
+    PRIMARY KEY (scan_id, tx_hash)
+);
+
+CREATE TABLE IF NOT EXISTS prev_buys (
+    scan_id INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+    swap_tx_hash TEXT NOT NULL,
+    wallet TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    block_diff INTEGER NOT NULL,
+    sol_amount REAL NOT NULL DEFAULT 0,
+    token_amount REAL NOT NULL DEFAULT 0,
+    target_sol_amount REAL NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS prev_buys_scan ON prev_buys (scan_id, wallet);
+CREATE INDEX IF NOT EXISTS prev_buys_wallet ON prev_buys (wallet);
+
+CREATE TABLE IF NOT EXISTS candidates (
+    scan_id INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+    wallet TEXT NOT NULL,
+    copy_count INTEGER NOT NULL,
+    avg_block_diff REAL NOT NULL,
+    mev INTEGER NOT NULL,
+    cluster INTEGER,
+    PRIMARY KEY (scan_id, wallet)
+);
+CREATE INDEX IF NOT EXISTS candidates_wallet ON candidates (wallet);
+
+CREATE TABLE IF NOT EXISTS sandwiches (
+    scan_id INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+    wallet TEXT NOT NULL,
+    target_tx_hash TEXT NOT NULL,
+    front_run_tx_hash TEXT NOT NULL,
+    back_run_tx_hash TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sandwiches_scan ON sandwiches (scan_id, wallet);
+";
+
+/// Represents a completed scan to record.
+///
+/// The record owns its data, so it can be moved to a blocking task.
+pub(crate) struct ScanRecord {
+    /// The scanned wallet address.
+    pub wallet: String,
+    /// The scan parameters.
+    pub args: ScanArgs,
+    /// The time the scan started, as a Unix timestamp in seconds.
+    pub started_at: u64,
+    /// The time the scan finished, as a Unix timestamp in seconds.
+    pub finished_at: u64,
+    /// The analysed swaps of the scanned wallet.
+    pub swaps: Vec<cielo_rs_sdk::models::feed::Swap>,
+    /// The observed previous buys with the hash of the swap they preceded and the buyer.
+    pub prev_buys: Vec<(String, String, PrevBuy)>,
+    /// The detected wallets, or the error message if the scan failed.
+    pub result: Result<Vec<RepeatingWallet>, String>,
+}
+
+/// Represents an SQLite database recording scans.
+///
+/// The connection is guarded by a mutex, so a store can be shared between tasks.
+#[derive(Debug)]
+pub struct Store {
+    /// The database connection.
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens or creates a database and creates the schema if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The database file.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the database cannot be opened or was created by a
+    /// newer version.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT_MS))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(crate::Error::StoreVersion(version));
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Records a scan with its swaps, previous buys and detected wallets in a single transaction.
+    ///
+    /// This function blocks on the database, so call it from a blocking task in async code.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The scan to record.
+    ///
+    /// # Returns
+    ///
+    /// The id of the recorded scan.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to the database fails.
+    pub(crate) fn record_scan(&self, record: &ScanRecord) -> Result<i64, crate::Error> {
+        let mut conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scans (wallet, started_at, finished_at, swap_num, scan_tx_count, \
+             mev_check, cluster_funding, params, error) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                record.wallet,
+                record.started_at,
+                record.finished_at,
+                record.args.swap_num,
+                record.args.scan_tx_count,
+                record.args.mev_check,
+                record.args.cluster_funding,
+                serde_json::to_string(&record.args).expect("Scan parameters always serialize"),
+                record.result.as_ref().err(),
+            ],
+        )?;
+        let scan_id = tx.last_insert_rowid();
+
+        {
+            let mut insert_swap = tx.prepare(
+                "INSERT OR IGNORE INTO swaps (scan_id, tx_hash, slot, timestamp, token_in, \
+                 amount_in, token_out, amount_out) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for swap in record.swaps.iter() {
+                insert_swap.execute(params![
+                    scan_id,
+                    swap.tx_hash,
+                    swap.block,
+                    swap.timestamp,
+                    swap.token0_address,
+                    swap.token0_amount,
+                    swap.token1_address,
+                    swap.token1_amount,
+                ])?;
+            }
+
+            let mut insert_buy = tx.prepare(
+                "INSERT INTO prev_buys (scan_id, swap_tx_hash, wallet, tx_hash, slot, block_diff, \
+                 sol_amount, token_amount, target_sol_amount) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for (swap_tx_hash, wallet, buy) in record.prev_buys.iter() {
+                insert_buy.execute(params![
+                    scan_id,
+                    swap_tx_hash,
+                    wallet,
+                    buy.tx_hash,
+                    buy.slot,
+                    buy.block_diff,
+                    buy.sol_amount,
+                    buy.token_amount,
+                    buy.target_sol_amount,
+                ])?;
+            }
+
+            let mut insert_candidate = tx.prepare(
+                "INSERT INTO candidates (scan_id, wallet, copy_count, avg_block_diff, mev, cluster) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for leader in record.result.as_deref().unwrap_or_default() {
+                let avg_block_diff = leader.txs.iter().map(|t| t.block_diff as f64).sum::<f64>()
+                    / leader.txs.len().max(1) as f64;
+                insert_candidate.execute(params![
+                    scan_id,
+                    leader.wallet,
+                    leader.txs.len(),
+                    avg_block_diff,
+                    leader.is_mev(),
+                    leader.cluster,
+                ])?;
+            }
+
+            let mut insert_sandwich = tx.prepare(
+                "INSERT INTO sandwiches (scan_id, wallet, target_tx_hash, front_run_tx_hash, \
+                 back_run_tx_hash, slot) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for leader in record.result.as_deref().unwrap_or_default() {
+                for sandwich in leader.sandwiches.iter() {
+                    insert_sandwich.execute(params![
+                        scan_id,
+                        leader.wallet,
+                        sandwich.target_tx_hash,
+                        sandwich.front_run_tx_hash,
+                        sandwich.back_run_tx_hash,
+                        sandwich.slot,
+                    ])?;
+                }
+            }
+        }
+
+        tx.commit()?;
+        tracing::debug!("Recorded scan {} of {}", scan_id, record.wallet);
+        Ok(scan_id)
+    }
+
+    /// Loads a recorded scan.
+    ///
+    /// The detected wallets are rebuilt from the candidates, their previous buys and sandwiches,
+    /// and their buy sizes are related again. Funding, PnL, outcomes, token metadata and launches
+    /// are not recorded, so they are left empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The scan id.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the scan does not exist or reading fails.
+    pub fn load_scan(&self, id: i64) -> Result<TargetScan, crate::Error> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let (wallet, error): (String, Option<String>) = conn
+            .query_row(
+                "SELECT wallet, error FROM scans WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or(crate::Error::ScanNotFound(id))?;
+
+        let mut leaders = conn
+            .prepare(
+                "SELECT wallet, cluster FROM candidates WHERE scan_id = ?1 \
+                 ORDER BY mev, copy_count DESC, wallet",
+            )?
+            .query_map([id], |row| {
+                Ok(RepeatingWallet {
+                    wallet: row.get(0)?,
+                    txs: Vec::new(),
+                    sandwiches: Vec::new(),
+                    funding: Vec::new(),
+                    cluster: row.get(1)?,
+                    pnl: None,
+                    outcomes: None,
+                    sizing: None,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut select_sandwiches = conn.prepare(
+            "SELECT target_tx_hash, front_run_tx_hash, back_run_tx_hash, slot FROM sandwiches \
+             WHERE scan_id = ?1 AND wallet = ?2 ORDER BY rowid",
+        )?;
+        let mut select_buys = conn.prepare(
+            "SELECT p.tx_hash, p.slot, p.block_diff, s.token_out, p.sol_amount, p.token_amount, \
+             p.target_sol_amount FROM prev_buys p \
+             LEFT JOIN swaps s ON s.scan_id = p.scan_id AND s.tx_hash = p.swap_tx_hash \
+             WHERE p.scan_id = ?1 AND p.wallet = ?2 ORDER BY p.rowid",
+        )?;
+        for leader in leaders.iter_mut() {
+            leader.txs = select_buys
+                .query_map(params![id, leader.wallet], |row| {
+                    Ok(PrevBuy {
+                        tx_hash: row.get(0)?,
+                        slot: row.get(1)?,
+                        block_diff: row.get(2)?,
+                        mint: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                        sol_amount: row.get(4)?,
+                        token_amount: row.get(5)?,
+                        target_sol_amount: row.get(6)?,
+                        ..Default::default()
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+            leader.sizing = crate::size_correlation(&leader.txs);
+            leader.sandwiches = select_sandwiches
+                .query_map(params![id, leader.wallet], |row| {
+                    Ok(Sandwich {
+                        target_tx_hash: row.get(0)?,
+                        front_run_tx_hash: row.get(1)?,
+                        back_run_tx_hash: row.get(2)?,
+                        slot: row.get(3)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(TargetScan {
+            wallet,
+            leaders,
+            error,
+        })
+    }
+
+    /// Returns the ids of the latest successful scans of a wallet.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The scanned wallet address.
+    /// * `limit` - The maximum number of scans to return.
+    ///
+    /// # Returns
+    ///
+    /// The scan ids, newest first.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading fails.
+    pub fn latest_scans(&self, wallet: &str, limit: usize) -> Result<Vec<i64>, crate::Error> {
+        let conn = self.conn.lock().unwrap_or_else(|e| e.into_inner());
+        let ids = conn
+            .prepare(
+                "SELECT id FROM scans WHERE wallet = ?1 AND error IS NULL \
+                 ORDER BY started_at DESC, id DESC LIMIT ?2",
+            )?
+            .query_map(params![wallet, limit], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+}
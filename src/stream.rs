@@ -0,0 +1,387 @@
+//! # Streaming Source
+//!
+//! This module subscribes to Solana PubSub notifications so that new activity is picked up in
+//! near real time instead of waiting for the next poll.
+//!
+//! Watched wallets and the mints they just bought are subscribed to with `logsSubscribe`, and
+//! `signatureSubscribe` is used to wait for a notified transaction to be confirmed before it is
+//! fetched through the regular APIs. Every subscription runs in its own task on a shared
+//! connection and forwards its notifications to a single channel.
+//!
+//! The transactions seen on the subscribed mints are recorded in a [`MintHistory`], which the
+//! [`Scanner`](crate::Scanner) uses as the history of previous buys of later swaps on those mints.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use solana_client::{
+    nonblocking::pubsub_client::PubsubClient,
+    rpc_config::{RpcSignatureSubscribeConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+/// Maximum number of events waiting to be received. The gRPC stream is not read further until the
+/// watch loop catches up, while websocket notifications beyond it are dropped.
+pub(crate) const EVENT_CAPACITY: usize = 1_024;
+
+/// Maximum number of signatures recorded per mint.
+const MAX_SIGNATURES_PER_MINT: usize = 500;
+
+/// Number of slots after which a mint without new transactions is dropped (about an hour).
+const MINT_EXPIRY_SLOTS: u64 = 9_000;
+
+/// The signatures recorded on a mint with their slot, oldest first.
+type MintSignatures = VecDeque<(String, u64)>;
+
+/// Represents the recent successful transaction signatures per mint seen on a stream.
+///
+/// The history is shared between the stream recording it and the scanner reading it, which uses
+/// it instead of paging `getSignaturesForAddress` when it covers the swap being analysed.
+#[derive(Debug, Clone, Default)]
+pub struct MintHistory {
+    /// The recorded signatures with their slot per mint, oldest first.
+    inner: Arc<Mutex<HashMap<String, MintSignatures>>>,
+}
+
+impl MintHistory {
+    /// Records a transaction on a mint.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The mint address.
+    /// * `signature` - The transaction signature.
+    /// * `slot` - The slot of the transaction.
+    pub fn record(&self, mint: &str, signature: &str, slot: u64) {
+        let mut mints = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let signatures = mints.entry(mint.to_owned()).or_default();
+        if signatures.len() == MAX_SIGNATURES_PER_MINT {
+            signatures.pop_front();
+        }
+        signatures.push_back((signature.to_owned(), slot));
+    }
+
+    /// Drops the mints without transactions in the last [`MINT_EXPIRY_SLOTS`] slots.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The current slot.
+    pub fn sweep(&self, slot: u64) {
+        let mut mints = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        mints.retain(|_, signatures| {
+            signatures
+                .back()
+                .is_some_and(|(_, last)| last + MINT_EXPIRY_SLOTS > slot)
+        });
+    }
+
+    /// Returns the signatures recorded on a mint before a transaction.
+    ///
+    /// The history only replaces the RPC when it covers the transaction and holds at least
+    /// `limit` signatures before it, since older transactions of the mint may predate the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The mint address.
+    /// * `before` - The signature of the transaction.
+    /// * `limit` - The number of signatures to return.
+    ///
+    /// # Returns
+    ///
+    /// The `limit` signatures preceding the transaction, newest first, or `None` if the history
+    /// does not cover them.
+    pub fn signatures_before(&self, mint: &str, before: &str, limit: usize) -> Option<Vec<String>> {
+        let mints = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let signatures = mints.get(mint)?;
+        let position = signatures.iter().position(|(s, _)| s == before)?;
+        if position < limit {
+            return None;
+        }
+
+        Some(
+            signatures
+                .range(position - limit..position)
+                .rev()
+                .map(|(s, _)| s.to_owned())
+                .collect(),
+        )
+    }
+}
+
+/// Represents a notification received from a subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamEvent {
+    /// A successful transaction mentioning a watched wallet.
+    Wallet {
+        /// The watched wallet.
+        wallet: Pubkey,
+        /// The transaction signature.
+        signature: String,
+        /// The slot of the transaction.
+        slot: u64,
+    },
+    /// A successful transaction mentioning a watched mint.
+    Mint {
+        /// The watched mint.
+        mint: Pubkey,
+        /// The transaction signature.
+        signature: String,
+        /// The slot of the transaction.
+        slot: u64,
+    },
+    /// A buy with SOL of a watched wallet, decoded from the transaction. Only sent by sources
+    /// that stream full transactions.
+    Swap {
+        /// The watched wallet.
+        wallet: Pubkey,
+        /// The transaction signature.
+        signature: String,
+        /// The slot of the transaction.
+        slot: u64,
+        /// The bought mint.
+        mint: Pubkey,
+        /// The SOL spent in lamports.
+        lamports: u64,
+        /// The tokens received in base units.
+        token_amount: u64,
+        /// The decimals of the bought token.
+        decimals: u8,
+    },
+}
+
+/// Represents a set of PubSub subscriptions sharing one websocket connection.
+pub struct SwapStream {
+    /// The PubSub client shared by the subscription tasks.
+    client: Arc<PubsubClient>,
+    /// The sender handed to every subscription task.
+    sender: mpsc::Sender<StreamEvent>,
+    /// The receiver of the notifications of all subscriptions.
+    receiver: mpsc::Receiver<StreamEvent>,
+    /// The subscription tasks by subscribed account.
+    tasks: HashMap<Pubkey, JoinHandle<()>>,
+}
+
+impl SwapStream {
+    /// Connects to a PubSub websocket endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `ws_url` - The websocket URL, e.g. `wss://api.mainnet-beta.solana.com`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the connection cannot be established.
+    pub async fn connect(ws_url: &str) -> Result<Self, crate::Error> {
+        let client = PubsubClient::new(ws_url).await?;
+        let (sender, receiver) = mpsc::channel(EVENT_CAPACITY);
+
+        Ok(Self {
+            client: Arc::new(client),
+            sender,
+            receiver,
+            tasks: HashMap::new(),
+        })
+    }
+
+    /// Subscribes to the transactions mentioning a wallet.
+    ///
+    /// Subscribing to a wallet that is already subscribed to does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The wallet to watch.
+    pub fn watch_wallet(&mut self, wallet: Pubkey) {
+        self.subscribe(wallet, move |signature, slot| StreamEvent::Wallet {
+            wallet,
+            signature,
+            slot,
+        });
+    }
+
+    /// Subscribes to the transactions mentioning a mint.
+    ///
+    /// Subscribing to a mint that is already subscribed to does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The mint to watch.
+    pub fn watch_mint(&mut self, mint: Pubkey) {
+        self.subscribe(mint, move |signature, slot| StreamEvent::Mint {
+            mint,
+            signature,
+            slot,
+        });
+    }
+
+    /// Cancels the subscription to a wallet or mint.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The subscribed wallet or mint.
+    pub fn unwatch(&mut self, account: &Pubkey) {
+        if let Some(task) = self.tasks.remove(account) {
+            task.abort();
+        }
+    }
+
+    /// Waits for the next notification of any subscription.
+    ///
+    /// # Returns
+    ///
+    /// The next notification, or `None` if every subscription has ended.
+    pub async fn next(&mut self) -> Option<StreamEvent> {
+        self.tasks.retain(|_, task| !task.is_finished());
+        if self.tasks.is_empty() {
+            return self.receiver.try_recv().ok();
+        }
+        self.receiver.recv().await
+    }
+
+    /// Waits for a transaction to be confirmed.
+    ///
+    /// Log notifications are requested at the `processed` commitment to get them as early as
+    /// possible, so the transaction may not be visible to the other APIs yet. The returned future
+    /// does not borrow the stream, so it can be awaited in its own task.
+    ///
+    /// # Arguments
+    ///
+    /// * `signature` - The transaction signature.
+    /// * `timeout` - The maximum time to wait.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the transaction was confirmed successfully within the timeout.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the signature is invalid or the subscription fails.
+    pub fn confirm(
+        &self,
+        signature: &str,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<bool, crate::Error>> + Send + 'static {
+        let client = self.client.clone();
+        let signature = signature.to_owned();
+        async move { confirm(&client, &signature, timeout).await }
+    }
+
+    /// Spawns a task forwarding the log notifications mentioning an account.
+    ///
+    /// Failed transactions are skipped. Notifications arriving while the channel is full are
+    /// dropped with a warning, the wallet is still picked up by the next poll. The task ends if the
+    /// subscription fails or the connection is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account to subscribe to.
+    /// * `event` - Builds the event from the signature and slot of a notification.
+    fn subscribe(
+        &mut self,
+        account: Pubkey,
+        event: impl Fn(String, u64) -> StreamEvent + Send + 'static,
+    ) {
+        if self.tasks.get(&account).is_some_and(|t| !t.is_finished()) {
+            return;
+        }
+
+        let client = self.client.clone();
+        let sender = self.sender.clone();
+        let task = tokio::spawn(async move {
+            let subscription = client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![account.to_string()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(CommitmentConfig::processed()),
+                    },
+                )
+                .await;
+            let (mut notifications, unsubscribe) = match subscription {
+                Ok(subscription) => subscription,
+                Err(e) => {
+                    tracing::error!("Failed to subscribe to {}: {}", account, e);
+                    return;
+                }
+            };
+            tracing::debug!("Subscribed to logs mentioning {}", account);
+
+            while let Some(response) = notifications.next().await {
+                if response.value.err.is_some() {
+                    continue;
+                }
+                match sender.try_send(event(response.value.signature, response.context.slot)) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(event)) => {
+                        tracing::warn!("Dropped the notification {:?}, the stream is full", event);
+                    }
+                    Err(mpsc::error::TrySendError::Closed(_)) => break,
+                }
+            }
+
+            drop(notifications);
+            unsubscribe().await;
+            tracing::debug!("Subscription to {} ended", account);
+        });
+        self.tasks.insert(account, task);
+    }
+}
+
+impl Drop for SwapStream {
+    fn drop(&mut self) {
+        for task in self.tasks.values() {
+            task.abort();
+        }
+    }
+}
+
+/// Waits for a transaction to be confirmed with a `signatureSubscribe` subscription.
+///
+/// # Arguments
+///
+/// * `client` - The PubSub client.
+/// * `signature` - The transaction signature.
+/// * `timeout` - The maximum time to wait.
+///
+/// # Returns
+///
+/// `true` if the transaction was confirmed successfully within the timeout.
+///
+/// # Errors
+///
+/// This function will return an error if the signature is invalid or the subscription fails.
+async fn confirm(
+    client: &PubsubClient,
+    signature: &str,
+    timeout: Duration,
+) -> Result<bool, crate::Error> {
+    let signature = Signature::from_str(signature)
+        .map_err(|e| crate::Error::InvalidSignature(signature.to_owned(), e))?;
+    let (mut notifications, unsubscribe) = client
+        .signature_subscribe(
+            &signature,
+            Some(RpcSignatureSubscribeConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+                enable_received_notification: Some(false),
+            }),
+        )
+        .await?;
+
+    let confirmed = match tokio::time::timeout(timeout, notifications.next()).await {
+        Ok(Some(response)) => match response.value {
+            solana_client::rpc_response::RpcSignatureResult::ProcessedSignature(result) => {
+                result.err.is_none()
+            }
+            solana_client::rpc_response::RpcSignatureResult::ReceivedSignature(_) => false,
+        },
+        Ok(None) | Err(_) => false,
+    };
+    drop(notifications);
+    unsubscribe().await;
+
+    Ok(confirmed)
+}
@@ -0,0 +1,440 @@
+//! # Tokens
+//!
+//! This module resolves the symbol, name, decimals and launch of the traded tokens and groups the
+//! copied swaps of a scan by token.
+//!
+//! The name and symbol are read from the token's Metaplex metadata account, and the decimals from
+//! the mint account, both fetched with a single `getMultipleAccounts` request. Tokens without
+//! Metaplex metadata, such as Token-2022 mints carrying their own metadata extension, keep the
+//! symbol reported by Cielo.
+//!
+//! The launch of a token is its oldest transaction, found by paging the mint's signatures back
+//! from a swap. The launch platform is the launchpad program invoked by that transaction, and a
+//! token has migrated once it trades on a pool that launchpad does not own.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey, pubkey::Pubkey, signature::Signature,
+};
+use solana_transaction_status::{EncodedTransaction, UiMessage, UiTransactionEncoding};
+
+use crate::{metrics, LaunchPlatform, RepeatingWallet, TokenOverlap};
+
+/// The Metaplex Token Metadata program.
+const METADATA_PROGRAM: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// The launchpad programs and the platforms they belong to.
+const LAUNCHPADS: [(Pubkey, LaunchPlatform); 4] = [
+    (
+        pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"),
+        LaunchPlatform::PumpFun,
+    ),
+    (
+        pubkey!("LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj"),
+        LaunchPlatform::RaydiumLaunchLab,
+    ),
+    (
+        pubkey!("MoonCVVNZFSYkqNXP6bxHLPL6QQJiMagDL3qcqUQTrG"),
+        LaunchPlatform::Moonshot,
+    ),
+    (
+        pubkey!("dbcij3LWUppWqq96dh6gJWwBifmcGfLSB5D4DuSMaqN"),
+        LaunchPlatform::MeteoraDbc,
+    ),
+];
+
+/// Number of signatures per page of the mint's history.
+const SIGNATURES_PAGE_SIZE: usize = 1_000;
+
+/// Maximum number of pages of the mint's history to search for its launch. Busier tokens are
+/// reported without a launch.
+const MAX_LAUNCH_PAGES: usize = 5;
+
+/// Offset of the name in a metadata account, after the key, update authority and mint.
+const METADATA_NAME_OFFSET: usize = 1 + 32 + 32;
+
+/// Offset of the decimals in a mint account, after the mint authority and supply. The layout is
+/// shared by the SPL Token and Token-2022 programs.
+const MINT_DECIMALS_OFFSET: usize = 36 + 8;
+
+/// The resolved metadata of a token.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TokenMetadata {
+    /// The token symbol, empty if unknown.
+    pub symbol: String,
+    /// The token name, empty if unknown.
+    pub name: String,
+    /// The token decimals, if known.
+    pub decimals: Option<u8>,
+}
+
+/// The launch of a token.
+#[derive(Debug, Clone)]
+pub struct TokenLaunch {
+    /// The slot the token was created in.
+    pub created_slot: u64,
+    /// The time the token was created, as a Unix timestamp in seconds, if known.
+    pub created_at: Option<i64>,
+    /// The platform the token was launched on.
+    pub platform: LaunchPlatform,
+}
+
+impl TokenLaunch {
+    /// Returns the age of the token at a given time in seconds, if its creation time is known.
+    ///
+    /// Times before the creation, e.g. from clock skew between sources, count as age 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The time as a Unix timestamp in seconds.
+    pub fn age_at(&self, timestamp: i64) -> Option<i64> {
+        self.created_at
+            .map(|created_at| timestamp.saturating_sub(created_at).max(0))
+    }
+}
+
+impl LaunchPlatform {
+    /// Returns the launchpad program of the platform, or `None` for other platforms.
+    fn program(&self) -> Option<Pubkey> {
+        LAUNCHPADS
+            .iter()
+            .find(|(_, platform)| platform == self)
+            .map(|(program, _)| *program)
+    }
+}
+
+impl fmt::Display for LaunchPlatform {
+    /// Writes the platform as named in the JSON output.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::PumpFun => "pump_fun",
+            Self::RaydiumLaunchLab => "raydium_launch_lab",
+            Self::Moonshot => "moonshot",
+            Self::MeteoraDbc => "meteora_dbc",
+            Self::Other => "other",
+        })
+    }
+}
+
+/// Fetches the metadata of a token.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `mint` - The token mint.
+///
+/// # Returns
+///
+/// The metadata, with empty fields for missing or unreadable accounts.
+///
+/// # Errors
+///
+/// This function will return an error if the RPC request fails.
+pub(crate) async fn fetch_metadata(
+    rpc_client: &RpcClient,
+    mint: &Pubkey,
+) -> Result<TokenMetadata, crate::Error> {
+    let (metadata_address, _) = Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM,
+    );
+    let accounts = metrics::upstream(
+        "rpc",
+        rpc_client
+            .get_multiple_accounts(&[metadata_address, *mint])
+            .await,
+    )?;
+
+    let (name, symbol) = accounts
+        .first()
+        .and_then(Option::as_ref)
+        .and_then(|account| parse_metadata(&account.data))
+        .unwrap_or_default();
+    let decimals = accounts
+        .get(1)
+        .and_then(Option::as_ref)
+        .and_then(|account| account.data.get(MINT_DECIMALS_OFFSET).copied());
+    Ok(TokenMetadata {
+        symbol,
+        name,
+        decimals,
+    })
+}
+
+/// The outcome of searching a mint's history for its launch.
+#[derive(Debug, Clone)]
+pub(crate) enum LaunchLookup {
+    /// The creation was reached.
+    Found(TokenLaunch),
+    /// The history reached past the window before the creation, so the token is older.
+    PastWindow,
+    /// The pages ran out before the creation or the end of the window, so the age is unknown.
+    NotFound,
+}
+
+/// What to do after fetching a page of a mint's history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaunchPage {
+    /// The page is the last one, so its oldest signature created the mint.
+    Creation,
+    /// The page reaches past the window, so the token is older than the window.
+    PastWindow,
+    /// Fetch the next page.
+    Next,
+    /// Stop without reaching the creation or the end of the window.
+    OutOfPages,
+}
+
+/// Decides how to continue the search for a token's launch after a page of the mint's history.
+///
+/// # Arguments
+///
+/// * `full` - Whether the page holds as many signatures as requested.
+/// * `oldest_block_time` - The block time of the oldest signature of the page, if known.
+/// * `not_before` - The start of the window as a Unix timestamp, if any.
+/// * `remaining_pages` - The number of pages that may still be fetched after this one.
+pub fn launch_page_outcome(
+    full: bool,
+    oldest_block_time: Option<i64>,
+    not_before: Option<i64>,
+    remaining_pages: usize,
+) -> LaunchPage {
+    if !full {
+        LaunchPage::Creation
+    } else if not_before.is_some_and(|t| oldest_block_time.is_some_and(|time| time < t)) {
+        LaunchPage::PastWindow
+    } else if remaining_pages == 0 {
+        LaunchPage::OutOfPages
+    } else {
+        LaunchPage::Next
+    }
+}
+
+/// Finds the launch of a token by paging the mint's signatures back from a swap.
+///
+/// Paging stops at the oldest signature, which created the mint, once the signatures are older
+/// than `not_before`, or after [`MAX_LAUNCH_PAGES`] pages, see [`launch_page_outcome`].
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `mint` - The token mint.
+/// * `before` - The signature of the swap to page back from.
+/// * `not_before` - Stop once the signatures are older than this Unix timestamp, if given.
+/// * `delay_ms` - The delay in milliseconds between requests.
+///
+/// # Errors
+///
+/// This function will return an error if an RPC request fails.
+pub(crate) async fn fetch_launch(
+    rpc_client: &RpcClient,
+    mint: &Pubkey,
+    before: &Signature,
+    not_before: Option<i64>,
+    delay_ms: u64,
+) -> Result<LaunchLookup, crate::Error> {
+    let mut before = *before;
+    let mut oldest = None;
+    for page_index in 0..MAX_LAUNCH_PAGES {
+        let page = metrics::upstream(
+            "rpc",
+            rpc_client
+                .get_signatures_for_address_with_config(
+                    mint,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before: Some(before),
+                        until: None,
+                        limit: Some(SIGNATURES_PAGE_SIZE),
+                        commitment: Some(CommitmentConfig::confirmed()),
+                    },
+                )
+                .await,
+        )?;
+        metrics::rate_limit_wait(delay_ms).await;
+
+        let full = page.len() == SIGNATURES_PAGE_SIZE;
+        // An empty page means the oldest signature of the previous page created the mint
+        if let Some(last) = page.into_iter().last() {
+            oldest = Some(last);
+        }
+        let Some(last) = oldest.as_ref() else {
+            return Ok(LaunchLookup::NotFound);
+        };
+        let remaining_pages = MAX_LAUNCH_PAGES - page_index - 1;
+        match launch_page_outcome(full, last.block_time, not_before, remaining_pages) {
+            LaunchPage::Creation => break,
+            LaunchPage::PastWindow => return Ok(LaunchLookup::PastWindow),
+            LaunchPage::OutOfPages => {
+                tracing::debug!("Launch of {} not found", mint);
+                return Ok(LaunchLookup::NotFound);
+            }
+            LaunchPage::Next => {}
+        }
+        let Ok(signature) = Signature::from_str(&last.signature) else {
+            return Ok(LaunchLookup::NotFound);
+        };
+        before = signature;
+    }
+    let Some(creation) = oldest else {
+        return Ok(LaunchLookup::NotFound);
+    };
+
+    let Ok(signature) = Signature::from_str(&creation.signature) else {
+        return Ok(LaunchLookup::NotFound);
+    };
+    let tx = metrics::upstream(
+        "rpc",
+        rpc_client
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await,
+    )?;
+    metrics::rate_limit_wait(delay_ms).await;
+
+    // Invoked programs are always static account keys
+    let account_keys = match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => match &ui_tx.message {
+            UiMessage::Raw(message) => message.account_keys.as_slice(),
+            UiMessage::Parsed(_) => &[],
+        },
+        _ => &[],
+    };
+    let platform = LAUNCHPADS
+        .iter()
+        .find(|(program, _)| account_keys.contains(&program.to_string()))
+        .map_or(LaunchPlatform::Other, |(_, platform)| *platform);
+    Ok(LaunchLookup::Found(TokenLaunch {
+        created_slot: creation.slot,
+        created_at: creation.block_time,
+        platform,
+    }))
+}
+
+/// Checks whether a token had migrated off its launchpad, from the pool a swap traded against.
+///
+/// # Arguments
+///
+/// * `rpc_client` - A reference to the Solana RPC client.
+/// * `pool` - The pool or bonding curve the swap traded against.
+/// * `platform` - The platform the token was launched on.
+/// * `delay_ms` - The delay in milliseconds after the request.
+///
+/// # Returns
+///
+/// Whether the pool is not owned by the launchpad, or `None` for tokens launched elsewhere or
+/// pools that no longer exist.
+///
+/// # Errors
+///
+/// This function will return an error if the RPC request fails.
+pub(crate) async fn fetch_migrated(
+    rpc_client: &RpcClient,
+    pool: &Pubkey,
+    platform: LaunchPlatform,
+    delay_ms: u64,
+) -> Result<Option<bool>, crate::Error> {
+    let Some(program) = platform.program() else {
+        return Ok(None);
+    };
+    let accounts = metrics::upstream(
+        "rpc",
+        rpc_client
+            .get_multiple_accounts(std::slice::from_ref(pool))
+            .await,
+    )?;
+    metrics::rate_limit_wait(delay_ms).await;
+    Ok(accounts
+        .into_iter()
+        .next()
+        .flatten()
+        .map(|account| account.owner != program))
+}
+
+/// Reads the name and symbol of a Metaplex metadata account.
+///
+/// Both are stored as length-prefixed strings padded with null bytes, which are trimmed.
+///
+/// # Arguments
+///
+/// * `data` - The account data.
+///
+/// # Returns
+///
+/// The name and symbol, or `None` if the data is not a metadata account.
+pub fn parse_metadata(data: &[u8]) -> Option<(String, String)> {
+    let (name, rest) = read_string(data.get(METADATA_NAME_OFFSET..)?)?;
+    let (symbol, _) = read_string(rest)?;
+    Some((name, symbol))
+}
+
+/// Reads a string prefixed with its little-endian `u32` length and trims its padding.
+///
+/// # Arguments
+///
+/// * `data` - The data starting with the string.
+///
+/// # Returns
+///
+/// The string and the data after it, or `None` if the data is too short or not UTF-8.
+fn read_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4 + len)?;
+    let text = std::str::from_utf8(bytes).ok()?;
+    Some((
+        text.trim_end_matches('\0').trim().to_owned(),
+        &data[4 + len..],
+    ))
+}
+
+/// Groups the copied swaps of the detected wallets by token.
+///
+/// MEV wallets are left out, as they front-run rather than lead.
+///
+/// # Arguments
+///
+/// * `leaders` - The detected wallets.
+///
+/// # Returns
+///
+/// The tokens the scanned wallet and its leaders overlap on, traded by the most leaders first.
+pub fn group_by_token(leaders: &[RepeatingWallet]) -> Vec<TokenOverlap> {
+    let mut tokens: BTreeMap<&str, TokenOverlap> = BTreeMap::new();
+    for leader in leaders.iter().filter(|l| !l.is_mev()) {
+        for tx in leader.txs.iter() {
+            let token = tokens.entry(&tx.mint).or_insert_with(|| TokenOverlap {
+                mint: tx.mint.to_owned(),
+                symbol: tx.symbol.to_owned(),
+                name: tx.name.to_owned(),
+                decimals: tx.decimals,
+                launch_platform: tx.launch_platform,
+                token_created_slot: tx.token_created_slot,
+                leaders: Vec::new(),
+                copy_count: 0,
+            });
+            token.copy_count += 1;
+            if !token.leaders.contains(&leader.wallet) {
+                token.leaders.push(leader.wallet.to_owned());
+            }
+        }
+    }
+
+    let mut tokens: Vec<_> = tokens.into_values().collect();
+    tokens.sort_by(|a, b| {
+        b.leaders
+            .len()
+            .cmp(&a.leaders.len())
+            .then(b.copy_count.cmp(&a.copy_count))
+    });
+    tokens
+}
@@ -0,0 +1,747 @@
+//! # Watch Mode
+//!
+//! This module keeps polling a set of wallets and analyses each new swap as it appears.
+//!
+//! Running statistics are kept per watched wallet and leader, and an alert event is emitted the
+//! first time a leader crosses the configured copy count or score threshold. The score of a leader
+//! is the fraction of the watched wallet's analysed swaps it preceded.
+//!
+//! With a websocket URL, the watched wallets are also subscribed to through a [`SwapStream`], so a
+//! wallet is polled as soon as one of its transactions is confirmed. The mints a wallet just
+//! bought are subscribed to for a while as well. Their trades are reported as events and recorded
+//! in a [`MintHistory`], from which the previous buys of the wallet's later swaps on those mints
+//! are taken. With the `yellowstone` feature, a Yellowstone gRPC stream can be used instead, whose
+//! decoded buys of the watched wallets are analysed as they land, without polling.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+
+use tokio::task::JoinSet;
+
+use crate::{
+    metrics, stream::StreamEvent, MintHistory, Scanner, SwapAnalysis, SwapStream, WatchArgs,
+};
+
+/// Minimum number of copied swaps before the score threshold is checked, so that a single
+/// co-occurrence on the first swap does not trigger a perfect score.
+const MIN_SCORE_COPIES: usize = 2;
+
+/// How long the mint of a swap stays subscribed to after the swap, in seconds.
+const MINT_WATCH_SECS: u64 = 300;
+
+/// How long to wait for a notified transaction to be confirmed before polling anyway.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of analysed swaps remembered per watched wallet. A poll returns at most the latest 100
+/// swaps, so older swaps cannot come back.
+const MAX_SEEN_SWAPS: usize = 1_000;
+
+/// Represents an event emitted while watching wallets.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// A new swap of a watched wallet was analysed.
+    Swap {
+        /// The watched wallet address.
+        target: String,
+        /// The swap transaction hash.
+        tx_hash: String,
+        /// The number of buys found before the swap.
+        prev_buys: usize,
+    },
+    /// A leader crossed an alert threshold.
+    Alert(Alert),
+    /// A transaction landed on a mint a watched wallet just bought. It is also recorded as a
+    /// previous buy candidate of the wallet's later swaps on the mint. Only emitted when
+    /// streaming.
+    MintTrade {
+        /// The watched wallet address.
+        target: String,
+        /// The mint address.
+        mint: String,
+        /// The transaction signature.
+        signature: String,
+        /// The slot of the transaction.
+        slot: u64,
+    },
+}
+
+/// Represents an alert on a leader crossing a threshold.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Alert {
+    /// The threshold that was crossed.
+    pub kind: AlertKind,
+    /// The watched wallet address.
+    pub target: String,
+    /// The leader wallet address.
+    pub leader: String,
+    /// The number of the watched wallet's swaps the leader preceded.
+    pub copy_count: usize,
+    /// The number of the watched wallet's swaps analysed so far.
+    pub swaps_seen: usize,
+    /// The fraction of the analysed swaps the leader preceded.
+    pub score: f64,
+    /// The swap that triggered the alert.
+    pub tx_hash: String,
+    /// The time of the alert as a Unix timestamp in seconds.
+    pub timestamp: u64,
+}
+
+/// The thresholds an alert can be raised for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The copy count threshold.
+    CopyCount,
+    /// The score threshold.
+    Score,
+}
+
+/// Represents the running statistics of a leader behind a watched wallet.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LeaderStats {
+    /// The leader wallet address.
+    pub wallet: String,
+    /// The number of the watched wallet's swaps the leader preceded.
+    pub copy_count: usize,
+    /// The fraction of the watched wallet's analysed swaps the leader preceded.
+    pub score: f64,
+    /// The average block difference between the leader's buys and the watched wallet's swaps.
+    pub avg_block_diff: f64,
+    /// Whether the leader was caught sandwiching the watched wallet's swaps.
+    pub mev: bool,
+}
+
+/// Represents the running statistics of a watched wallet.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TargetStats {
+    /// The watched wallet address.
+    pub wallet: String,
+    /// The number of swaps analysed so far.
+    pub swaps_seen: usize,
+    /// The leaders found so far, sorted by copy count in descending order.
+    pub leaders: Vec<LeaderStats>,
+}
+
+/// Represents the state kept for a watched wallet between polls.
+///
+/// The wallet's swaps are recorded as they are analysed, and the statistics of their leaders
+/// updated. Only the latest [`MAX_SEEN_SWAPS`] swaps are remembered to skip swaps seen before.
+pub struct WatchedWallet {
+    /// The watched wallet.
+    wallet: Pubkey,
+    /// The latest swaps already analysed.
+    seen: HashSet<String>,
+    /// The latest swaps already analysed, oldest first.
+    seen_order: VecDeque<String>,
+    /// The number of swaps analysed so far.
+    swaps_seen: usize,
+    /// The leaders by wallet address.
+    leaders: HashMap<String, LeaderState>,
+}
+
+impl WatchedWallet {
+    /// Creates the state of a wallet without analysed swaps.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - The watched wallet.
+    pub fn new(wallet: Pubkey) -> Self {
+        Self {
+            wallet,
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            swaps_seen: 0,
+            leaders: HashMap::new(),
+        }
+    }
+
+    /// Returns whether a swap was seen before.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The swap transaction hash.
+    pub fn is_seen(&self, tx_hash: &str) -> bool {
+        self.seen.contains(tx_hash)
+    }
+
+    /// Remembers a swap so that it is skipped from now on, forgetting the oldest swap if
+    /// [`MAX_SEEN_SWAPS`] are remembered.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The swap transaction hash.
+    pub fn mark_seen(&mut self, tx_hash: &str) {
+        if !self.seen.insert(tx_hash.to_owned()) {
+            return;
+        }
+        self.seen_order.push_back(tx_hash.to_owned());
+        if self.seen_order.len() > MAX_SEEN_SWAPS {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+
+    /// Records an analysed swap and updates the statistics of its leaders.
+    ///
+    /// Swaps seen before are ignored. An alert is raised the first time a leader crosses each
+    /// threshold, except for leaders caught sandwiching the wallet's swaps.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The swap transaction hash.
+    /// * `analysis` - The analysis of the swap.
+    /// * `alert_copies` - The copy count threshold.
+    /// * `alert_score` - The score threshold, if any.
+    ///
+    /// # Returns
+    ///
+    /// The alerts raised by the swap.
+    pub fn record(
+        &mut self,
+        tx_hash: &str,
+        analysis: &SwapAnalysis,
+        alert_copies: usize,
+        alert_score: Option<f64>,
+    ) -> Vec<Alert> {
+        if self.is_seen(tx_hash) {
+            return Vec::new();
+        }
+        self.mark_seen(tx_hash);
+        self.swaps_seen += 1;
+
+        let mut copied_by = HashSet::new();
+        for (wallet, buy) in analysis.prev_buys.iter() {
+            let leader = self.leaders.entry(wallet.to_owned()).or_default();
+            leader.block_diff_sum += buy.block_diff;
+            leader.buy_count += 1;
+            if copied_by.insert(wallet.as_str()) {
+                leader.copy_count += 1;
+            }
+        }
+        for (wallet, _) in analysis.sandwiches.iter() {
+            self.leaders.entry(wallet.to_owned()).or_default().mev = true;
+        }
+
+        let mut alerts = Vec::new();
+        for wallet in copied_by {
+            let leader = self
+                .leaders
+                .get_mut(wallet)
+                .expect("leader was just inserted");
+            if leader.mev {
+                continue;
+            }
+            // A leader becomes a candidate once it preceded two of the wallet's swaps
+            if leader.copy_count == MIN_SCORE_COPIES {
+                metrics::candidates(1);
+            }
+
+            let score = leader.copy_count as f64 / self.swaps_seen as f64;
+            let mut crossed = Vec::new();
+            if leader.copy_count >= alert_copies {
+                crossed.push(AlertKind::CopyCount);
+            }
+            if alert_score.is_some_and(|min| leader.copy_count >= MIN_SCORE_COPIES && score >= min)
+            {
+                crossed.push(AlertKind::Score);
+            }
+
+            for kind in crossed {
+                if !leader.alerted.insert(kind) {
+                    continue;
+                }
+                alerts.push(Alert {
+                    kind,
+                    target: self.wallet.to_string(),
+                    leader: wallet.to_owned(),
+                    copy_count: leader.copy_count,
+                    swaps_seen: self.swaps_seen,
+                    score,
+                    tx_hash: tx_hash.to_owned(),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs()),
+                });
+            }
+        }
+        alerts
+    }
+
+    /// Summarizes the state of the wallet.
+    ///
+    /// Only leaders that preceded more than one swap are included, as in a regular scan.
+    pub fn stats(&self) -> TargetStats {
+        let mut leaders: Vec<_> = self
+            .leaders
+            .iter()
+            .filter(|(_, leader)| leader.copy_count > 1)
+            .map(|(wallet, leader)| LeaderStats {
+                wallet: wallet.to_owned(),
+                copy_count: leader.copy_count,
+                score: leader.copy_count as f64 / self.swaps_seen.max(1) as f64,
+                avg_block_diff: leader.block_diff_sum as f64 / leader.buy_count.max(1) as f64,
+                mev: leader.mev,
+            })
+            .collect();
+        leaders.sort_by(|a, b| {
+            a.mev
+                .cmp(&b.mev)
+                .then(b.copy_count.cmp(&a.copy_count))
+                .then(a.wallet.cmp(&b.wallet))
+        });
+
+        TargetStats {
+            wallet: self.wallet.to_string(),
+            swaps_seen: self.swaps_seen,
+            leaders,
+        }
+    }
+}
+
+/// The state kept for a leader behind a watched wallet.
+#[derive(Default)]
+struct LeaderState {
+    /// The number of the watched wallet's swaps the leader preceded.
+    copy_count: usize,
+    /// The sum of the block differences of the leader's buys.
+    block_diff_sum: u64,
+    /// The number of the leader's buys.
+    buy_count: u64,
+    /// Whether the leader was caught sandwiching the watched wallet's swaps.
+    mev: bool,
+    /// The thresholds already alerted on.
+    alerted: HashSet<AlertKind>,
+}
+
+/// What woke the watch loop up between two scheduled polls.
+enum Wake {
+    /// A notification of the source, or `None` if every subscription ended.
+    Event(Option<StreamEvent>),
+    /// The notified transaction of a wallet was confirmed, or the confirmation timed out.
+    Confirmed(Pubkey),
+}
+
+/// The sources of real-time notifications.
+enum Source {
+    /// Solana PubSub subscriptions over a websocket.
+    PubSub(SwapStream),
+    /// A Yellowstone gRPC transaction stream.
+    #[cfg(feature = "yellowstone")]
+    Geyser(crate::GeyserStream),
+}
+
+impl Source {
+    /// Waits for the next notification.
+    async fn next(&mut self) -> Option<StreamEvent> {
+        match self {
+            Source::PubSub(stream) => stream.next().await,
+            #[cfg(feature = "yellowstone")]
+            Source::Geyser(stream) => stream.next().await,
+        }
+    }
+
+    /// Subscribes to the transactions on a mint.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The mint to watch.
+    fn watch_mint(&mut self, mint: Pubkey) {
+        match self {
+            Source::PubSub(stream) => stream.watch_mint(mint),
+            #[cfg(feature = "yellowstone")]
+            Source::Geyser(stream) => stream.watch_mint(mint),
+        }
+    }
+
+    /// Cancels the subscription to a mint.
+    ///
+    /// # Arguments
+    ///
+    /// * `mint` - The watched mint.
+    fn unwatch(&mut self, mint: &Pubkey) {
+        match self {
+            Source::PubSub(stream) => stream.unwatch(mint),
+            #[cfg(feature = "yellowstone")]
+            Source::Geyser(stream) => stream.unwatch(mint),
+        }
+    }
+
+    /// Returns the PubSub subscriptions, which also support confirmations.
+    fn pubsub(&mut self) -> Option<&mut SwapStream> {
+        match self {
+            Source::PubSub(stream) => Some(stream),
+            #[cfg(feature = "yellowstone")]
+            Source::Geyser(_) => None,
+        }
+    }
+}
+
+/// Watches wallets until interrupted with Ctrl-C.
+///
+/// Every poll fetches the latest swaps of each watched wallet and analyses those that were not
+/// seen before, oldest first. The first poll therefore analyses up to `swap_num` recent swaps to
+/// seed the statistics. Errors while polling a wallet or analysing a swap are logged and retried
+/// on the next poll.
+///
+/// If a websocket URL is given, a wallet is also polled as soon as one of its transactions is
+/// confirmed, and the interval only acts as a fallback. Confirmations are awaited in their own
+/// tasks, so a slow confirmation does not hold up other notifications. If a metrics address is
+/// given, the metrics are served on it while watching.
+///
+/// # Arguments
+///
+/// * `args` - The watch arguments containing the scan parameters and thresholds.
+/// * `wallets` - The wallets to watch.
+/// * `on_event` - Called for every analysed swap, alert and mint trade.
+///
+/// # Returns
+///
+/// The statistics of every watched wallet at the time of the interruption.
+///
+/// # Errors
+///
+/// This function will return an error if the API clients could not be built, the metrics
+/// address cannot be bound or the websocket connection could not be established.
+pub async fn run_watch(
+    args: &WatchArgs,
+    wallets: &[Pubkey],
+    mut on_event: impl FnMut(&WatchEvent),
+) -> Result<Vec<TargetStats>, crate::Error> {
+    let mut scanner = Scanner::new(&args.scan)?;
+    let metrics_server = match args.metrics_bind {
+        Some(bind) => Some(tokio::spawn(metrics::serve_metrics(bind)?)),
+        None => None,
+    };
+    let mut targets: Vec<_> = wallets.iter().copied().map(WatchedWallet::new).collect();
+
+    let history = MintHistory::default();
+    let mut stream = match args.ws_url.as_deref() {
+        Some(ws_url) => {
+            let mut stream = SwapStream::connect(ws_url).await?;
+            for target in targets.iter() {
+                stream.watch_wallet(target.wallet);
+            }
+            Some(Source::PubSub(stream))
+        }
+        None => None,
+    };
+    #[cfg(feature = "yellowstone")]
+    if let Some(grpc_url) = args.grpc_url.as_deref() {
+        let geyser =
+            crate::GeyserStream::connect(grpc_url, args.grpc_x_token.clone(), wallets).await?;
+        stream = Some(Source::Geyser(geyser));
+    }
+    if stream.is_some() {
+        scanner = scanner.with_mint_history(history.clone());
+    }
+    // The watched mints with the wallet that bought them and when to stop watching them
+    let mut mints: HashMap<Pubkey, (Pubkey, Instant)> = HashMap::new();
+    // The notified wallets waiting for their transaction to be confirmed
+    let mut confirms = JoinSet::new();
+    let mut latest_slot = 0;
+
+    'watch: loop {
+        tokio::select! {
+            _ = poll(args, &mut scanner, &mut targets, &mut stream, &mut mints, &mut on_event) => {}
+            _ = tokio::signal::ctrl_c() => break,
+        }
+
+        // Wait for the next scheduled poll, polling wallets early on their notifications
+        let next_poll = tokio::time::Instant::now() + Duration::from_secs(args.interval_secs);
+        loop {
+            if let Some(stream) = stream.as_mut() {
+                let expired = mints.len();
+                mints.retain(|mint, (_, until)| {
+                    let keep = *until > Instant::now();
+                    if !keep {
+                        stream.unwatch(mint);
+                    }
+                    keep
+                });
+                if mints.len() < expired {
+                    history.sweep(latest_slot);
+                }
+            }
+
+            let wake = tokio::select! {
+                event = next_event(&mut stream) => Wake::Event(event),
+                Some(Ok(wallet)) = confirms.join_next() => Wake::Confirmed(wallet),
+                _ = tokio::time::sleep_until(next_poll) => break,
+                _ = tokio::signal::ctrl_c() => break 'watch,
+            };
+            let wallet = match wake {
+                Wake::Event(None) => {
+                    tracing::warn!("Every subscription ended, falling back to polling");
+                    stream = None;
+                    continue;
+                }
+                Wake::Event(Some(StreamEvent::Wallet {
+                    wallet, signature, ..
+                })) => {
+                    tracing::debug!("Notified of {} by {}", signature, wallet);
+                    match stream.as_mut().and_then(Source::pubsub) {
+                        Some(stream) => {
+                            let confirm = stream.confirm(&signature, CONFIRM_TIMEOUT);
+                            confirms.spawn(async move {
+                                if let Err(e) = confirm.await {
+                                    tracing::warn!("Failed to confirm {}: {}", signature, e);
+                                }
+                                wallet
+                            });
+                            continue;
+                        }
+                        None => wallet,
+                    }
+                }
+                Wake::Event(Some(StreamEvent::Mint {
+                    mint,
+                    signature,
+                    slot,
+                })) => {
+                    history.record(&mint.to_string(), &signature, slot);
+                    latest_slot = latest_slot.max(slot);
+                    if let Some((target, _)) = mints.get(&mint) {
+                        on_event(&WatchEvent::MintTrade {
+                            target: target.to_string(),
+                            mint: mint.to_string(),
+                            signature,
+                            slot,
+                        });
+                    }
+                    continue;
+                }
+                Wake::Event(Some(event @ StreamEvent::Swap { wallet, .. })) => {
+                    let Some(target) = targets.iter_mut().find(|t| t.wallet == wallet) else {
+                        continue;
+                    };
+                    let Some(swap) = decoded_swap(event) else {
+                        continue;
+                    };
+                    tracing::debug!("Streamed swap {} by {}", swap.tx_hash, wallet);
+                    tokio::select! {
+                        _ = analyse_new_swap(
+                            args,
+                            &mut scanner,
+                            target,
+                            &swap,
+                            &mut stream,
+                            &mut mints,
+                            &mut on_event,
+                        ) => {}
+                        _ = tokio::signal::ctrl_c() => break 'watch,
+                    }
+                    continue;
+                }
+                Wake::Confirmed(wallet) => wallet,
+            };
+
+            let Some(index) = targets.iter().position(|t| t.wallet == wallet) else {
+                continue;
+            };
+            tokio::select! {
+                _ = poll(
+                    args,
+                    &mut scanner,
+                    &mut targets[index..=index],
+                    &mut stream,
+                    &mut mints,
+                    &mut on_event,
+                ) => {}
+                _ = tokio::signal::ctrl_c() => break 'watch,
+            }
+        }
+    }
+
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
+    Ok(targets.iter().map(WatchedWallet::stats).collect())
+}
+
+/// Waits for the next notification of the source, or forever if there is no source.
+///
+/// # Arguments
+///
+/// * `stream` - The source, if streaming is enabled.
+async fn next_event(stream: &mut Option<Source>) -> Option<StreamEvent> {
+    match stream.as_mut() {
+        Some(stream) => stream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Polls watched wallets once and analyses their new swaps.
+///
+/// # Arguments
+///
+/// * `args` - The watch arguments containing the thresholds.
+/// * `scanner` - The scanner used to fetch and analyse swaps.
+/// * `targets` - The state of the wallets to poll.
+/// * `stream` - The source to subscribe to bought mints with, if streaming is enabled.
+/// * `mints` - The watched mints, updated with the mints bought in recent swaps.
+/// * `on_event` - Called for every analysed swap and alert.
+async fn poll(
+    args: &WatchArgs,
+    scanner: &mut Scanner,
+    targets: &mut [WatchedWallet],
+    stream: &mut Option<Source>,
+    mints: &mut HashMap<Pubkey, (Pubkey, Instant)>,
+    on_event: &mut impl FnMut(&WatchEvent),
+) {
+    let started = Instant::now();
+    for target in targets.iter_mut() {
+        let swaps = match scanner.fetch_swaps(&target.wallet).await {
+            Ok(swaps) => swaps,
+            Err(e) => {
+                tracing::warn!("Failed to poll {}: {}", target.wallet, e);
+                continue;
+            }
+        };
+
+        // Swaps are returned newest first
+        for swap in swaps.iter().rev() {
+            analyse_new_swap(args, scanner, target, swap, stream, mints, on_event).await;
+        }
+    }
+    metrics::scan_duration("poll", started.elapsed());
+}
+
+/// Analyses a swap of a watched wallet unless it was seen before, and updates the statistics
+/// of its leaders.
+///
+/// # Arguments
+///
+/// * `args` - The watch arguments containing the thresholds.
+/// * `scanner` - The scanner used to analyse the swap.
+/// * `target` - The state of the wallet that made the swap.
+/// * `swap` - The swap.
+/// * `stream` - The source to subscribe to the bought mint with, if streaming is enabled.
+/// * `mints` - The watched mints, updated with the mint bought in the swap.
+/// * `on_event` - Called for the analysed swap and every alert.
+async fn analyse_new_swap(
+    args: &WatchArgs,
+    scanner: &mut Scanner,
+    target: &mut WatchedWallet,
+    swap: &cielo_rs_sdk::models::feed::Swap,
+    stream: &mut Option<Source>,
+    mints: &mut HashMap<Pubkey, (Pubkey, Instant)>,
+    on_event: &mut impl FnMut(&WatchEvent),
+) {
+    if target.is_seen(&swap.tx_hash) {
+        return;
+    }
+    let analysis = match scanner.analyse_swap(swap).await {
+        Ok(analysis) => analysis,
+        Err(e) => {
+            tracing::warn!("Failed to analyse swap {}: {}", swap.tx_hash, e);
+            // Malformed swaps would fail again, so they are not retried
+            if e.is_malformed_data() {
+                target.mark_seen(&swap.tx_hash);
+            }
+            return;
+        }
+    };
+    let alerts = target.record(
+        &swap.tx_hash,
+        &analysis,
+        args.alert_copies,
+        args.alert_score,
+    );
+
+    if let Some(stream) = stream.as_mut() {
+        watch_mint(stream, mints, target.wallet, swap);
+    }
+
+    on_event(&WatchEvent::Swap {
+        target: target.wallet.to_string(),
+        tx_hash: swap.tx_hash.to_owned(),
+        prev_buys: analysis.prev_buys.len(),
+    });
+    for alert in alerts {
+        tracing::info!(
+            "Alert on {} behind {}: {:?}",
+            alert.leader,
+            alert.target,
+            alert.kind
+        );
+        on_event(&WatchEvent::Alert(alert));
+    }
+}
+
+/// Subscribes to the mint bought in a swap if the swap is recent.
+///
+/// # Arguments
+///
+/// * `stream` - The stream to subscribe with.
+/// * `mints` - The watched mints.
+/// * `target` - The watched wallet that made the swap.
+/// * `swap` - The swap.
+fn watch_mint(
+    stream: &mut Source,
+    mints: &mut HashMap<Pubkey, (Pubkey, Instant)>,
+    target: Pubkey,
+    swap: &cielo_rs_sdk::models::feed::Swap,
+) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let age = now.saturating_sub(swap.timestamp).max(0) as u64;
+    if age >= MINT_WATCH_SECS || swap.token1_address == "native" {
+        return;
+    }
+    let Ok(mint) = Pubkey::from_str(&swap.token1_address) else {
+        return;
+    };
+
+    stream.watch_mint(mint);
+    mints.insert(
+        mint,
+        (
+            target,
+            Instant::now() + Duration::from_secs(MINT_WATCH_SECS - age),
+        ),
+    );
+}
+
+/// Converts a buy decoded from a stream into a swap as returned by the feed.
+///
+/// The block time is not streamed, so the swap is timed at its receipt.
+///
+/// # Arguments
+///
+/// * `event` - The [`StreamEvent::Swap`].
+fn decoded_swap(event: StreamEvent) -> Option<cielo_rs_sdk::models::feed::Swap> {
+    let StreamEvent::Swap {
+        wallet,
+        signature,
+        slot,
+        mint,
+        lamports,
+        token_amount,
+        decimals,
+    } = event
+    else {
+        return None;
+    };
+    Some(cielo_rs_sdk::models::feed::Swap {
+        wallet: wallet.to_string(),
+        tx_hash: signature,
+        block: slot,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64),
+        token0_address: "native".to_owned(),
+        token0_amount: lamports as f64 / LAMPORTS_PER_SOL as f64,
+        token0_symbol: "SOL".to_owned(),
+        token1_address: mint.to_string(),
+        token1_amount: token_amount as f64 / 10f64.powi(decimals.into()),
+        token1_symbol: String::new(),
+    })
+}
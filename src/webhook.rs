@@ -0,0 +1,387 @@
+//! # Webhooks
+//!
+//! This module delivers completed reports and alerts to webhook targets.
+//!
+//! Three kinds of targets are supported:
+//!
+//! * Generic JSON endpoints receive the notification as a JSON POST request. If a secret is
+//!   configured, the body is signed with HMAC-SHA256 and the signature is sent in the
+//!   `X-Signature-256` header as `sha256=<hex>`.
+//! * Discord webhooks receive a short text summary as a message.
+//! * Telegram chats receive the same summary through the Bot API.
+//!
+//! Failed deliveries are retried with exponential backoff. Deliveries that still fail are
+//! appended to a dead-letter file as JSON lines, so they can be inspected or replayed later.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
+
+use crate::{Alert, BatchReport, RepeatingWallet, WebhookArgs};
+
+/// Delay before the first retry of a failed delivery, doubled on every retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Timeout of a single delivery.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of leaders listed in a text summary.
+const SUMMARY_LEADERS: usize = 10;
+
+/// Maximum length of a Discord message.
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// Maximum length of a Telegram message.
+const TELEGRAM_MAX_CHARS: usize = 4096;
+
+/// Represents a notification delivered to webhook targets.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    /// A completed scan of a single wallet.
+    Report {
+        /// The scanned wallet address.
+        wallet: String,
+        /// The detected wallets.
+        leaders: Vec<RepeatingWallet>,
+    },
+    /// A completed batch scan.
+    Batch(BatchReport),
+    /// A leader crossed an alert threshold in watch mode.
+    Alert(Alert),
+}
+
+/// A webhook target.
+#[derive(Debug, Clone)]
+enum Target {
+    /// A generic endpoint receiving JSON.
+    Json(String),
+    /// A Discord webhook.
+    Discord(String),
+    /// A Telegram chat.
+    Telegram {
+        /// The bot token.
+        token: String,
+        /// The chat id.
+        chat_id: String,
+    },
+}
+
+impl Target {
+    /// Returns a description of the target for logs, without secrets.
+    fn name(&self) -> String {
+        match self {
+            Target::Json(url) => redact_url(url),
+            Target::Discord(_) => "Discord webhook".to_owned(),
+            Target::Telegram { chat_id, .. } => format!("Telegram chat {}", chat_id),
+        }
+    }
+}
+
+/// Delivers notifications to the configured webhook targets.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    /// The HTTP client.
+    client: reqwest::Client,
+    /// The webhook targets.
+    targets: Vec<Target>,
+    /// The secret signing JSON payloads, if any.
+    secret: Option<String>,
+    /// The number of retries of a failed delivery.
+    retries: u32,
+    /// The file failed deliveries are appended to.
+    dead_letter_file: PathBuf,
+}
+
+impl Notifier {
+    /// Creates a notifier for the configured webhook targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The webhook arguments.
+    ///
+    /// # Returns
+    ///
+    /// The notifier, or `None` if no target is configured.
+    pub fn new(args: &WebhookArgs) -> Option<Self> {
+        let mut targets: Vec<_> = args.webhooks.iter().cloned().map(Target::Json).collect();
+        targets.extend(args.discord_webhooks.iter().cloned().map(Target::Discord));
+        if let (Some(token), Some(chat_id)) = (&args.telegram_bot_token, &args.telegram_chat_id) {
+            targets.push(Target::Telegram {
+                token: token.to_owned(),
+                chat_id: chat_id.to_owned(),
+            });
+        }
+        if targets.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            targets,
+            secret: args.webhook_secret.to_owned(),
+            retries: args.webhook_retries,
+            dead_letter_file: args.dead_letter_file.to_owned(),
+        })
+    }
+
+    /// Delivers a notification to every target.
+    ///
+    /// Failures are logged and written to the dead-letter file; they are never returned, so a
+    /// failing webhook does not fail the scan.
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The notification to deliver.
+    pub async fn notify(&self, notification: &Notification) {
+        let json_body = serde_json::to_vec(notification).unwrap_or_default();
+        let text = summary(notification);
+
+        for target in self.targets.iter() {
+            let request = match target {
+                Target::Json(url) => {
+                    let mut request = self
+                        .client
+                        .post(url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(json_body.clone());
+                    if let Some(secret) = self.secret.as_ref() {
+                        request = request.header("X-Signature-256", sign(secret, &json_body));
+                    }
+                    request
+                }
+                Target::Discord(url) => self.client.post(url).json(&serde_json::json!({
+                    "content": truncate(&text, DISCORD_MAX_CHARS),
+                })),
+                Target::Telegram { token, chat_id } => self
+                    .client
+                    .post(format!("https://api.telegram.org/bot{}/sendMessage", token))
+                    .json(&serde_json::json!({
+                        "chat_id": chat_id,
+                        "text": truncate(&text, TELEGRAM_MAX_CHARS),
+                        "disable_web_page_preview": true,
+                    })),
+            };
+
+            if let Err(e) = self.deliver(target, request).await {
+                tracing::error!("Failed to deliver webhook to {}: {}", target.name(), e);
+                self.dead_letter(target, &e, notification).await;
+            }
+        }
+    }
+
+    /// Sends a request, retrying on network errors, rate limits and server errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The target of the request.
+    /// * `request` - The request.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the last error if every attempt fails.
+    async fn deliver(
+        &self,
+        target: &Target,
+        request: reqwest::RequestBuilder,
+    ) -> Result<(), String> {
+        let mut delay = RETRY_BASE_DELAY;
+        let mut attempt = 0;
+        loop {
+            let Some(request) = request.try_clone() else {
+                return Err("Request cannot be retried".to_owned());
+            };
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    tracing::debug!("Delivered webhook to {}", target.name());
+                    return Ok(());
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                    {
+                        return Err(format!("HTTP status {}", status));
+                    }
+                    format!("HTTP status {}", status)
+                }
+                Err(e) => e.without_url().to_string(),
+            };
+
+            if attempt >= self.retries {
+                return Err(error);
+            }
+            attempt += 1;
+            crate::metrics::retry("webhook");
+            tracing::warn!(
+                "Webhook delivery to {} failed ({}), retry {} in {:?}",
+                target.name(),
+                error,
+                attempt,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    /// Appends a failed delivery to the dead-letter file.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The target of the delivery.
+    /// * `error` - The error of the last attempt.
+    /// * `notification` - The notification that failed to be delivered.
+    async fn dead_letter(&self, target: &Target, error: &str, notification: &Notification) {
+        // Discord webhook URLs embed the webhook token, so they are left out like bot tokens
+        let (kind, url, chat_id) = match target {
+            Target::Json(url) => ("json", Some(redact_url(url)), None),
+            Target::Discord(_) => ("discord", None, None),
+            Target::Telegram { chat_id, .. } => ("telegram", None, Some(chat_id)),
+        };
+        let line = serde_json::json!({
+            "failed_at": SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            "target": kind,
+            "url": url,
+            "chat_id": chat_id,
+            "error": error,
+            "notification": notification,
+        });
+        let mut line = serde_json::to_vec(&line).unwrap_or_default();
+        line.push(b'\n');
+
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.dead_letter_file)
+                .await?;
+            file.write_all(&line).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to write to dead-letter file {}: {}",
+                self.dead_letter_file.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Strips a URL down to its scheme and host.
+///
+/// The path, query and credentials of a webhook URL may carry a token, so only the scheme and
+/// host are logged and saved.
+///
+/// # Arguments
+///
+/// * `url` - The URL.
+///
+/// # Returns
+///
+/// The URL as `<scheme>://<host>`, or a placeholder if it cannot be parsed.
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()),
+        Err(_) => "invalid URL".to_owned(),
+    }
+}
+
+/// Signs a payload with HMAC-SHA256.
+///
+/// # Arguments
+///
+/// * `secret` - The shared secret.
+/// * `body` - The payload.
+///
+/// # Returns
+///
+/// The signature as `sha256=<hex>`.
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    let signature: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", signature)
+}
+
+/// Formats a notification as a short text message.
+///
+/// # Arguments
+///
+/// * `notification` - The notification.
+fn summary(notification: &Notification) -> String {
+    match notification {
+        Notification::Report { wallet, leaders } => {
+            let leaders: Vec<_> = leaders.iter().filter(|l| !l.is_mev()).collect();
+            let mut text = format!(
+                "Scan of {} found {} potential leaders",
+                wallet,
+                leaders.len()
+            );
+            for leader in leaders.iter().take(SUMMARY_LEADERS) {
+                text.push_str(&format!(
+                    "\n{} copied {} times",
+                    leader.wallet,
+                    leader.txs.len()
+                ));
+            }
+            text
+        }
+        Notification::Batch(report) => {
+            let failed = report.targets.iter().filter(|t| t.error.is_some()).count();
+            let mut text = format!(
+                "Batch scan of {} wallets ({} failed) found {} shared leaders",
+                report.targets.len(),
+                failed,
+                report.overlap.leaders.len()
+            );
+            for leader in report.overlap.leaders.iter().take(SUMMARY_LEADERS) {
+                text.push_str(&format!(
+                    "\n{} copied by {} wallets",
+                    leader.wallet, leader.target_count
+                ));
+            }
+            text
+        }
+        Notification::Alert(alert) => format!(
+            "Alert: {} preceded {} of {} swaps ({:.0}%) of {}\nLast swap: {}",
+            alert.leader,
+            alert.copy_count,
+            alert.swaps_seen,
+            alert.score * 100.0,
+            alert.target,
+            alert.tx_hash
+        ),
+    }
+}
+
+/// Truncates a message to a maximum number of characters.
+///
+/// # Arguments
+///
+/// * `text` - The message.
+/// * `max_chars` - The maximum number of characters.
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
@@ -14,6 +14,8 @@ fn test_basic_flow() -> Result<(), Box<dyn std::error::Error>> {
         "5",
         "--scan-tx-count",
         "30",
+        "--format",
+        "json",
     ]);
 
     let output = cmd.assert().success().get_output().clone();
@@ -0,0 +1,73 @@
+use std::{fs, str::FromStr};
+
+use solana_copy_trade_detect::{overlap, read_wallets, TargetScan};
+use solana_sdk::pubkey::Pubkey;
+
+mod common;
+
+const FIRST: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+const SECOND: &str = "7Ppgch9d4XRAygVNJP4bDkc7V6htYXGfghX4zzG9r4cH";
+
+#[test]
+fn test_read_wallets() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("batch-wallets");
+    let input = dir.join("wallets.csv");
+    fs::write(
+        &input,
+        format!(
+            "wallet,label\n\
+             # Copy traders to check\n\
+             \n\
+             \"{FIRST}\",first\n\
+             \x20\x20\n\
+             {SECOND};second\n\
+             # {SECOND}\n\
+             {FIRST} duplicate\n\
+             not-a-wallet\n"
+        ),
+    )?;
+
+    // The header row, comments, blank lines, invalid entries and duplicates are skipped
+    let wallets = read_wallets(Some(&input))?;
+    assert_eq!(
+        wallets,
+        [Pubkey::from_str(FIRST)?, Pubkey::from_str(SECOND)?]
+    );
+
+    assert!(read_wallets(Some(&dir.join("missing.txt"))).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_shared_leaders() -> Result<(), Box<dyn std::error::Error>> {
+    let targets: Vec<TargetScan> = serde_json::from_value(serde_json::json!([
+        {"wallet": "Target1", "leaders": [
+            {"wallet": "Shared", "txs": [{"tx_hash": "a", "block_diff": 0}, {"tx_hash": "b", "block_diff": 1}]},
+            {"wallet": "Only1", "txs": [{"tx_hash": "c", "block_diff": 0}, {"tx_hash": "d", "block_diff": 0}]},
+            {"wallet": "Sandwicher", "txs": [{"tx_hash": "e", "block_diff": 0}, {"tx_hash": "f", "block_diff": 0}],
+             "sandwiches": [{"target_tx_hash": "x", "front_run_tx_hash": "e", "back_run_tx_hash": "y", "slot": 0}]}
+        ]},
+        {"wallet": "Target2", "leaders": [
+            {"wallet": "Shared", "txs": [{"tx_hash": "g", "block_diff": 0}, {"tx_hash": "h", "block_diff": 0}, {"tx_hash": "i", "block_diff": 2}]},
+            {"wallet": "Sandwicher", "txs": [{"tx_hash": "j", "block_diff": 0}, {"tx_hash": "k", "block_diff": 0}]}
+        ]},
+        {"wallet": "Failed", "leaders": [], "error": "Failed to fetch fresh swaps"},
+        {"wallet": "Target3", "leaders": [
+            {"wallet": "Only1", "txs": [{"tx_hash": "l", "block_diff": 0}, {"tx_hash": "m", "block_diff": 0}]}
+        ]}
+    ]))?;
+
+    // Only leaders detected behind two scanned wallets are shared, leaving out MEV wallets and
+    // failed scans
+    let report = overlap(&targets, 2);
+    assert_eq!(report.targets, ["Target1", "Target2", "Target3"]);
+    let shared: Vec<_> = report.leaders.iter().map(|l| l.wallet.as_str()).collect();
+    assert_eq!(shared, ["Shared", "Only1"]);
+    assert_eq!(report.leaders[0].hits, [2, 3, 0]);
+    assert_eq!(report.leaders[0].copy_count, 5);
+    assert_eq!(report.leaders[1].hits, [2, 0, 2]);
+
+    // A leader detected behind a single scanned wallet is not shared
+    assert!(overlap(&targets, 3).leaders.is_empty());
+    Ok(())
+}
@@ -1,3 +1,26 @@
+#![allow(dead_code)]
+
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 pub fn setup() {
     dotenvy::from_filename(".env.test").ok();
 }
+
+/// Creates an empty directory unique to the test and the test run, so that concurrent runs do
+/// not share files.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let dir = std::env::temp_dir().join(format!(
+        "solana-copy-trade-detect-{}-{}-{}",
+        name,
+        std::process::id(),
+        nanos
+    ));
+    std::fs::create_dir_all(&dir).expect("failed to create the test directory");
+    dir
+}
@@ -0,0 +1,139 @@
+use std::fs;
+
+use assert_cmd::Command;
+use clap::{CommandFactory, FromArgMatches};
+use solana_copy_trade_detect::{Args, Command as Subcommand, Config};
+
+mod common;
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+
+const CONFIG: &str = r#"
+profile = "fast"
+
+[providers]
+cielo_api_key = "file-cielo"
+shyft_api_key = "file-shyft"
+
+[output]
+format = "json"
+
+[profiles.fast.thresholds]
+swap_num = 5
+
+[profiles.deep.thresholds]
+swap_num = 100
+cluster_funding = true
+"#;
+
+#[test]
+fn test_config_show_profiles() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("config-show");
+    let config_file = dir.join("copytrade.toml");
+    fs::write(&config_file, CONFIG)?;
+
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["config", "show", "--profile", "deep"])
+        .arg("--config")
+        .arg(&config_file)
+        .env("SHYFT_API_KEY", "env-shyft")
+        .env_remove("CIELO_API_KEY")
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let shown = String::from_utf8(output.stdout)?;
+    assert!(shown.contains("# Profile: deep"));
+    assert!(shown.contains("swap_num = 100 # profile deep"));
+    assert!(shown.contains("cluster_funding = true # profile deep"));
+    assert!(shown.contains("cielo_api_key = \"***\" # config file"));
+    assert!(shown.contains("shyft_api_key = \"***\" # env SHYFT_API_KEY"));
+    assert!(shown.contains("delay_ms = 500 # default"));
+
+    Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["config", "show", "--profile", "missing"])
+        .arg("--config")
+        .arg(&config_file)
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_config_provides_scan_options() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("config-scan");
+    fs::write(dir.join("copytrade.toml"), CONFIG)?;
+
+    // The keys come from copytrade.toml in the working directory, so the scan gets as far as
+    // opening the database, which is a directory and fails before any request is made
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .current_dir(&dir)
+        .args(["-w", WALLET])
+        .args(["--error-format", "json", "--db", "."])
+        .env_remove("CIELO_API_KEY")
+        .env_remove("SHYFT_API_KEY")
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["error"]["kind"], "store");
+
+    fs::write(dir.join("invalid.toml"), "[thresholds]\nswap_count = 5\n")?;
+    Command::cargo_bin("solana-copy-trade-detect")?
+        .current_dir(&dir)
+        .args(["config", "show", "--config", "invalid.toml"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_command_line_disables_config_flags() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("config-flags");
+    let config_file = dir.join("copytrade.toml");
+    fs::write(
+        &config_file,
+        "[thresholds]\nmev_check = true\nleader_pnl = true\n\n[output]\nquiet = true\n",
+    )?;
+    let config = Config::load(Some(&config_file), None)?;
+    let parse = |args: &[&str]| -> Result<Args, clap::Error> {
+        let matches = config
+            .apply(Args::command())
+            .try_get_matches_from(["solana-copy-trade-detect"].iter().chain(args))?;
+        Args::from_arg_matches(&matches)
+    };
+    let scan = ["-c", "key", "-s", "key", "-w", WALLET];
+
+    let args = parse(&scan)?;
+    let scan_args = args.scan.ok_or("missing scan arguments")?;
+    assert!(scan_args.mev_check && scan_args.leader_pnl && args.quiet);
+
+    let args = parse(&[&scan[..], &["--no-mev-check", "--no-quiet"]].concat())?;
+    let scan_args = args.scan.ok_or("missing scan arguments")?;
+    assert!(!scan_args.mev_check && scan_args.leader_pnl && !args.quiet);
+
+    // The last of a flag and its negation wins
+    let args = parse(
+        &[
+            &scan[..],
+            &[
+                "--no-mev-check",
+                "--mev-check",
+                "--leader-pnl",
+                "--no-leader-pnl",
+            ],
+        ]
+        .concat(),
+    )?;
+    let scan_args = args.scan.ok_or("missing scan arguments")?;
+    assert!(scan_args.mev_check && !scan_args.leader_pnl);
+
+    let args = parse(&["batch", "-c", "key", "-s", "key", "--no-mev-check"])?;
+    let Some(Subcommand::Batch(batch)) = args.command else {
+        return Err("expected the batch subcommand".into());
+    };
+    assert!(!batch.scan.mev_check && batch.scan.leader_pnl);
+    Ok(())
+}
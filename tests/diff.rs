@@ -0,0 +1,92 @@
+use std::fs;
+
+use assert_cmd::Command;
+use solana_copy_trade_detect::Store;
+
+mod common;
+
+#[test]
+fn test_diff_saved_outputs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("diff");
+
+    let old = dir.join("old.json");
+    fs::write(
+        &old,
+        r#"[
+            {"wallet": "Kept", "txs": [{"tx_hash": "a", "block_diff": 0}, {"tx_hash": "b", "block_diff": 0}]},
+            {"wallet": "Faster", "txs": [{"tx_hash": "c", "block_diff": 4}, {"tx_hash": "d", "block_diff": 6}]},
+            {"wallet": "Dropped", "txs": [{"tx_hash": "e", "block_diff": 1}, {"tx_hash": "f", "block_diff": 1}]}
+        ]"#,
+    )?;
+    let new = dir.join("new.json");
+    fs::write(
+        &new,
+        r#"[
+            {"wallet": "Kept", "txs": [{"tx_hash": "g", "block_diff": 0}, {"tx_hash": "h", "block_diff": 1}, {"tx_hash": "i", "block_diff": 0}]},
+            {"wallet": "Faster", "txs": [{"tx_hash": "j", "block_diff": 0}, {"tx_hash": "k", "block_diff": 1}]},
+            {"wallet": "Added", "txs": [{"tx_hash": "l", "block_diff": 2}, {"tx_hash": "m", "block_diff": 2}]},
+            {"wallet": "Sandwicher", "sandwiches": [{"target_tx_hash": "x", "front_run_tx_hash": "n", "back_run_tx_hash": "y", "slot": 0}], "txs": [{"tx_hash": "n", "block_diff": 0}, {"tx_hash": "o", "block_diff": 0}]}
+        ]"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .args(["--format", "json"]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(report["new_leaders"][0]["wallet"], "Added");
+    assert_eq!(report["new_leaders"].as_array().map(Vec::len), Some(1));
+    assert_eq!(report["dropped_leaders"][0]["wallet"], "Dropped");
+    assert_eq!(report["changed_leaders"][0]["wallet"], "Faster");
+    assert_eq!(report["changed_leaders"][0]["old_avg_lag"], 5.0);
+    assert_eq!(report["changed_leaders"][0]["new_avg_lag"], 0.5);
+    assert_eq!(report["unchanged_count"], 1);
+    Ok(())
+}
+
+#[test]
+fn test_diff_latest_stored_scans() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("diff-store");
+    let db = dir.join("scans.db");
+    drop(Store::open(&db)?);
+
+    let conn = rusqlite::Connection::open(&db)?;
+    conn.execute_batch(
+        "INSERT INTO scans (id, wallet, started_at, finished_at, swap_num, scan_tx_count,
+             mev_check, cluster_funding, params, error)
+         VALUES (1, 'Target', 100, 110, 15, 50, 0, 0, '{}', NULL),
+                (2, 'Target', 200, 210, 15, 50, 0, 0, '{}', 'Failed to fetch fresh swaps'),
+                (3, 'Target', 300, 310, 15, 50, 0, 0, '{}', NULL);
+         INSERT INTO candidates (scan_id, wallet, copy_count, avg_block_diff, mev, cluster)
+         VALUES (1, 'Old', 2, 0.0, 0, NULL), (3, 'New', 2, 1.0, 0, NULL),
+                (3, 'Sandwicher', 2, 0.0, 1, NULL);
+         INSERT INTO prev_buys (scan_id, swap_tx_hash, wallet, tx_hash, slot, block_diff)
+         VALUES (1, 's1', 'Old', 'a', 10, 0), (1, 's2', 'Old', 'b', 20, 0),
+                (3, 's3', 'New', 'c', 30, 1), (3, 's4', 'New', 'd', 40, 1),
+                (3, 's3', 'Sandwicher', 'e', 30, 0), (3, 's4', 'Sandwicher', 'f', 40, 0);
+         INSERT INTO sandwiches (scan_id, wallet, target_tx_hash, front_run_tx_hash,
+             back_run_tx_hash, slot)
+         VALUES (3, 'Sandwicher', 's3', 'e', 'g', 30);",
+    )?;
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.args(["diff", "-w", "Target", "--format", "json", "--db"])
+        .arg(&db);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(report["wallet"], "Target");
+    assert_eq!(report["old"], "scan 1");
+    assert_eq!(report["new"], "scan 3");
+    assert_eq!(report["new_leaders"][0]["wallet"], "New");
+    assert_eq!(report["new_leaders"][0]["new_copy_count"], 2);
+    // The recorded sandwiches flag the wallet as MEV, which leaves it out
+    assert_eq!(report["new_leaders"].as_array().map(Vec::len), Some(1));
+    assert_eq!(report["dropped_leaders"][0]["wallet"], "Old");
+    Ok(())
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "yellowstone")]
+
+use std::{collections::HashSet, str::FromStr};
+
+use solana_copy_trade_detect::{decode_buy, subscribe_request, StreamEvent};
+use solana_sdk::pubkey::Pubkey;
+use yellowstone_grpc_proto::prelude::{TokenBalance, TransactionStatusMeta, UiTokenAmount};
+
+const WALLET: &str = "7Ppgch9d4XRAygVNJP4bDkc7V6htYXGfghX4zzG9r4cH";
+const MINT: &str = "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm";
+const POOL: &str = "5yuefgbJJpmFNK2iiYbLSpv1aZXq7F9AUKkZKErTYCvs";
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Builds the token balance of an account.
+fn token_balance(owner: &str, mint: &str, amount: u64, decimals: u32) -> TokenBalance {
+    TokenBalance {
+        mint: mint.to_owned(),
+        owner: owner.to_owned(),
+        ui_token_amount: Some(UiTokenAmount {
+            amount: amount.to_string(),
+            decimals,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_subscribe_request_filters() -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = Pubkey::from_str(WALLET)?;
+
+    // Without mints, only the wallets are subscribed to
+    let request = subscribe_request(&[wallet], &HashSet::new());
+    assert_eq!(request.transactions.len(), 1);
+    assert_eq!(
+        request.transactions["wallets"].account_include,
+        vec![WALLET.to_owned()]
+    );
+
+    let mint = Pubkey::from_str(MINT)?;
+    let request = subscribe_request(&[wallet], &HashSet::from([mint]));
+    assert_eq!(request.transactions.len(), 2);
+    assert_eq!(
+        request.transactions["mints"].account_include,
+        vec![MINT.to_owned()]
+    );
+    assert!(request
+        .transactions
+        .values()
+        .all(|filter| filter.vote == Some(false) && filter.failed == Some(false)));
+    Ok(())
+}
+
+#[test]
+fn test_decode_buy() -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = Pubkey::from_str(WALLET)?;
+    let keys = vec![
+        wallet.to_bytes().to_vec(),
+        Pubkey::from_str(POOL)?.to_bytes().to_vec(),
+    ];
+    let meta = TransactionStatusMeta {
+        fee: 5_000,
+        pre_balances: vec![2_000_005_000, 0],
+        post_balances: vec![1_500_000_000, 0],
+        pre_token_balances: vec![
+            token_balance(WALLET, WSOL_MINT, 250_000_000, 9),
+            token_balance(POOL, MINT, 9_000_000_000, 6),
+        ],
+        post_token_balances: vec![
+            token_balance(WALLET, WSOL_MINT, 0, 9),
+            token_balance(WALLET, MINT, 1_000_000_000, 6),
+            token_balance(POOL, MINT, 8_000_000_000, 6),
+        ],
+        ..Default::default()
+    };
+
+    // The fee is left out and the wrapped SOL is counted
+    let event = decode_buy(&wallet, "sig", 7, &keys, &meta);
+    assert_eq!(
+        event,
+        Some(StreamEvent::Swap {
+            wallet,
+            signature: "sig".to_owned(),
+            slot: 7,
+            mint: Pubkey::from_str(MINT)?,
+            lamports: 750_000_000,
+            token_amount: 1_000_000_000,
+            decimals: 6,
+        })
+    );
+
+    // A sell spends no SOL
+    let sell = TransactionStatusMeta {
+        pre_balances: vec![1_500_000_000, 0],
+        post_balances: vec![2_000_000_000, 0],
+        pre_token_balances: meta.post_token_balances.clone(),
+        post_token_balances: vec![token_balance(WALLET, MINT, 0, 6)],
+        ..Default::default()
+    };
+    assert_eq!(decode_buy(&wallet, "sig", 7, &keys, &sell), None);
+
+    // A wallet missing from the transaction made no buy
+    let other = Pubkey::from_str(POOL)?;
+    assert_eq!(decode_buy(&other, "sig", 7, &keys[..1], &meta), None);
+    Ok(())
+}
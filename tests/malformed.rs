@@ -0,0 +1,93 @@
+use cielo_rs_sdk::models::feed::Swap;
+use clap::Parser;
+use shyft_rs_sdk::models::parsed_transaction_details::ParsedTransactionDetails;
+use solana_copy_trade_detect::{parsed_tx_slot, Args, Scanner};
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+
+const TX_HASH: &str =
+    "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+fn scanner() -> Scanner {
+    // The RPC URL is unreachable, so any request made before the validation fails the test
+    let args = Args::try_parse_from([
+        "solana-copy-trade-detect",
+        "-w",
+        WALLET,
+        "-c",
+        "invalid",
+        "-s",
+        "invalid",
+        "-r",
+        "http://127.0.0.1:9",
+    ])
+    .unwrap();
+    Scanner::new(&args.scan.unwrap()).unwrap()
+}
+
+fn swap(tx_hash: &str, token1_address: &str) -> Swap {
+    Swap {
+        wallet: WALLET.to_owned(),
+        tx_hash: tx_hash.to_owned(),
+        block: 300_000_000,
+        timestamp: 1_714_521_600,
+        token0_address: "native".to_owned(),
+        token0_amount: 1.0,
+        token0_symbol: "SOL".to_owned(),
+        token1_address: token1_address.to_owned(),
+        token1_amount: 1_000.0,
+        token1_symbol: "TOKEN".to_owned(),
+    }
+}
+
+fn parsed_tx(raw: serde_json::Value) -> ParsedTransactionDetails {
+    serde_json::from_value(serde_json::json!({
+        "timestamp": "2024-05-01T00:00:00.000Z",
+        "fee": 0.000005,
+        "fee_payer": WALLET,
+        "signers": [WALLET],
+        "signatures": [TX_HASH],
+        "protocol": { "address": "11111111111111111111111111111111", "name": "SYSTEM_PROGRAM" },
+        "type": "SWAP",
+        "status": "Success",
+        "actions": [],
+        "raw": raw,
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_malformed_swap_signature() {
+    let error = scanner()
+        .analyse_swap(&swap("not-a-signature", "native"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "invalid_signature");
+    assert!(error.is_malformed_data());
+}
+
+#[tokio::test]
+async fn test_malformed_swap_mint() {
+    let error = scanner()
+        .analyse_swap(&swap(TX_HASH, "not-a-mint"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "invalid_mint");
+    assert!(error.is_malformed_data());
+}
+
+#[test]
+fn test_parsed_tx_without_slot() {
+    let error = parsed_tx_slot(&parsed_tx(serde_json::Value::Null)).unwrap_err();
+    assert_eq!(error.kind(), "missing_slot");
+    assert!(error.to_string().contains(TX_HASH));
+
+    let error = parsed_tx_slot(&parsed_tx(serde_json::json!({ "blockTime": 1714521600 })));
+    assert_eq!(error.unwrap_err().kind(), "missing_slot");
+
+    let error = parsed_tx_slot(&parsed_tx(serde_json::json!({ "slot": "312345678" })));
+    assert_eq!(error.unwrap_err().kind(), "missing_slot");
+
+    let slot = parsed_tx_slot(&parsed_tx(serde_json::json!({ "slot": 312345678 })));
+    assert_eq!(slot.unwrap(), 312345678);
+}
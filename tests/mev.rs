@@ -0,0 +1,188 @@
+use shyft_rs_sdk::models::parsed_transaction_details::ParsedTransactionDetails;
+use solana_copy_trade_detect::{
+    is_sell, pool_addresses, signatures_address, swap_pool, touches_any,
+};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedTransactionWithStatusMeta;
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+const MINT: &str = "9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump";
+const POOL: &str = "5yuefgbJJpmFNK2iiYbLSpv1aZXq7F9AUKkZKErTYCvs";
+const LOOKUP_POOL: &str = "8sLbNZoA1cfnvMJLPfp98ZLAnFSYCFApfJKMbiXNLwxj";
+
+/// Returns the token balance of the wallet.
+fn balance(amount: &str) -> serde_json::Value {
+    serde_json::json!({
+        "accountIndex": 1,
+        "mint": MINT,
+        "owner": WALLET,
+        "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        "uiTokenAmount": {
+            "amount": amount,
+            "decimals": 6,
+            "uiAmount": null,
+            "uiAmountString": "0",
+        },
+    })
+}
+
+/// Builds a block transaction changing the wallet's balance of the token.
+fn block_tx(pre: &str, post: &str, err: serde_json::Value) -> EncodedTransactionWithStatusMeta {
+    serde_json::from_value(serde_json::json!({
+        "transaction": {
+            "signatures": ["sig"],
+            "message": {
+                "header": {
+                    "numRequiredSignatures": 1,
+                    "numReadonlySignedAccounts": 0,
+                    "numReadonlyUnsignedAccounts": 0,
+                },
+                "accountKeys": [WALLET, POOL],
+                "recentBlockhash": "11111111111111111111111111111111",
+                "instructions": [],
+            },
+        },
+        "meta": {
+            "err": err,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+            "preTokenBalances": [balance(pre)],
+            "postTokenBalances": [balance(post)],
+            "loadedAddresses": { "writable": [LOOKUP_POOL], "readonly": [] },
+        },
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_is_sell() {
+    assert!(is_sell(
+        &block_tx("1000", "0", serde_json::Value::Null),
+        WALLET,
+        MINT
+    ));
+    // Buys, other tokens, other wallets and failed transactions are no sells
+    assert!(!is_sell(
+        &block_tx("0", "1000", serde_json::Value::Null),
+        WALLET,
+        MINT
+    ));
+    assert!(!is_sell(
+        &block_tx("1000", "0", serde_json::Value::Null),
+        WALLET,
+        POOL
+    ));
+    assert!(!is_sell(
+        &block_tx("1000", "0", serde_json::Value::Null),
+        POOL,
+        MINT
+    ));
+    assert!(!is_sell(
+        &block_tx(
+            "1000",
+            "0",
+            serde_json::json!({ "InstructionError": [0, { "Custom": 1 }] })
+        ),
+        WALLET,
+        MINT
+    ));
+}
+
+#[test]
+fn test_touches_any() {
+    let tx = block_tx("1000", "0", serde_json::Value::Null);
+    assert!(touches_any(&tx, &[POOL.to_owned()]));
+    // Accounts loaded from lookup tables count as well
+    assert!(touches_any(&tx, &[LOOKUP_POOL.to_owned()]));
+    assert!(!touches_any(&tx, &[MINT.to_owned()]));
+    // An unknown pool matches any transaction
+    assert!(touches_any(&tx, &[]));
+}
+
+/// Builds a parsed transaction of the wallet with the given actions.
+fn parsed_tx(actions: serde_json::Value) -> ParsedTransactionDetails {
+    serde_json::from_value(serde_json::json!({
+        "timestamp": "2024-05-01T00:00:00.000Z",
+        "fee": 0.000005,
+        "fee_payer": WALLET,
+        "signers": [WALLET],
+        "signatures": ["sig"],
+        "protocol": { "address": "11111111111111111111111111111111", "name": "SYSTEM_PROGRAM" },
+        "type": "SWAP",
+        "status": "Success",
+        "actions": actions,
+        "raw": null,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_pool_addresses() {
+    let tx = parsed_tx(serde_json::json!([
+        {
+            "info": { "swaps": [
+                { "liquidity_pool_address": POOL },
+                { "liquidity_pool_address": LOOKUP_POOL },
+            ] },
+            "source_protocol": { "address": "", "name": "" },
+            "type": "SWAP",
+        },
+        {
+            "info": { "swaps": [{ "liquidity_pool_address": MINT }] },
+            "source_protocol": { "address": "", "name": "" },
+            "type": "TOKEN_TRANSFER",
+        },
+    ]));
+
+    assert_eq!(pool_addresses(&tx), [POOL, LOOKUP_POOL]);
+    // A routed swap is paged on the last pool, which paid out the bought token
+    assert_eq!(swap_pool(&tx), Some(LOOKUP_POOL.parse().unwrap()));
+}
+
+#[test]
+fn test_signatures_address() {
+    let mint: Pubkey = MINT.parse().unwrap();
+    let swap = |pool: &str| {
+        parsed_tx(serde_json::json!([{
+            "info": { "swaps": [{ "liquidity_pool_address": pool }] },
+            "source_protocol": { "address": "", "name": "" },
+            "type": "SWAP",
+        }]))
+    };
+
+    assert_eq!(
+        signatures_address(Some(&swap(POOL)), &mint),
+        POOL.parse().unwrap()
+    );
+    // The mint is paged if the swap is unknown, names no pool or a malformed one
+    assert_eq!(signatures_address(None, &mint), mint);
+    assert_eq!(
+        signatures_address(Some(&parsed_tx(serde_json::json!([]))), &mint),
+        mint
+    );
+    assert_eq!(signatures_address(Some(&swap("invalid")), &mint), mint);
+}
+
+#[test]
+fn test_signatures_address_of_sell() {
+    // Sells are paged on their pool as well, not only the buys with SOL
+    let sell = parsed_tx(serde_json::json!([{
+        "info": {
+            "swapper": WALLET,
+            "tokens_swapped": {
+                "in": { "token_address": MINT, "symbol": "TOKEN", "amount": 1000.0 },
+                "out": { "token_address": "So11111111111111111111111111111111111111112", "symbol": "SOL", "amount": 1.0 },
+            },
+            "swaps": [{ "liquidity_pool_address": POOL }],
+        },
+        "source_protocol": { "address": "", "name": "" },
+        "type": "SWAP",
+    }]));
+
+    assert_eq!(
+        signatures_address(Some(&sell), &MINT.parse().unwrap()),
+        POOL.parse().unwrap()
+    );
+}
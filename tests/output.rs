@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use assert_cmd::Command;
+use solana_copy_trade_detect::{
+    json_schema, write_found, write_report, OutputFormat, RepeatingWallet, SCHEMA_VERSION,
+};
+
+mod common;
+
+/// Returns two detected wallets, the second one with two copied swaps and a cluster.
+fn leaders() -> Vec<RepeatingWallet> {
+    serde_json::from_value(serde_json::json!([
+        {"wallet": "Leader1", "txs": [{"tx_hash": "a", "slot": 10, "block_diff": 0}]},
+        {"wallet": "Leader2", "cluster": 1, "txs": [
+            {"tx_hash": "b", "slot": 20, "block_diff": 1},
+            {"tx_hash": "c", "slot": 30, "block_diff": 2}
+        ]}
+    ]))
+    .unwrap()
+}
+
+/// Reads the rows of a CSV output by column name. The fields must not be quoted.
+fn csv_rows(csv: &str) -> Vec<HashMap<&str, &str>> {
+    let mut lines = csv.lines();
+    let header: Vec<_> = lines.next().unwrap_or_default().split(',').collect();
+    lines
+        .map(|line| header.iter().copied().zip(line.split(',')).collect())
+        .collect()
+}
+
+#[test]
+fn test_output_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut json = Vec::new();
+    write_report(&mut json, OutputFormat::Json, "Target", &leaders())?;
+    let report: serde_json::Value = serde_json::from_slice(&json)?;
+    assert_eq!(report["schema_version"], SCHEMA_VERSION);
+    assert_eq!(report["wallet"], "Target");
+    assert_eq!(report["leaders"][1]["txs"][1]["slot"], 30);
+    assert_eq!(
+        report["tokens"][0]["leaders"].as_array().map(Vec::len),
+        Some(2)
+    );
+
+    let mut jsonl = Vec::new();
+    write_report(&mut jsonl, OutputFormat::Jsonl, "Target", &leaders())?;
+    let lines: Vec<serde_json::Value> = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["target"], "Target");
+    assert_eq!(lines[0]["event"], "leader");
+    assert_eq!(lines[0]["wallet"], "Leader1");
+    assert_eq!(lines[1]["cluster"], 1);
+
+    let mut csv = Vec::new();
+    write_report(&mut csv, OutputFormat::Csv, "Target", &leaders())?;
+    let csv = String::from_utf8(csv)?;
+    let rows = csv_rows(&csv);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["schema_version"], SCHEMA_VERSION.to_string());
+    assert_eq!(rows[0]["target"], "Target");
+    assert_eq!(rows[0]["wallet"], "Leader1");
+    assert_eq!(rows[0]["cluster"], "");
+    assert_eq!(rows[0]["tx_hash"], "a");
+    assert_eq!(rows[2]["wallet"], "Leader2");
+    assert_eq!(rows[2]["cluster"], "1");
+    assert_eq!(rows[2]["copy_count"], "2");
+    assert_eq!(rows[2]["slot"], "30");
+    assert_eq!(rows[2]["block_diff"], "2");
+    assert_eq!(rows[2]["sol_amount"], "0");
+    // The analyses of the wallets are left to the JSON formats
+    assert!(!rows[0].contains_key("realised_pnl_sol"));
+    assert!(!rows[0].contains_key("captured_return"));
+    assert!(!rows[0].contains_key("size_pattern"));
+    // So is the metadata of the tokens
+    assert!(!rows[0].contains_key("symbol"));
+    assert!(!rows[0].contains_key("launch_platform"));
+    Ok(())
+}
+
+#[test]
+fn test_outputs_match_schemas() -> Result<(), Box<dyn std::error::Error>> {
+    let mut leaders = leaders();
+    leaders[1].txs[0].launch_platform = Some(solana_copy_trade_detect::LaunchPlatform::PumpFun);
+    leaders[1].txs[0].token_created_slot = Some(5);
+
+    let mut json = Vec::new();
+    write_report(&mut json, OutputFormat::Json, "Target", &leaders)?;
+    let schema = json_schema(OutputFormat::Json).unwrap();
+    let validator = jsonschema::validator_for(&schema)?;
+    let report: serde_json::Value = serde_json::from_slice(&json)?;
+    assert!(validator.is_valid(&report));
+
+    // Streamed buys come before the detected wallets
+    let mut jsonl = Vec::new();
+    write_found(&mut jsonl, "Target", "Leader2", &leaders[1].txs[0])?;
+    write_report(&mut jsonl, OutputFormat::Jsonl, "Target", &leaders)?;
+    let schema = json_schema(OutputFormat::Jsonl).unwrap();
+    let validator = jsonschema::validator_for(&schema)?;
+    let lines: Vec<serde_json::Value> = String::from_utf8(jsonl)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["event"], "buy");
+    assert_eq!(lines[0]["wallet"], "Leader2");
+    assert_eq!(lines[0]["tx_hash"], "b");
+    assert!(lines.iter().all(|line| validator.is_valid(line)));
+
+    // A line missing its target does not match
+    let mut line = lines[0].clone();
+    line.as_object_mut().unwrap().remove("target");
+    assert!(!validator.is_valid(&line));
+
+    let mut csv = Vec::new();
+    write_report(&mut csv, OutputFormat::Csv, "Target", &leaders)?;
+    let schema = json_schema(OutputFormat::Csv).unwrap();
+    let validator = jsonschema::validator_for(&schema)?;
+    let csv = String::from_utf8(csv)?;
+    for row in csv_rows(&csv) {
+        assert!(validator.is_valid(&serde_json::to_value(&row)?));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_html_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mut html = Vec::new();
+    write_report(&mut html, OutputFormat::Html, "Target", &leaders())?;
+    let html = String::from_utf8(html)?;
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<th data-type=\"number\">Copied swaps</th>"));
+    assert!(html.contains("href=\"https://solscan.io/tx/b\""));
+    assert!(html.contains("<summary class=\"mono\">Leader2</summary>"));
+    // No external resources, so the report works offline
+    assert!(!html.contains("src="));
+    assert!(!html.contains("<link"));
+    Ok(())
+}
+
+#[test]
+fn test_schema_command() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["schema", "jsonl"])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        schema["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    assert_eq!(
+        schema["oneOf"][1]["allOf"][1]["properties"]["schema_version"]["const"],
+        SCHEMA_VERSION
+    );
+    assert!(schema["$defs"]["prev_buy"]["properties"]["block_diff"].is_object());
+
+    // The formats meant for humans have no schema
+    for format in ["text", "html"] {
+        Command::cargo_bin("solana-copy-trade-detect")?
+            .args(["schema", format])
+            .assert()
+            .failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_json_errors() -> Result<(), Box<dyn std::error::Error>> {
+    // The database is a directory, so the scan fails before any request is made
+    let dir = common::temp_dir("output-errors");
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["-w", "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi"])
+        .args(["-c", "invalid", "-s", "invalid", "--quiet"])
+        .args(["--format", "json", "--error-format", "json", "--db"])
+        .arg(&dir)
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["error"]["kind"], "store");
+    assert!(error["error"]["message"].is_string());
+
+    // Invalid arguments are rejected before the scan starts
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["-w", "invalid", "-c", "invalid", "-s", "invalid"])
+        .args(["--format", "json", "--error-format", "json"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)?.contains("invalid value 'invalid'"));
+    Ok(())
+}
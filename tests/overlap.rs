@@ -0,0 +1,76 @@
+use std::fs;
+
+use assert_cmd::Command;
+
+mod common;
+
+#[test]
+fn test_overlap_from_saved_outputs() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("overlap");
+
+    let first = dir.join("Target1.json");
+    fs::write(
+        &first,
+        r#"[
+            {"wallet": "Leader1", "txs": [{"tx_hash": "a", "block_diff": 0}, {"tx_hash": "b", "block_diff": 1}]},
+            {"wallet": "Leader2", "txs": [{"tx_hash": "c", "block_diff": 0}, {"tx_hash": "d", "block_diff": 2}]}
+        ]"#,
+    )?;
+    let second = dir.join("Target2.json");
+    fs::write(
+        &second,
+        r#"[
+            {"wallet": "Leader1", "txs": [{"tx_hash": "e", "block_diff": 0}, {"tx_hash": "f", "block_diff": 0}, {"tx_hash": "g", "block_diff": 1}]}
+        ]"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.arg("overlap")
+        .arg(&first)
+        .arg(&second)
+        .args(["--format", "json"]);
+
+    let output = cmd.assert().success().get_output().clone();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(report["targets"], serde_json::json!(["Target1", "Target2"]));
+    let leaders = report["leaders"]
+        .as_array()
+        .expect("leaders should be an array");
+    assert_eq!(leaders.len(), 1);
+    assert_eq!(leaders[0]["wallet"], "Leader1");
+    assert_eq!(leaders[0]["hits"], serde_json::json!([2, 3]));
+    assert_eq!(leaders[0]["target_count"], 2);
+    Ok(())
+}
+
+#[test]
+fn test_overlap_error_format() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("overlap-error");
+    let missing = dir.join("Missing.json");
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.arg("overlap")
+        .arg(&missing)
+        .arg(&missing)
+        .args(["--error-format", "json"]);
+
+    let output = cmd.assert().failure().get_output().clone();
+    assert!(output.stdout.is_empty());
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["error"]["kind"], "io");
+
+    // Only the text and JSON formats can be written
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.arg("overlap").arg(&missing).arg(&missing).args([
+        "--format",
+        "csv",
+        "--error-format",
+        "json",
+    ]);
+
+    let output = cmd.assert().failure().get_output().clone();
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["error"]["kind"], "unsupported_format");
+    Ok(())
+}
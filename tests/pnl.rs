@@ -0,0 +1,92 @@
+use solana_copy_trade_detect::{compare_outcomes, leader_pnl, Trade, TradeSide};
+
+fn trade(mint: &str, side: TradeSide, sol_amount: f64, token_amount: f64, timestamp: i64) -> Trade {
+    Trade {
+        tx_hash: format!("{}-{}", mint, timestamp),
+        mint: mint.to_owned(),
+        side,
+        sol_amount,
+        token_amount,
+        timestamp: Some(timestamp),
+    }
+}
+
+#[test]
+fn test_leader_pnl() {
+    let pnl = leader_pnl(&[
+        // Sold half at 3x, then the rest at 2x
+        trade("win", TradeSide::Sell, 1.0, 500.0, 1_300),
+        trade("win", TradeSide::Buy, 1.0, 1_000.0, 1_000),
+        trade("win", TradeSide::Sell, 1.5, 500.0, 1_100),
+        // Sold everything at half the price
+        trade("loss", TradeSide::Buy, 2.0, 100.0, 2_000),
+        trade("loss", TradeSide::Sell, 1.0, 100.0, 2_100),
+        // Still held
+        trade("open", TradeSide::Buy, 0.5, 10.0, 3_000),
+    ]);
+
+    assert_eq!(pnl.tokens.len(), 3);
+    assert!((pnl.realised_pnl_sol - 0.5).abs() < 1e-9);
+    assert_eq!(pnl.win_rate, Some(0.5));
+    assert_eq!(pnl.avg_hold_secs, Some(200.0));
+
+    let win = pnl.tokens.iter().find(|t| t.mint == "win").unwrap();
+    assert_eq!((win.buy_count, win.sell_count), (1, 2));
+    assert!((win.realised_pnl_sol - 1.5).abs() < 1e-9);
+    assert_eq!(win.roi, Some(1.5));
+    assert_eq!(win.tokens_held, 0.0);
+    assert_eq!(win.hold_secs, Some(300));
+
+    let loss = pnl.tokens.iter().find(|t| t.mint == "loss").unwrap();
+    assert_eq!(loss.roi, Some(-0.5));
+
+    let open = pnl.tokens.iter().find(|t| t.mint == "open").unwrap();
+    assert_eq!((open.realised_pnl_sol, open.roi), (0.0, None));
+    assert_eq!((open.tokens_held, open.hold_secs), (10.0, None));
+}
+
+#[test]
+fn test_leader_pnl_ignores_tokens_without_cost() {
+    // Tokens received by transfer and sold have no known cost
+    let pnl = leader_pnl(&[
+        trade("airdrop", TradeSide::Buy, 1.0, 100.0, 1_000),
+        trade("airdrop", TradeSide::Sell, 4.0, 200.0, 1_100),
+    ]);
+    assert!((pnl.realised_pnl_sol - 1.0).abs() < 1e-9);
+    assert_eq!(pnl.tokens[0].sol_received, 4.0);
+    assert_eq!(pnl.tokens[0].tokens_held, 0.0);
+}
+
+#[test]
+fn test_compare_outcomes() {
+    let leader = [
+        trade("shared", TradeSide::Buy, 1.0, 1_000.0, 1_000),
+        trade("shared", TradeSide::Sell, 3.0, 1_000.0, 1_600),
+        trade("other", TradeSide::Buy, 1.0, 10.0, 2_000),
+        trade("other", TradeSide::Sell, 2.0, 10.0, 2_100),
+    ];
+    // The target bought later at a higher price and sold after the leader
+    let target = [
+        trade("shared", TradeSide::Buy, 2.0, 1_000.0, 1_010),
+        trade("shared", TradeSide::Sell, 4.0, 1_000.0, 1_700),
+    ];
+
+    let outcomes = compare_outcomes(&target, &leader);
+    assert_eq!(outcomes.tokens.len(), 1);
+    let shared = &outcomes.tokens[0];
+    assert_eq!(shared.mint, "shared");
+    assert_eq!(shared.target.entry_price, Some(0.002));
+    assert_eq!(shared.leader.exit_price, Some(0.003));
+    assert_eq!(
+        (shared.target.hold_secs, shared.leader.hold_secs),
+        (Some(690), Some(600))
+    );
+    assert_eq!(outcomes.target_avg_roi, Some(1.0));
+    assert_eq!(outcomes.leader_avg_roi, Some(2.0));
+    assert_eq!(outcomes.captured_return, Some(0.5));
+
+    // A leader that has not sold has no return to capture
+    let outcomes = compare_outcomes(&leader, &target[..1]);
+    assert_eq!(outcomes.leader_avg_roi, None);
+    assert_eq!(outcomes.captured_return, None);
+}
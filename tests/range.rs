@@ -0,0 +1,77 @@
+use assert_cmd::Command;
+use solana_copy_trade_detect::{signature_paging_stop, PagingStop, RangeBound};
+
+#[test]
+fn test_range_bounds() {
+    assert_eq!("slot:312345678".parse(), Ok(RangeBound::Slot(312345678)));
+    assert_eq!("1714521600".parse(), Ok(RangeBound::Timestamp(1714521600)));
+    assert_eq!("2024-05-01".parse(), Ok(RangeBound::Timestamp(1714521600)));
+    assert_eq!(
+        "2024-05-01T12:30:15Z".parse(),
+        Ok(RangeBound::Timestamp(1714566615))
+    );
+    assert_eq!("1970-01-01".parse(), Ok(RangeBound::Timestamp(0)));
+    assert!("2024-13-01".parse::<RangeBound>().is_err());
+    assert!("slot:latest".parse::<RangeBound>().is_err());
+    assert!("yesterday".parse::<RangeBound>().is_err());
+}
+
+#[test]
+fn test_range_scan_pages_wallet_history() -> Result<(), Box<dyn std::error::Error>> {
+    // The swaps of a range come from the wallet's signatures on the RPC, not from the Cielo feed
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["-w", "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi"])
+        .args(["-c", "invalid", "-s", "invalid", "-r", "http://127.0.0.1:9"])
+        .args(["--since", "2024-05-01", "--until", "slot:312345678"])
+        .args(["--quiet", "--error-format", "json"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(error["error"]["kind"], "rpc_client");
+    Ok(())
+}
+
+#[test]
+fn test_lookback_window_is_slots_or_secs() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["-w", "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi"])
+        .args(["-c", "invalid", "-s", "invalid"])
+        .args(["--lookback-slots", "150", "--lookback-secs", "60"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn test_signature_paging_stop() {
+    // Without a window, paging stops once enough signatures are found
+    assert_eq!(signature_paging_stop(49, 50, None), None);
+    assert_eq!(signature_paging_stop(50, 50, None), Some(PagingStop::Count));
+
+    // With a window, paging stops at its start
+    assert_eq!(signature_paging_stop(10, 1000, Some(false)), None);
+    assert_eq!(
+        signature_paging_stop(10, 1000, Some(true)),
+        Some(PagingStop::WindowEnd)
+    );
+    assert_eq!(
+        signature_paging_stop(1000, 1000, Some(true)),
+        Some(PagingStop::WindowEnd)
+    );
+
+    // or once the limit is reached before it
+    assert_eq!(
+        signature_paging_stop(1000, 1000, Some(false)),
+        Some(PagingStop::Cap)
+    );
+    assert_eq!(
+        signature_paging_stop(1500, 1000, Some(true)),
+        Some(PagingStop::Cap)
+    );
+}
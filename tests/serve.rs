@@ -0,0 +1,159 @@
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Child, Command},
+    thread,
+    time::Duration,
+};
+
+/// Kills the server when the test ends.
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+    }
+}
+
+/// Sends a request to the server and returns the status code and body.
+fn send(
+    port: u16,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> Result<(u16, String), Box<dyn std::error::Error>> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )?;
+    read_response(stream)
+}
+
+/// Sends a request with a chunked body to the server and returns the status code and body.
+fn send_chunked(
+    port: u16,
+    path: &str,
+    chunks: &[&str],
+) -> Result<(u16, String), Box<dyn std::error::Error>> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n"
+    )?;
+    for chunk in chunks {
+        write!(stream, "{:x}\r\n{chunk}\r\n", chunk.len())?;
+    }
+    write!(stream, "0\r\n\r\n")?;
+    read_response(stream)
+}
+
+/// Reads the status code and body of a response.
+fn read_response(mut stream: TcpStream) -> Result<(u16, String), Box<dyn std::error::Error>> {
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response
+        .split(' ')
+        .nth(1)
+        .ok_or("missing status")?
+        .parse()?;
+    let (_, body) = response.split_once("\r\n\r\n").ok_or("missing body")?;
+    Ok((status, body.to_owned()))
+}
+
+/// Sends a request to the server and returns the status code and JSON body.
+fn request(
+    port: u16,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> Result<(u16, serde_json::Value), Box<dyn std::error::Error>> {
+    let (status, body) = send(port, method, path, body)?;
+    Ok((status, serde_json::from_str(&body)?))
+}
+
+#[test]
+fn test_serve_scan_jobs() -> Result<(), Box<dyn std::error::Error>> {
+    let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let _server = Server(
+        Command::new(assert_cmd::cargo::cargo_bin("solana-copy-trade-detect"))
+            .args(["serve", "--bind", &format!("127.0.0.1:{port}")])
+            .args(["-c", "invalid", "-s", "invalid", "-d", "0"])
+            .spawn()?,
+    );
+
+    // Wait for the server to listen
+    let mut health = None;
+    for _ in 0..50 {
+        if let Ok(response) = request(port, "GET", "/health", "") {
+            health = Some(response);
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(health.ok_or("server did not start")?.0, 200);
+
+    let (status, body) = request(port, "POST", "/scans", r#"{"wallet": "not a wallet"}"#)?;
+    assert_eq!(status, 400);
+    assert!(body["error"].is_string());
+
+    // Chunked bodies are read up to the size limit
+    let (status, body) = send_chunked(port, "/scans", &[r#"{"wallet": "#, r#""not a wallet"}"#])?;
+    assert_eq!(status, 400);
+    assert!(body.contains("Invalid wallet address"));
+    let padding = " ".repeat(16 * 1024);
+    let (status, _) = send_chunked(port, "/scans", &[padding.as_str(); 5])?;
+    assert_eq!(status, 413);
+
+    let (status, _) = request(port, "GET", "/scans/42", "")?;
+    assert_eq!(status, 404);
+
+    let (status, job) = request(
+        port,
+        "POST",
+        "/scans",
+        r#"{"wallet": "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi", "swap_num": 5}"#,
+    )?;
+    assert_eq!(status, 202);
+    assert_eq!(
+        job["wallet"],
+        "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi"
+    );
+    let path = format!("/scans/{}", job["id"]);
+
+    // The API keys are invalid, so the job fails
+    let mut job = job;
+    for _ in 0..100 {
+        if job["status"] == "failed" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        job = request(port, "GET", &path, "")?.1;
+    }
+    assert_eq!(job["status"], "failed");
+    assert!(job["error"].is_string());
+
+    let (status, _) = request(port, "GET", &format!("{path}/result"), "")?;
+    assert_eq!(status, 409);
+
+    let (status, metrics) = send(port, "GET", "/metrics", "")?;
+    assert_eq!(status, 200);
+    assert!(metrics.contains("# TYPE copytrade_upstream_requests_total counter"));
+    assert!(metrics.contains("copytrade_upstream_requests_total{provider=\"cielo\""));
+    assert!(metrics.contains("copytrade_swaps_scanned_total 0"));
+    Ok(())
+}
+
+#[test]
+fn test_serve_rejects_zero_limits() -> Result<(), Box<dyn std::error::Error>> {
+    for limit in ["--max-concurrent", "--max-queued"] {
+        let output = Command::new(assert_cmd::cargo::cargo_bin("solana-copy-trade-detect"))
+            .args(["serve", "-c", "invalid", "-s", "invalid", limit, "0"])
+            .output()?;
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr)?;
+        assert!(stderr.contains("invalid value '0'"));
+    }
+    Ok(())
+}
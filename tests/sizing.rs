@@ -0,0 +1,44 @@
+use solana_copy_trade_detect::{size_correlation, PrevBuy, SizePattern};
+
+fn buy(sol_amount: f64, target_sol_amount: f64) -> PrevBuy {
+    PrevBuy {
+        tx_hash: format!("{}-{}", sol_amount, target_sol_amount),
+        slot: 300_000_000,
+        block_diff: 1,
+        mint: "mint".to_owned(),
+        sol_amount,
+        token_amount: sol_amount * 1_000.0,
+        target_sol_amount,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_fixed_size() {
+    let sizing = size_correlation(&[buy(1.0, 0.5), buy(3.0, 0.5), buy(0.2, 0.5)]).unwrap();
+    assert_eq!(sizing.pattern, SizePattern::FixedSize);
+    assert_eq!(sizing.pairs, 3);
+    assert_eq!(sizing.avg_target_sol, 0.5);
+    assert_eq!(sizing.target_size_cv, 0.0);
+}
+
+#[test]
+fn test_proportional_size() {
+    let sizing = size_correlation(&[buy(1.0, 0.1), buy(3.0, 0.3), buy(0.5, 0.05)]).unwrap();
+    assert_eq!(sizing.pattern, SizePattern::Proportional);
+    assert!((sizing.avg_ratio - 0.1).abs() < 1e-9);
+    assert!(sizing.ratio_cv < 1e-9);
+}
+
+#[test]
+fn test_uncorrelated_size() {
+    let sizing = size_correlation(&[buy(1.0, 0.1), buy(1.0, 2.0), buy(3.0, 0.5)]).unwrap();
+    assert_eq!(sizing.pattern, SizePattern::Uncorrelated);
+}
+
+#[test]
+fn test_too_few_sizes() {
+    // Buys with an unknown size on either side are left out
+    assert!(size_correlation(&[buy(1.0, 0.5), buy(0.0, 0.5), buy(1.0, 0.0)]).is_none());
+    assert!(size_correlation(&[]).is_none());
+}
@@ -0,0 +1,60 @@
+use assert_cmd::Command;
+
+mod common;
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+
+#[test]
+fn test_store_records_failed_scan() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("store");
+    let db = dir.join("scans.db");
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.args(["batch", "-c", "invalid", "-s", "invalid", "-d", "0"])
+        .args(["--swap-num", "7", "--mev-check", "--max-token-age", "600"])
+        .args(["--since", "slot:100"])
+        .arg("--output-dir")
+        .arg(&dir)
+        .arg("--db")
+        .arg(&db)
+        .write_stdin(format!("{WALLET}\n"));
+    cmd.assert().success();
+
+    let conn = rusqlite::Connection::open(&db)?;
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    assert_eq!(version, 1);
+
+    let (wallet, swap_num, mev_check, params, error): (String, u32, bool, String, Option<String>) =
+        conn.query_row(
+            "SELECT wallet, swap_num, mev_check, params, error FROM scans",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
+        )?;
+    assert_eq!(wallet, WALLET);
+    assert_eq!(swap_num, 7);
+    assert!(mev_check);
+    assert!(error.is_some());
+
+    // Every parameter is recorded, but not the API keys
+    let params: serde_json::Value = serde_json::from_str(&params)?;
+    assert_eq!(params["swap_num"], 7);
+    assert_eq!(params["since"], "slot:100");
+    assert_eq!(params["until"], serde_json::Value::Null);
+    assert_eq!(params["max_token_age"], 600);
+    assert_eq!(params["leader_pnl"], false);
+    assert!(params.get("cielo_api_key").is_none());
+    assert!(params.get("rpc_url").is_none());
+
+    let candidates: i64 =
+        conn.query_row("SELECT COUNT(*) FROM candidates", [], |row| row.get(0))?;
+    assert_eq!(candidates, 0);
+    Ok(())
+}
@@ -0,0 +1,167 @@
+use std::{str::FromStr, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use solana_copy_trade_detect::{MintHistory, StreamEvent, SwapStream};
+use solana_sdk::pubkey::Pubkey;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+const WALLET: &str = "7Ppgch9d4XRAygVNJP4bDkc7V6htYXGfghX4zzG9r4cH";
+const SIGNATURE: &str =
+    "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+/// Starts a mock PubSub server answering subscriptions with canned notifications.
+async fn mock_server() -> Result<String, Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("ws://{}", listener.local_addr()?);
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+
+        while let Some(Ok(message)) = ws.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            let request: Value = serde_json::from_str(&text).unwrap();
+            let id = request["id"].clone();
+            let method = request["method"].as_str().unwrap().to_owned();
+
+            let (result, notifications) = match method.as_str() {
+                "logsSubscribe" => (
+                    json!(7),
+                    vec![
+                        logs_notification(
+                            "Failed",
+                            json!({"InstructionError": [0, {"Custom": 1}]}),
+                        ),
+                        logs_notification(SIGNATURE, Value::Null),
+                    ],
+                ),
+                "signatureSubscribe" => (
+                    json!(8),
+                    vec![json!({
+                        "jsonrpc": "2.0",
+                        "method": "signatureNotification",
+                        "params": {
+                            "result": {"context": {"slot": 6}, "value": {"err": null}},
+                            "subscription": 8
+                        }
+                    })],
+                ),
+                _ => (json!(true), Vec::new()),
+            };
+
+            let response = json!({"jsonrpc": "2.0", "result": result, "id": id});
+            ws.send(Message::Text(response.to_string())).await.unwrap();
+            for notification in notifications {
+                ws.send(Message::Text(notification.to_string()))
+                    .await
+                    .unwrap();
+            }
+        }
+    });
+
+    Ok(url)
+}
+
+/// Builds a `logsNotification` message.
+fn logs_notification(signature: &str, err: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "logsNotification",
+        "params": {
+            "result": {
+                "context": {"slot": 5},
+                "value": {"signature": signature, "err": err, "logs": []}
+            },
+            "subscription": 7
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_stream_wallet_notifications() -> Result<(), Box<dyn std::error::Error>> {
+    let url = mock_server().await?;
+    let wallet = Pubkey::from_str(WALLET)?;
+
+    let mut stream = SwapStream::connect(&url).await?;
+    stream.watch_wallet(wallet);
+
+    let event = tokio::time::timeout(Duration::from_secs(5), stream.next()).await?;
+    assert_eq!(
+        event,
+        Some(StreamEvent::Wallet {
+            wallet,
+            signature: SIGNATURE.to_owned(),
+            slot: 5,
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_confirm_signature() -> Result<(), Box<dyn std::error::Error>> {
+    let url = mock_server().await?;
+
+    let stream = SwapStream::connect(&url).await?;
+    let confirmed = stream.confirm(SIGNATURE, Duration::from_secs(5)).await?;
+
+    assert!(confirmed);
+    Ok(())
+}
+
+#[test]
+fn test_mint_history_signatures_before() {
+    let history = MintHistory::default();
+    for (slot, signature) in ["a", "b", "c", "d"].into_iter().enumerate() {
+        history.record(WALLET, signature, slot as u64);
+    }
+
+    assert_eq!(
+        history.signatures_before(WALLET, "d", 2),
+        Some(vec!["c".to_owned(), "b".to_owned()])
+    );
+    assert_eq!(
+        history.signatures_before(WALLET, "d", 3),
+        Some(vec!["c".to_owned(), "b".to_owned(), "a".to_owned()])
+    );
+    // Older transactions may predate the history
+    assert_eq!(history.signatures_before(WALLET, "c", 3), None);
+    assert_eq!(history.signatures_before(WALLET, "e", 1), None);
+    assert_eq!(history.signatures_before(SIGNATURE, "d", 1), None);
+}
+
+#[test]
+fn test_mint_history_cap() {
+    let history = MintHistory::default();
+    for slot in 0..501 {
+        history.record(WALLET, &slot.to_string(), slot);
+    }
+
+    // The oldest signature was dropped to make room for the newest
+    assert_eq!(history.signatures_before(WALLET, "500", 500), None);
+    let signatures = history.signatures_before(WALLET, "500", 499).unwrap();
+    assert_eq!(signatures.first().map(String::as_str), Some("499"));
+    assert_eq!(signatures.last().map(String::as_str), Some("1"));
+}
+
+#[test]
+fn test_mint_history_sweep() {
+    let history = MintHistory::default();
+    history.record(WALLET, "a", 100);
+    history.record(WALLET, "b", 200);
+    history.record(SIGNATURE, "c", 100);
+    history.record(SIGNATURE, "d", 101);
+
+    // Mints are kept while their latest transaction is within 9000 slots
+    history.sweep(9_101);
+    assert!(history.signatures_before(WALLET, "b", 1).is_some());
+    assert!(history.signatures_before(SIGNATURE, "d", 1).is_none());
+
+    history.sweep(9_199);
+    assert!(history.signatures_before(WALLET, "b", 1).is_some());
+    history.sweep(9_200);
+    assert!(history.signatures_before(WALLET, "b", 1).is_none());
+}
@@ -0,0 +1,135 @@
+use solana_copy_trade_detect::{
+    group_by_token, launch_page_outcome, parse_metadata, LaunchPage, LaunchPlatform,
+    RepeatingWallet, TokenLaunch,
+};
+
+/// Encodes a string the way the Metaplex metadata account stores it, padded with null bytes.
+fn padded(text: &str, len: usize) -> Vec<u8> {
+    let mut bytes = (len as u32).to_le_bytes().to_vec();
+    bytes.extend(text.as_bytes());
+    bytes.resize(4 + len, 0);
+    bytes
+}
+
+#[test]
+fn test_parse_metadata() {
+    let mut data = vec![4];
+    data.extend([1; 32]); // Update authority
+    data.extend([2; 32]); // Mint
+    data.extend(padded("Bonk", 32));
+    data.extend(padded("BONK", 10));
+    data.extend(padded("https://example.com/bonk.json", 200));
+
+    assert_eq!(
+        parse_metadata(&data),
+        Some(("Bonk".to_owned(), "BONK".to_owned()))
+    );
+    // Truncated accounts are not metadata
+    assert_eq!(parse_metadata(&data[..80]), None);
+    assert_eq!(parse_metadata(&[]), None);
+}
+
+#[test]
+fn test_group_by_token() {
+    let leaders: Vec<RepeatingWallet> = serde_json::from_value(serde_json::json!([
+        {"wallet": "Leader1", "txs": [
+            {"tx_hash": "a", "block_diff": 0, "mint": "Mint1", "symbol": "ONE", "decimals": 6},
+            {"tx_hash": "b", "block_diff": 1, "mint": "Mint2", "symbol": "TWO"},
+        ]},
+        {"wallet": "Leader2", "txs": [
+            {"tx_hash": "c", "block_diff": 0, "mint": "Mint2", "symbol": "TWO"},
+            {"tx_hash": "d", "block_diff": 2, "mint": "Mint2", "symbol": "TWO"},
+        ]},
+        {"wallet": "Sandwicher", "sandwiches": [{
+            "target_tx_hash": "x", "front_run_tx_hash": "e", "back_run_tx_hash": "y", "slot": 0,
+        }], "txs": [
+            {"tx_hash": "e", "block_diff": 0, "mint": "Mint3"},
+            {"tx_hash": "f", "block_diff": 0, "mint": "Mint3"},
+        ]}
+    ]))
+    .unwrap();
+
+    let tokens = group_by_token(&leaders);
+    assert_eq!(tokens.len(), 2);
+    assert_eq!(tokens[0].mint, "Mint2");
+    assert_eq!(tokens[0].leaders, ["Leader1", "Leader2"]);
+    assert_eq!(tokens[0].copy_count, 3);
+    assert_eq!(tokens[1].symbol, "ONE");
+    assert_eq!(tokens[1].decimals, Some(6));
+}
+
+#[test]
+fn test_group_by_token_launch() {
+    let leaders: Vec<RepeatingWallet> = serde_json::from_value(serde_json::json!([
+        {"wallet": "Leader1", "txs": [
+            {"tx_hash": "a", "block_diff": 0, "mint": "Mint1", "token_created_slot": 100,
+             "token_age_secs": 30, "launch_platform": "pump_fun", "migrated": false},
+            {"tx_hash": "b", "block_diff": 1, "mint": "Mint2", "launch_platform": "raydium_launch_lab"},
+        ]}
+    ]))
+    .unwrap();
+    assert_eq!(leaders[0].txs[0].migrated, Some(false));
+    assert_eq!(leaders[0].txs[1].token_age_secs, None);
+
+    let tokens = group_by_token(&leaders);
+    assert_eq!(tokens[0].launch_platform, Some(LaunchPlatform::PumpFun));
+    assert_eq!(tokens[0].token_created_slot, Some(100));
+    assert_eq!(
+        tokens[1].launch_platform.unwrap().to_string(),
+        "raydium_launch_lab"
+    );
+}
+
+#[test]
+fn test_launch_page_outcome() {
+    // A short page ends the history, so its oldest signature created the mint
+    assert_eq!(
+        launch_page_outcome(false, Some(100), Some(500), 4),
+        LaunchPage::Creation
+    );
+    // A full page older than the window means the token is older than the limit
+    assert_eq!(
+        launch_page_outcome(true, Some(100), Some(500), 4),
+        LaunchPage::PastWindow
+    );
+    assert_eq!(
+        launch_page_outcome(true, Some(100), Some(500), 0),
+        LaunchPage::PastWindow
+    );
+    // Running out of pages inside the window leaves the age unknown
+    assert_eq!(
+        launch_page_outcome(true, Some(600), Some(500), 0),
+        LaunchPage::OutOfPages
+    );
+    assert_eq!(
+        launch_page_outcome(true, None, Some(500), 0),
+        LaunchPage::OutOfPages
+    );
+    assert_eq!(
+        launch_page_outcome(true, Some(600), Some(500), 4),
+        LaunchPage::Next
+    );
+    assert_eq!(
+        launch_page_outcome(true, Some(100), None, 4),
+        LaunchPage::Next
+    );
+}
+
+#[test]
+fn test_token_launch_age_at() {
+    let launch = TokenLaunch {
+        created_slot: 1,
+        created_at: Some(1_000),
+        platform: LaunchPlatform::PumpFun,
+    };
+    assert_eq!(launch.age_at(1_600), Some(600));
+    // Swaps timed before the creation count as age 0
+    assert_eq!(launch.age_at(900), Some(0));
+    assert_eq!(launch.age_at(i64::MIN), Some(0));
+
+    let unknown = TokenLaunch {
+        created_at: None,
+        ..launch
+    };
+    assert_eq!(unknown.age_at(1_600), None);
+}
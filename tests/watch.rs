@@ -0,0 +1,139 @@
+use assert_cmd::Command;
+use solana_copy_trade_detect::{AlertKind, PrevBuy, Sandwich, SwapAnalysis, WatchedWallet};
+use solana_sdk::pubkey::Pubkey;
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+
+/// Builds the analysis of a swap preceded by a buy of each leader.
+fn analysis(leaders: &[&str]) -> SwapAnalysis {
+    SwapAnalysis {
+        prev_buys: leaders
+            .iter()
+            .map(|leader| {
+                (
+                    leader.to_string(),
+                    PrevBuy {
+                        block_diff: 2,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect(),
+        sandwiches: Vec::new(),
+    }
+}
+
+#[test]
+fn test_watched_wallet_skips_seen_swaps() {
+    let mut target = WatchedWallet::new(WALLET.parse().unwrap());
+    assert!(!target.is_seen("tx1"));
+
+    // A swap returned again by the next poll is not counted twice
+    target.record("tx1", &analysis(&["leader"]), 3, None);
+    target.record("tx1", &analysis(&["leader"]), 3, None);
+    assert!(target.is_seen("tx1"));
+    target.record("tx2", &analysis(&["leader"]), 3, None);
+
+    let stats = target.stats();
+    assert_eq!(stats.wallet, WALLET);
+    assert_eq!(stats.swaps_seen, 2);
+    assert_eq!(stats.leaders.len(), 1);
+    assert_eq!(stats.leaders[0].copy_count, 2);
+    assert_eq!(stats.leaders[0].avg_block_diff, 2.0);
+
+    // Swaps that failed to be analysed are skipped without being counted
+    target.mark_seen("malformed");
+    assert!(target.is_seen("malformed"));
+    assert_eq!(target.stats().swaps_seen, 2);
+}
+
+#[test]
+fn test_watched_wallet_forgets_oldest_swaps() {
+    let mut target = WatchedWallet::new(Pubkey::new_unique());
+    for i in 0..1_001 {
+        target.record(&format!("tx{i}"), &analysis(&[]), 3, None);
+    }
+
+    assert!(!target.is_seen("tx0"));
+    assert!(target.is_seen("tx1"));
+    assert!(target.is_seen("tx1000"));
+    assert_eq!(target.stats().swaps_seen, 1_001);
+}
+
+#[test]
+fn test_watched_wallet_alerts_once_per_threshold() {
+    let mut target = WatchedWallet::new(WALLET.parse().unwrap());
+
+    // A single copy is not enough for a score alert, even at a score of 1
+    assert!(target
+        .record("tx1", &analysis(&["leader"]), 3, Some(0.5))
+        .is_empty());
+
+    let alerts = target.record("tx2", &analysis(&["leader"]), 3, Some(0.5));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertKind::Score);
+    assert_eq!(alerts[0].target, WALLET);
+    assert_eq!(alerts[0].leader, "leader");
+    assert_eq!(alerts[0].copy_count, 2);
+    assert_eq!(alerts[0].swaps_seen, 2);
+    assert_eq!(alerts[0].tx_hash, "tx2");
+
+    let alerts = target.record("tx3", &analysis(&["leader"]), 3, Some(0.5));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].kind, AlertKind::CopyCount);
+
+    assert!(target
+        .record("tx4", &analysis(&["leader"]), 3, Some(0.5))
+        .is_empty());
+}
+
+#[test]
+fn test_watched_wallet_ignores_mev_leaders() {
+    let mut target = WatchedWallet::new(Pubkey::new_unique());
+    let mut sandwiched = analysis(&["bot"]);
+    sandwiched.sandwiches.push((
+        "bot".to_owned(),
+        Sandwich {
+            target_tx_hash: "tx1".to_owned(),
+            front_run_tx_hash: "front".to_owned(),
+            back_run_tx_hash: "back".to_owned(),
+            slot: 1,
+        },
+    ));
+    target.record("tx1", &sandwiched, 2, None);
+
+    assert!(target
+        .record("tx2", &analysis(&["bot"]), 2, None)
+        .is_empty());
+    let stats = target.stats();
+    assert!(stats.leaders[0].mev);
+}
+
+#[test]
+fn test_alert_score_is_a_fraction() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["watch", "-w", WALLET, "-c", "invalid", "-s", "invalid"])
+        .args(["--alert-score", "1.5"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("invalid value '1.5'"));
+    assert!(stderr.contains("not between 0 and 1"));
+    Ok(())
+}
+
+#[test]
+fn test_alert_copies_is_positive() -> Result<(), Box<dyn std::error::Error>> {
+    let output = Command::cargo_bin("solana-copy-trade-detect")?
+        .args(["watch", "-w", WALLET, "-c", "invalid", "-s", "invalid"])
+        .args(["--alert-copies", "0"])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("invalid value '0'"));
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+};
+
+use assert_cmd::Command;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+mod common;
+
+const WALLET: &str = "C8WtJP4YveQbza5k1otS7BNFQ6My4pjVwecApCEQCNQi";
+
+/// The headers and body of a received request.
+type Received = (Vec<String>, Vec<u8>);
+
+/// Starts a mock webhook endpoint answering every request with the given status.
+///
+/// Returns the endpoint URL and a handle yielding the headers and body of the first request.
+fn mock_endpoint(status: u16) -> (String, JoinHandle<Received>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_owned();
+            if line.is_empty() {
+                break;
+            }
+            headers.push(line);
+        }
+        let length = headers
+            .iter()
+            .find_map(|h| {
+                h.to_lowercase()
+                    .strip_prefix("content-length: ")
+                    .map(str::to_owned)
+            })
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        (headers, body)
+    });
+
+    (url, handle)
+}
+
+#[test]
+fn test_webhook_signed_batch_report() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("webhook-signed");
+    let (url, endpoint) = mock_endpoint(200);
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.args(["batch", "-c", "invalid", "-s", "invalid", "-d", "0"])
+        .arg("--output-dir")
+        .arg(&dir)
+        .args(["--webhook", &url, "--webhook-secret", "secret"])
+        .write_stdin(format!("{WALLET}\n"));
+    cmd.assert().success();
+
+    let (headers, body) = endpoint.join().unwrap();
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret")?;
+    mac.update(&body);
+    let expected: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    assert!(headers
+        .iter()
+        .any(|h| h.to_lowercase() == format!("x-signature-256: sha256={expected}")));
+
+    let notification: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(notification["event"], "batch");
+    assert_eq!(notification["targets"][0]["wallet"], WALLET);
+    Ok(())
+}
+
+#[test]
+fn test_webhook_dead_letter() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = common::temp_dir("webhook-dead-letter");
+    let dead_letter_file = dir.join("failed.jsonl");
+    let (url, endpoint) = mock_endpoint(500);
+
+    let mut cmd = Command::cargo_bin("solana-copy-trade-detect")?;
+    cmd.args(["batch", "-c", "invalid", "-s", "invalid", "-d", "0"])
+        .arg("--output-dir")
+        .arg(&dir)
+        .args(["--webhook", &format!("{url}?token=secret")])
+        .args(["--webhook-retries", "0"])
+        .arg("--dead-letter-file")
+        .arg(&dead_letter_file)
+        .write_stdin(format!("{WALLET}\n"));
+    cmd.assert().success();
+    endpoint.join().unwrap();
+
+    let content = fs::read_to_string(&dead_letter_file)?;
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 1);
+    let line: serde_json::Value = serde_json::from_str(lines[0])?;
+    assert_eq!(line["target"], "json");
+    // Only the scheme and host of the URL are saved
+    assert_eq!(line["url"], "http://127.0.0.1");
+    assert!(!lines[0].contains("secret"));
+    assert!(line["error"].as_str().unwrap_or_default().contains("500"));
+    assert_eq!(line["notification"]["event"], "batch");
+    Ok(())
+}